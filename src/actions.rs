@@ -1,40 +1,73 @@
+use crate::archive::{
+    ArchiveEntry, ArchiveFormat, BackendChoice, ChangeCheck, CompressionMethod, CreateOptions,
+    ExtractOptions, UpdateOptions, ZipEncryption,
+};
+use crate::compress::{self, Codec, CompressOptions, CompressReport};
+use crate::copy::{self, ConflictPolicy, CopyOptions, CopyReport};
+use crate::dupes::{self, DuplicateGroup, Resolution, ResolveReport};
 use crate::error;
-use rand::RngCore;
+use crate::hash::{self, HashAlgo, VerifyReport};
+use crate::shred::{self, Scheme, ShredOptions};
+use crate::split::{self, SplitMode};
+use crate::trash::{self, TrashItem};
 use std::fs;
 use std::io;
-use std::io::Write;
+use std::path::Path;
 use std::sync::{
     Arc,
     atomic::{AtomicBool, Ordering},
     mpsc::Sender,
 };
 
-/// Copy a file
+/// Copy a file or directory
 pub fn copy_file() {
-    println!("Enter source file:");
+    println!("Enter source file or directory:");
     let mut src = String::new();
     io::stdin().read_line(&mut src).unwrap();
     let src = src.trim();
 
-    println!("Enter destination file:");
+    println!("Enter destination:");
     let mut dst = String::new();
     io::stdin().read_line(&mut dst).unwrap();
     let dst = dst.trim();
 
-    match fs::copy(src, dst) {
-        Ok(_) => println!("File copied successfully."),
+    let options = CopyOptions {
+        policy: read_conflict_policy(),
+        ..CopyOptions::default()
+    };
+    match copy_file_noninteractive(src, dst, &options) {
+        Ok(report) => print_copy_report(&report),
         Err(e) => println!("Error copying file: {}", e),
     }
 }
 
-/// Delete a single file with confirmation
+/// Ask which conflict policy to use; defaults to overwrite.
+fn read_conflict_policy() -> ConflictPolicy {
+    println!("If a file already exists (skip, overwrite, newer, rename; default overwrite):");
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    ConflictPolicy::parse(&input).unwrap_or(ConflictPolicy::Overwrite)
+}
+
+/// Print the conflicts and errors collected by a copy, then its summary.
+pub fn print_copy_report(report: &CopyReport) {
+    for conflict in &report.conflicts {
+        println!("Conflict: {}", conflict);
+    }
+    for (path, e) in &report.errors {
+        println!("Error copying '{}': {}", path.display(), e);
+    }
+    println!("Copy finished: {}", report.summary());
+}
+
+/// Move a single file to the trash with confirmation
 pub fn delete_file() {
     println!("Enter the file name to delete:");
     let mut filename = String::new();
     io::stdin().read_line(&mut filename).unwrap();
     let filename = filename.trim();
 
-    println!("Are you sure you want to delete '{}'? (y/n)", filename);
+    println!("Move '{}' to the trash? (y/n)", filename);
 
     let mut input = String::new();
     io::stdin()
@@ -42,25 +75,22 @@ pub fn delete_file() {
         .expect("Failed to read input");
 
     match input.trim().to_lowercase().as_str() {
-        "y" | "yes" => match fs::remove_file(filename) {
-            Ok(_) => println!("File '{}' deleted successfully.", filename),
+        "y" | "yes" => match trash_noninteractive(filename) {
+            Ok(_) => println!("File '{}' moved to trash.", filename),
             Err(e) => error::handle_error(e, filename),
         },
         _ => println!("Delete cancelled."),
     }
 }
 
-/// Delete a directory with confirmation
+/// Move a directory to the trash with confirmation
 pub fn delete_directory() {
     println!("Enter the directory name to delete:");
     let mut dirname = String::new();
     io::stdin().read_line(&mut dirname).unwrap();
     let dirname = dirname.trim();
 
-    println!(
-        "Are you sure you want to delete directory '{}'? (y/n)",
-        dirname
-    );
+    println!("Move directory '{}' to the trash? (y/n)", dirname);
 
     let mut input = String::new();
     io::stdin()
@@ -68,22 +98,110 @@ pub fn delete_directory() {
         .expect("Failed to read input");
 
     match input.trim().to_lowercase().as_str() {
-        "y" | "yes" => match fs::remove_dir_all(dirname) {
-            Ok(_) => println!("Directory '{}' deleted successfully.", dirname),
+        "y" | "yes" => match trash_noninteractive(dirname) {
+            Ok(_) => println!("Directory '{}' moved to trash.", dirname),
             Err(e) => error::handle_error(e, dirname),
         },
         _ => println!("Delete cancelled."),
     }
 }
 
-/// Batch delete files with one confirmation prompt
+/// Permanently delete a file or directory, bypassing the trash
+pub fn permanent_delete() {
+    println!("Enter the file or directory to delete permanently:");
+    let mut target = String::new();
+    io::stdin().read_line(&mut target).unwrap();
+    let target = target.trim();
+
+    println!(
+        "Permanently delete '{}'? This cannot be undone. (y/n)",
+        target
+    );
+
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read input");
+
+    match input.trim().to_lowercase().as_str() {
+        "y" | "yes" => {
+            let result = if Path::new(target).is_dir() {
+                fs::remove_dir_all(target)
+            } else {
+                fs::remove_file(target)
+            };
+            match result {
+                Ok(_) => println!("'{}' permanently deleted.", target),
+                Err(e) => error::handle_error(e, target),
+            }
+        }
+        _ => println!("Delete cancelled."),
+    }
+}
+
+/// List the contents of the trash
+pub fn trash_list() {
+    match trash_list_noninteractive() {
+        Ok(items) if items.is_empty() => println!("Trash is empty."),
+        Ok(items) => {
+            for item in items {
+                println!(
+                    "{} | {} | deleted {} | {}",
+                    item.name,
+                    item.original_path.display(),
+                    item.deletion_date,
+                    if item.is_dir {
+                        "Directory".to_string()
+                    } else {
+                        format!("{} bytes", item.size)
+                    }
+                );
+            }
+        }
+        Err(e) => println!("Error reading trash: {}", e),
+    }
+}
+
+/// Restore an item from the trash to its original location
+pub fn trash_restore() {
+    println!("Enter the trash item name to restore (see 'Trash: List'):");
+    let mut name = String::new();
+    io::stdin().read_line(&mut name).unwrap();
+    let name = name.trim();
+
+    match trash_restore_noninteractive(name) {
+        Ok(path) => println!("Restored '{}' to {}", name, path.display()),
+        Err(e) => error::handle_error(e, name),
+    }
+}
+
+/// Permanently delete everything in the trash with confirmation
+pub fn trash_empty() {
+    println!("Permanently delete everything in the trash? (y/n)");
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+
+    match input.trim().to_lowercase().as_str() {
+        "y" | "yes" => match trash_empty_noninteractive() {
+            Ok(n) => println!("Emptied trash ({} items removed).", n),
+            Err(e) => println!("Error emptying trash: {}", e),
+        },
+        _ => println!("Empty trash cancelled."),
+    }
+}
+
+/// Batch move files to the trash with one confirmation prompt
 pub fn batch_delete() {
     println!("Enter file names to delete (comma separated):");
     let mut input = String::new();
     io::stdin().read_line(&mut input).unwrap();
     let files: Vec<&str> = input.trim().split(',').map(|s| s.trim()).collect();
 
-    println!("You are about to delete {} files: {:?}", files.len(), files);
+    println!(
+        "You are about to move {} files to the trash: {:?}",
+        files.len(),
+        files
+    );
     println!("Are you sure? (y/n)");
 
     let mut confirm = String::new();
@@ -92,8 +210,8 @@ pub fn batch_delete() {
     match confirm.trim().to_lowercase().as_str() {
         "y" | "yes" => {
             for file in files {
-                match fs::remove_file(file) {
-                    Ok(_) => println!("Deleted '{}'", file),
+                match trash_noninteractive(file) {
+                    Ok(_) => println!("Moved '{}' to trash", file),
                     Err(e) => println!("Error deleting '{}': {}", file, e),
                 }
             }
@@ -120,7 +238,7 @@ pub fn rename_file() {
     }
 }
 
-/// Move a file (same as rename but with path change)
+/// Move a file or directory, across filesystems if needed
 pub fn move_file() {
     println!("Enter file to move:");
     let mut src = String::new();
@@ -132,7 +250,14 @@ pub fn move_file() {
     io::stdin().read_line(&mut dst).unwrap();
     let dst = dst.trim();
 
-    match fs::rename(src, dst) {
+    let cancel = AtomicBool::new(false);
+    let mut progress = |msg: String| println!("{}", msg);
+    match copy::move_path(
+        Path::new(src),
+        &move_target(src, dst),
+        &mut progress,
+        &cancel,
+    ) {
         Ok(_) => println!("File moved successfully."),
         Err(e) => println!("Error moving file: {}", e),
     }
@@ -161,6 +286,10 @@ pub fn batch_copy() {
         dst_dir,
         files
     );
+    let options = CopyOptions {
+        policy: read_conflict_policy(),
+        ..CopyOptions::default()
+    };
     println!("Are you sure? (y/n)");
 
     let mut confirm = String::new();
@@ -168,14 +297,14 @@ pub fn batch_copy() {
 
     match confirm.trim().to_lowercase().as_str() {
         "y" | "yes" => {
-            for file in files {
-                let filename = std::path::Path::new(file)
-                    .file_name()
-                    .map(|s| s.to_string_lossy().to_string())
-                    .unwrap_or_else(|| file.to_string());
-                let dest = std::path::Path::new(dst_dir).join(&filename);
-                match fs::copy(file, &dest) {
-                    Ok(_) => println!("Copied '{}' -> '{}'", file, dest.to_string_lossy()),
+            let paths: Vec<String> = files.iter().map(|f| f.to_string()).collect();
+            let results = batch_copy_noninteractive(&paths, dst_dir, &options);
+            for (file, res) in files.iter().zip(results) {
+                match res {
+                    Ok(report) => {
+                        println!("Copied '{}' -> '{}'", file, dst_dir);
+                        print_copy_report(&report);
+                    }
                     Err(e) => println!("Error copying '{}': {}", file, e),
                 }
             }
@@ -245,20 +374,32 @@ pub fn create_directory() {
     }
 }
 
-/// List archive contents via 7z CLI wrapper
+/// List archive contents
 pub fn archive_list_cli() {
     println!("Enter archive file path:");
     let mut path = String::new();
     io::stdin().read_line(&mut path).unwrap();
     let path = path.trim();
 
-    match crate::archive::list_archive(path) {
-        Ok(contents) => println!("Archive contents:\n{}", contents),
+    println!("Enter password (or press Enter for none):");
+    let mut password = String::new();
+    io::stdin().read_line(&mut password).unwrap();
+    let password = if password.trim().is_empty() {
+        None
+    } else {
+        Some(password.trim())
+    };
+
+    match crate::archive::list_archive(path, password, BackendChoice::Auto) {
+        Ok(entries) => println!(
+            "Archive contents:\n{}",
+            crate::archive::format_listing(&entries)
+        ),
         Err(e) => println!("Error listing archive: {}", e),
     }
 }
 
-/// Extract archive via 7z CLI wrapper
+/// Extract an archive
 pub fn archive_extract_cli() {
     println!("Enter archive file path:");
     let mut path = String::new();
@@ -279,13 +420,127 @@ pub fn archive_extract_cli() {
         Some(password.trim().to_string())
     };
 
-    match crate::archive::extract_archive(path, dest, password.as_deref()) {
+    println!("Enter entries or patterns to extract (comma-separated, or press Enter for all):");
+    let mut patterns = String::new();
+    io::stdin().read_line(&mut patterns).unwrap();
+
+    println!("Strip leading path components (or press Enter for 0):");
+    let mut strip = String::new();
+    io::stdin().read_line(&mut strip).unwrap();
+
+    let options = ExtractOptions {
+        patterns: patterns
+            .split(',')
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect(),
+        strip_components: strip.trim().parse().unwrap_or(0),
+        policy: read_conflict_policy(),
+        ..ExtractOptions::default()
+    };
+    let cancel = AtomicBool::new(false);
+    match crate::archive::extract_archive(
+        path,
+        dest,
+        password.as_deref(),
+        &options,
+        BackendChoice::Auto,
+        &mut |msg| println!("{}", msg),
+        &cancel,
+    ) {
         Ok(msg) => println!("Archive extracted successfully:\n{}", msg),
         Err(e) => println!("Error extracting archive: {}", e),
     }
 }
 
-/// Create archive via 7z CLI wrapper
+/// Add, replace and delete entries of an existing archive
+pub fn archive_update_cli() {
+    println!("Enter archive file path:");
+    let mut path = String::new();
+    io::stdin().read_line(&mut path).unwrap();
+    let path = path.trim();
+
+    println!("Enter files or directories to add (comma-separated, or press Enter for none):");
+    let mut sources_input = String::new();
+    io::stdin().read_line(&mut sources_input).unwrap();
+    let sources: Vec<&str> = sources_input
+        .trim()
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    println!("Enter entries or patterns to delete (comma-separated, or press Enter for none):");
+    let mut delete = String::new();
+    io::stdin().read_line(&mut delete).unwrap();
+
+    println!("Replace changed files by (mtime, hash, or press Enter for mtime):");
+    let mut compare = String::new();
+    io::stdin().read_line(&mut compare).unwrap();
+
+    println!("Enter password (or press Enter for none):");
+    let mut password = String::new();
+    io::stdin().read_line(&mut password).unwrap();
+    let password = if password.trim().is_empty() {
+        None
+    } else {
+        Some(password.trim())
+    };
+
+    let options = UpdateOptions {
+        compare: ChangeCheck::parse(&compare).unwrap_or_default(),
+        delete: delete
+            .split(',')
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect(),
+        ..UpdateOptions::default()
+    };
+    let cancel = AtomicBool::new(false);
+    match crate::archive::update_archive(
+        path,
+        &sources,
+        password,
+        &options,
+        BackendChoice::Auto,
+        &mut |msg| println!("{}", msg),
+        &cancel,
+    ) {
+        Ok(msg) => println!("Archive updated successfully:\n{}", msg),
+        Err(e) => println!("Error updating archive: {}", e),
+    }
+}
+
+/// Test an archive, printing the result for every entry
+pub fn archive_test_cli() {
+    println!("Enter archive file path:");
+    let mut path = String::new();
+    io::stdin().read_line(&mut path).unwrap();
+    let path = path.trim();
+
+    println!("Enter password (or press Enter for none):");
+    let mut password = String::new();
+    io::stdin().read_line(&mut password).unwrap();
+    let password = if password.trim().is_empty() {
+        None
+    } else {
+        Some(password.trim().to_string())
+    };
+
+    let cancel = AtomicBool::new(false);
+    match crate::archive::test_archive(
+        path,
+        password.as_deref(),
+        BackendChoice::Auto,
+        &mut |msg| println!("{}", msg),
+        &cancel,
+    ) {
+        Ok(msg) => println!("Archive is intact:\n{}", msg),
+        Err(e) => println!("Archive test failed: {}", e),
+    }
+}
+
+/// Create an archive
 pub fn archive_create_cli() {
     println!("Enter source file or directory (or comma-separated list):");
     let mut sources_input = String::new();
@@ -302,13 +557,18 @@ pub fn archive_create_cli() {
     io::stdin().read_line(&mut output).unwrap();
     let output = output.trim();
 
-    println!("Enter format (zip, 7z, tar, gz, bz2, or press Enter for zip):");
+    println!(
+        "Enter format (zip, tar, tar.gz, tar.bz2, tar.xz, tar.zst, 7z, or press Enter to use the output extension):"
+    );
     let mut format = String::new();
     io::stdin().read_line(&mut format).unwrap();
     let format = if format.trim().is_empty() {
-        Some("zip")
+        None
+    } else if let Some(f) = ArchiveFormat::parse(&format) {
+        Some(f)
     } else {
-        Some(format.trim())
+        println!("Unknown archive format '{}'", format.trim());
+        return;
     };
 
     println!("Enter password (or press Enter for none):");
@@ -320,111 +580,215 @@ pub fn archive_create_cli() {
         Some(password.trim())
     };
 
-    match crate::archive::create_archive(&sources, output, format, password) {
+    let mut encryption = String::new();
+    if password.is_some() {
+        println!("Enter zip encryption (aes256 or zipcrypto, or press Enter for aes256):");
+        io::stdin().read_line(&mut encryption).unwrap();
+    }
+
+    println!(
+        "Enter zip/7z compression method (store, deflate, bzip2, zstd, or press Enter for the default):"
+    );
+    let mut method = String::new();
+    io::stdin().read_line(&mut method).unwrap();
+
+    println!("Enter compression level (or press Enter for the default):");
+    let mut level = String::new();
+    io::stdin().read_line(&mut level).unwrap();
+
+    println!("Enter base directory the sources are relative to (or press Enter for none):");
+    let mut base_dir = String::new();
+    io::stdin().read_line(&mut base_dir).unwrap();
+    let base_dir = base_dir.trim();
+
+    println!("Enter patterns to exclude, comma-separated (e.g. target/, .git/, *.o):");
+    let mut exclude = String::new();
+    io::stdin().read_line(&mut exclude).unwrap();
+
+    let options = CreateOptions {
+        format,
+        method: CompressionMethod::parse(&method),
+        level: level.trim().parse().ok(),
+        encryption: ZipEncryption::parse(&encryption).unwrap_or_default(),
+        base_dir: (!base_dir.is_empty()).then(|| base_dir.into()),
+        exclude: exclude
+            .split(',')
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect(),
+    };
+    let cancel = AtomicBool::new(false);
+    match crate::archive::create_archive(
+        &sources,
+        output,
+        password,
+        &options,
+        BackendChoice::Auto,
+        &mut |msg| println!("{}", msg),
+        &cancel,
+    ) {
         Ok(msg) => println!("Archive created successfully:\n{}", msg),
         Err(e) => println!("Error creating archive: {}", e),
     }
 }
 
-/// Calculate hash of a file (SHA256 or BLAKE3)
+/// Calculate hash of a file
 pub fn calculate_hash() {
     println!("Enter file path:");
     let mut filepath = String::new();
     io::stdin().read_line(&mut filepath).unwrap();
     let filepath = filepath.trim();
 
-    println!("Enter hash algorithm (sha256 or blake3, default sha256):");
+    println!("Enter hash algorithm (sha256, blake3, sha1, sha512, md5, crc32; default sha256):");
     let mut algo = String::new();
     io::stdin().read_line(&mut algo).unwrap();
-    let algo = algo.trim();
+    let algo = if algo.trim().is_empty() {
+        "sha256"
+    } else {
+        algo.trim()
+    };
 
-    match std::fs::read(filepath) {
-        Ok(data) => {
-            let hash_str = if algo.eq_ignore_ascii_case("blake3") {
-                format!("BLAKE3: {}", blake3::hash(&data))
-            } else {
-                use sha2::Digest;
-                let mut hasher = sha2::Sha256::new();
-                hasher.update(&data);
-                format!("SHA256: {:x}", hasher.finalize())
-            };
-            println!("{}\n File: {}", hash_str, filepath);
+    match calculate_hash_noninteractive(filepath, algo) {
+        Ok(s) => println!("{}", s),
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Verify files against a sha256sum/b3sum-style checksum file
+pub fn verify_checksums() {
+    println!("Enter checksum file path:");
+    let mut sum_file = String::new();
+    io::stdin().read_line(&mut sum_file).unwrap();
+    let sum_file = sum_file.trim();
+
+    println!("Enter hash algorithm (leave empty to detect):");
+    let mut algo = String::new();
+    io::stdin().read_line(&mut algo).unwrap();
+    let algo = algo.trim();
+    let parsed = if algo.is_empty() {
+        None
+    } else {
+        match HashAlgo::parse(algo) {
+            Some(a) => Some(a),
+            None => {
+                println!("Unsupported hash algorithm '{}'", algo);
+                return;
+            }
         }
-        Err(e) => println!("Error reading file: {}", e),
+    };
+
+    let cancel = AtomicBool::new(false);
+    match hash::verify_checksum_file(
+        Path::new(sum_file),
+        parsed,
+        &mut |r| println!("{}", r),
+        &cancel,
+    ) {
+        Ok(report) => println!("{}", report.summary()),
+        Err(e) => println!("Error reading checksum file: {}", e),
     }
 }
 
-/// Find duplicate files in a directory by hash
+/// Find duplicate files under a directory and optionally resolve them
 pub fn find_duplicates() {
     println!("Enter directory path:");
     let mut dir = String::new();
     io::stdin().read_line(&mut dir).unwrap();
     let dir = dir.trim();
 
-    use std::collections::HashMap;
-    let mut hashes: HashMap<String, Vec<String>> = HashMap::new();
-
-    if let Ok(entries) = std::fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_file() {
-                if let Ok(data) = std::fs::read(&path) {
-                    let hash = blake3::hash(&data).to_hex().to_string();
-                    hashes
-                        .entry(hash)
-                        .or_insert_with(Vec::new)
-                        .push(path.to_string_lossy().to_string());
-                }
-            }
+    let groups = match find_duplicates_noninteractive(dir) {
+        Ok(g) => g,
+        Err(e) => {
+            println!("{}", e);
+            return;
         }
+    };
+    if groups.is_empty() {
+        println!("No duplicate files found.");
+        return;
     }
+    print_duplicate_groups(&groups);
 
-    let mut duplicates_found = false;
-    for (hash, files) in hashes.iter() {
-        if files.len() > 1 {
-            duplicates_found = true;
-            println!("\n=== Hash: {} ===", &hash[0..16]);
-            for file in files {
-                println!("  {}", file);
+    println!("Keep the first file of each group and delete, hardlink or symlink the rest?");
+    println!("Enter delete, hardlink, symlink or leave empty to do nothing:");
+    let mut choice = String::new();
+    io::stdin().read_line(&mut choice).unwrap();
+    if choice.trim().is_empty() {
+        return;
+    }
+    let Some(resolution) = Resolution::parse(&choice) else {
+        println!("Unknown action '{}'", choice.trim());
+        return;
+    };
+    for group in &groups {
+        match resolve_duplicates_noninteractive(group, 0, resolution) {
+            Ok(report) => {
+                for (path, e) in &report.errors {
+                    println!("  {}: {}", path.display(), e);
+                }
             }
+            Err(e) => println!("{}", e),
         }
     }
+    println!("Done.");
+}
 
-    if !duplicates_found {
-        println!("No duplicate files found.");
+pub fn print_duplicate_groups(groups: &[DuplicateGroup]) {
+    for (i, group) in groups.iter().enumerate() {
+        println!(
+            "=== Group {} ({} files, {} bytes each) ===",
+            i + 1,
+            group.files.len(),
+            group.size
+        );
+        for file in &group.files {
+            println!("  {}", file.display());
+        }
     }
 }
 
-/// Secure delete a file (overwrite with random data before deletion)
+/// Secure delete a file or directory tree (overwrite before deletion)
 pub fn secure_delete() {
-    println!("Enter file path to securely delete:");
+    println!("Enter file or directory path to securely delete:");
     let mut filepath = String::new();
     io::stdin().read_line(&mut filepath).unwrap();
     let filepath = filepath.trim();
 
-    if !std::path::Path::new(filepath).exists() {
-        println!("File not found: {}", filepath);
-        return;
-    }
-
-    // Overwrite with random data
-    use rand::RngCore;
-    if let Ok(metadata) = std::fs::metadata(filepath) {
-        let size = metadata.len() as usize;
-        let mut random_data = vec![0u8; size];
-        let mut rng = rand::thread_rng();
-        rng.fill_bytes(&mut random_data);
-
-        if let Err(e) = std::fs::write(filepath, random_data) {
-            println!("Error overwriting file: {}", e);
-            return;
+    println!("Enter scheme (zero, random, dod or a number of random passes; default random):");
+    let mut scheme = String::new();
+    io::stdin().read_line(&mut scheme).unwrap();
+    let scheme = if scheme.trim().is_empty() {
+        Scheme::Random
+    } else {
+        match Scheme::parse(&scheme) {
+            Some(s) => s,
+            None => {
+                println!("Unknown scheme '{}'", scheme.trim());
+                return;
+            }
         }
-    }
+    };
+
+    println!("Verify the last pass? (y/N):");
+    let mut verify = String::new();
+    io::stdin().read_line(&mut verify).unwrap();
+    let options = ShredOptions {
+        scheme,
+        verify: verify.trim().eq_ignore_ascii_case("y"),
+    };
 
-    // Delete the file
-    match std::fs::remove_file(filepath) {
-        Ok(_) => println!("File '{}' securely deleted.", filepath),
-        Err(e) => println!("Error deleting file: {}", e),
+    let cancel = AtomicBool::new(false);
+    match shred::shred_path(
+        Path::new(filepath),
+        &options,
+        &mut |msg| println!("{}", msg),
+        &cancel,
+    ) {
+        Ok(report) => println!(
+            "'{}' securely deleted ({} files, {} directories).",
+            filepath, report.files, report.dirs
+        ),
+        Err(e) => println!("Error during secure delete: {}", e),
     }
 }
 
@@ -435,30 +799,34 @@ pub fn split_file() {
     io::stdin().read_line(&mut filepath).unwrap();
     let filepath = filepath.trim();
 
-    println!("Enter chunk size in MB (default 100):");
+    println!("Enter chunk size in MB (default 100), or a part count like 'x4':");
     let mut size_input = String::new();
     io::stdin().read_line(&mut size_input).unwrap();
-    let chunk_size = size_input.trim().parse::<u64>().unwrap_or(100) * 1024 * 1024;
-
-    match std::fs::read(filepath) {
-        Ok(data) => {
-            let num_chunks = (data.len() as u64 + chunk_size - 1) / chunk_size;
-            for (i, chunk) in data.chunks(chunk_size as usize).enumerate() {
-                let out_path = format!("{}.{:03}", filepath, i);
-                match std::fs::write(&out_path, chunk) {
-                    Ok(_) => println!("Created: {} ({} bytes)", out_path, chunk.len()),
-                    Err(e) => println!("Error creating chunk: {}", e),
-                }
-            }
-            println!("Split complete: {} chunks", num_chunks);
-        }
-        Err(e) => println!("Error reading file: {}", e),
+    let size_input = size_input.trim();
+    let mode = match size_input.strip_prefix('x') {
+        Some(count) => SplitMode::Parts(count.parse::<u64>().unwrap_or(1)),
+        None => SplitMode::Size(size_input.parse::<u64>().unwrap_or(100) * 1024 * 1024),
+    };
+
+    let cancel = AtomicBool::new(false);
+    match split::split_file(
+        Path::new(filepath),
+        mode,
+        &mut |msg| println!("{}", msg),
+        &cancel,
+    ) {
+        Ok(manifest) => println!(
+            "Split complete: {} chunks, manifest {}",
+            manifest.parts.len(),
+            split::manifest_path(Path::new(filepath)).display()
+        ),
+        Err(e) => println!("Error splitting file: {}", e),
     }
 }
 
 /// Join split file chunks back together
 pub fn join_files() {
-    println!("Enter base file path (without .001, .002, etc):");
+    println!("Enter original file path (parts are <path>.000, <path>.001, ...):");
     let mut base = String::new();
     io::stdin().read_line(&mut base).unwrap();
     let base = base.trim();
@@ -468,37 +836,123 @@ pub fn join_files() {
     io::stdin().read_line(&mut output).unwrap();
     let output = output.trim();
 
-    use std::fs::File;
-    use std::io::Write;
-
-    match File::create(output) {
-        Ok(mut out_file) => {
-            let mut part_num = 0;
-            loop {
-                let part_path = format!("{}.{:03}", base, part_num);
-                match std::fs::read(&part_path) {
-                    Ok(data) => {
-                        if let Err(e) = out_file.write_all(&data) {
-                            println!("Error writing to output file: {}", e);
-                            return;
-                        }
-                        part_num += 1;
-                    }
-                    Err(_) => break,
-                }
-            }
-            println!("Joined {} parts into: {}", part_num, output);
-        }
-        Err(e) => println!("Error creating output file: {}", e),
+    let cancel = AtomicBool::new(false);
+    match split::join_files(
+        &split_base(base),
+        Path::new(output),
+        &mut |msg| println!("{}", msg),
+        &cancel,
+    ) {
+        Ok(n) => println!("Joined {} parts into: {}", n, output),
+        Err(e) => println!("Error joining files: {}", e),
+    }
+}
+
+/// Compress a single file to .gz, .bz2, .xz or .zst
+pub fn compress_file_cli() {
+    println!("Enter file path to compress:");
+    let mut filepath = String::new();
+    io::stdin().read_line(&mut filepath).unwrap();
+    let filepath = filepath.trim();
+
+    println!("Enter format (gz, bz2, xz, zst, or press Enter for gz):");
+    let mut format = String::new();
+    io::stdin().read_line(&mut format).unwrap();
+    let codec = if format.trim().is_empty() {
+        Codec::default()
+    } else if let Some(c) = Codec::parse(&format) {
+        c
+    } else {
+        println!("Unknown compression format '{}'", format.trim());
+        return;
+    };
+
+    let (low, high) = codec.levels();
+    println!(
+        "Enter compression level {}-{} (or press Enter for {}):",
+        low,
+        high,
+        codec.default_level()
+    );
+    let mut level = String::new();
+    io::stdin().read_line(&mut level).unwrap();
+
+    let options = CompressOptions {
+        codec,
+        level: level.trim().parse().ok(),
+        keep_original: prompt_keep_original(),
+    };
+    let cancel = AtomicBool::new(false);
+    match compress::compress_file(
+        Path::new(filepath),
+        &options,
+        &mut |msg| println!("{}", msg),
+        &cancel,
+    ) {
+        Ok(report) => println!("{}", compress_summary(filepath, &report)),
+        Err(e) => println!("Error compressing file: {}", e),
     }
 }
 
+/// Decompress a single .gz, .bz2, .xz or .zst file
+pub fn decompress_file_cli() {
+    println!("Enter file path to decompress:");
+    let mut filepath = String::new();
+    io::stdin().read_line(&mut filepath).unwrap();
+    let filepath = filepath.trim();
+
+    let keep_original = prompt_keep_original();
+    let cancel = AtomicBool::new(false);
+    match compress::decompress_file(
+        Path::new(filepath),
+        keep_original,
+        &mut |msg| println!("{}", msg),
+        &cancel,
+    ) {
+        Ok(report) => println!("{}", decompress_summary(filepath, &report)),
+        Err(e) => println!("Error decompressing file: {}", e),
+    }
+}
+
+fn prompt_keep_original() -> bool {
+    println!("Keep the original file? (y/N):");
+    let mut keep = String::new();
+    io::stdin().read_line(&mut keep).unwrap();
+    keep.trim().eq_ignore_ascii_case("y")
+}
+
+fn compress_summary(filepath: &str, report: &CompressReport) -> String {
+    format!(
+        "Compressed {} -> {} ({}, {} -> {} bytes, {}%)",
+        filepath,
+        report.output.display(),
+        report.codec,
+        report.input_size,
+        report.output_size,
+        report.ratio()
+    )
+}
+
+fn decompress_summary(filepath: &str, report: &CompressReport) -> String {
+    format!(
+        "Decompressed {} -> {} ({}, {} -> {} bytes)",
+        filepath,
+        report.output.display(),
+        report.codec,
+        report.input_size,
+        report.output_size
+    )
+}
+
 // --- Progress-capable worker helpers ---
-/// Archive extract with progress messages. Sends status updates to `tx`. Observes `cancel` flag.
+/// Archive extract with per-entry progress messages. Sends status updates to
+/// `tx`; setting `cancel` stops it and removes the files it had written.
 pub fn archive_extract_progress(
     path: &str,
     dest: &str,
     password: Option<&str>,
+    options: ExtractOptions,
+    backend: BackendChoice,
     tx: Sender<String>,
     cancel: Arc<AtomicBool>,
 ) {
@@ -507,35 +961,72 @@ pub fn archive_extract_progress(
         let _ = tx.send("Canceled before start".to_string());
         return;
     }
-    // prefer crate::archive which has fallbacks
-    match crate::archive::extract_archive(path, dest, password) {
+    let mut progress = |msg: String| {
+        let _ = tx.send(msg);
+    };
+    match crate::archive::extract_archive(
+        path,
+        dest,
+        password,
+        &options,
+        backend,
+        &mut progress,
+        &cancel,
+    ) {
         Ok(msg) => {
             let _ = tx.send(format!("Finished: {}", msg));
         }
+        Err(e) if cancel.load(Ordering::SeqCst) => {
+            let _ = tx.send(format!("Canceled: {}", e));
+        }
         Err(e) => {
             let _ = tx.send(format!("Error: {}", e));
         }
     }
 }
 
-/// Archive create with progress messages (simple starts/finishes).
-pub fn archive_create_progress(
-    sources: &[String],
-    output: &str,
-    format: Option<&str>,
+pub fn archive_test_progress(
+    path: &str,
     password: Option<&str>,
+    backend: BackendChoice,
     tx: Sender<String>,
     cancel: Arc<AtomicBool>,
 ) {
-    let _ = tx.send(format!("Starting archive create -> {}", output));
+    let _ = tx.send(format!("Starting test: {}", path));
+    let mut progress = |msg: String| {
+        let _ = tx.send(msg);
+    };
+    match crate::archive::test_archive(path, password, backend, &mut progress, &cancel) {
+        Ok(msg) => {
+            let _ = tx.send(format!("Finished: {}", msg));
+        }
+        Err(e) if cancel.load(Ordering::SeqCst) => {
+            let _ = tx.send(format!("Canceled: {}", e));
+        }
+        Err(e) => {
+            let _ = tx.send(format!("Error: {}", e));
+        }
+    }
+}
+
+/// Move with progress messages. Falls back to copy, verify, delete across filesystems.
+pub fn move_file_progress(src: &str, dst: &str, tx: Sender<String>, cancel: Arc<AtomicBool>) {
+    let _ = tx.send(format!("Starting move: {} -> {}", src, dst));
     if cancel.load(Ordering::SeqCst) {
         let _ = tx.send("Canceled before start".to_string());
         return;
     }
-    let src_refs: Vec<&str> = sources.iter().map(|s| s.as_str()).collect();
-    match crate::archive::create_archive(&src_refs, output, format, password) {
-        Ok(msg) => {
-            let _ = tx.send(format!("Finished: {}", msg));
+    let mut progress = |msg: String| {
+        let _ = tx.send(msg);
+    };
+    match copy::move_path(
+        Path::new(src),
+        &move_target(src, dst),
+        &mut progress,
+        &cancel,
+    ) {
+        Ok(_) => {
+            let _ = tx.send(format!("Finished: moved {} -> {}", src, dst));
         }
         Err(e) => {
             let _ = tx.send(format!("Error: {}", e));
@@ -543,280 +1034,634 @@ pub fn archive_create_progress(
     }
 }
 
-/// Find duplicates with incremental progress messages.
-pub fn find_duplicates_progress(dir: &str, tx: Sender<String>, cancel: Arc<AtomicBool>) {
-    use std::collections::HashMap;
-    let _ = tx.send(format!("Scanning directory: {}", dir));
-    if cancel.load(Ordering::SeqCst) {
-        let _ = tx.send("Canceled before scan".to_string());
+/// Move several files or directories into the directory `dst`. An item that
+/// fails is reported and skipped; the last message sums up the failures.
+pub fn batch_move_progress(
+    sources: &[String],
+    dst: &str,
+    tx: Sender<String>,
+    cancel: Arc<AtomicBool>,
+) {
+    let _ = tx.send(format!(
+        "Starting move of {} items -> {}",
+        sources.len(),
+        dst
+    ));
+    if !Path::new(dst).is_dir() {
+        let _ = tx.send(format!("Error: {} is not a directory", dst));
         return;
     }
-    let mut hashes: HashMap<String, Vec<String>> = HashMap::new();
-    let mut total = 0usize;
-    if let Ok(entries) = std::fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_file() {
-                total += 1;
-            }
+    let mut progress = |msg: String| {
+        let _ = tx.send(msg);
+    };
+    let mut failed = Vec::new();
+    for (i, src) in sources.iter().enumerate() {
+        if cancel.load(Ordering::SeqCst) {
+            progress(format!("Canceled after {} of {} items", i, sources.len()));
+            return;
+        }
+        progress(format!("Moving {}/{}: {}", i + 1, sources.len(), src));
+        if let Err(e) = copy::move_path(
+            Path::new(src),
+            &move_target(src, dst),
+            &mut progress,
+            &cancel,
+        ) {
+            progress(format!("Error moving {}: {}", src, e));
+            failed.push(src.as_str());
         }
     }
-    let _ = tx.send(format!("Found {} files, hashing...", total));
-    let mut processed = 0usize;
-    if let Ok(entries) = std::fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            if cancel.load(Ordering::SeqCst) {
-                let _ = tx.send("Canceled during hashing".to_string());
-                return;
-            }
-            let path = entry.path();
-            if path.is_file() {
-                processed += 1;
-                let pstr = path.to_string_lossy().to_string();
-                if let Ok(data) = std::fs::read(&path) {
-                    let hash = blake3::hash(&data).to_hex().to_string();
-                    hashes
-                        .entry(hash)
-                        .or_insert_with(Vec::new)
-                        .push(pstr.clone());
+    if failed.is_empty() {
+        let _ = tx.send(format!(
+            "Finished: moved {} items -> {}",
+            sources.len(),
+            dst
+        ));
+    } else {
+        let _ = tx.send(format!(
+            "Error: {} of {} items could not be moved: {}",
+            failed.len(),
+            sources.len(),
+            failed.join(", ")
+        ));
+    }
+}
+
+/// Where pasting `src` into `dst_dir` puts it: under its own name, or under a
+/// free "name (N)" when pasting it next to itself.
+pub fn paste_target(src: &str, dst_dir: &str) -> std::path::PathBuf {
+    let target = move_target(src, dst_dir);
+    match (Path::new(src).canonicalize(), target.canonicalize()) {
+        (Ok(a), Ok(b)) if a == b => copy::unique_name(&target),
+        _ => target,
+    }
+}
+
+/// Paste clipboard entries into `dst_dir`: copy them, or move them when they
+/// were cut. Conflicts and errors are reported as they happen.
+pub fn paste_progress(
+    sources: &[String],
+    dst_dir: &str,
+    cut: bool,
+    options: CopyOptions,
+    tx: Sender<String>,
+    cancel: Arc<AtomicBool>,
+) {
+    if cut {
+        return batch_move_progress(sources, dst_dir, tx, cancel);
+    }
+    let _ = tx.send(format!(
+        "Starting paste of {} items -> {}",
+        sources.len(),
+        dst_dir
+    ));
+    if !Path::new(dst_dir).is_dir() {
+        let _ = tx.send(format!("Error: {} is not a directory", dst_dir));
+        return;
+    }
+    let (mut copied, mut bytes, mut errors) = (0, 0, 0);
+    for (i, src) in sources.iter().enumerate() {
+        if cancel.load(Ordering::SeqCst) {
+            let _ = tx.send(format!("Canceled after {} of {} items", i, sources.len()));
+            return;
+        }
+        let _ = tx.send(format!("Copying {}/{}: {}", i + 1, sources.len(), src));
+        match copy::copy_recursive(Path::new(src), &paste_target(src, dst_dir), &options) {
+            Ok(report) => {
+                for conflict in &report.conflicts {
+                    let _ = tx.send(format!("Conflict: {}", conflict));
                 }
-                let _ = tx.send(format!(
-                    "Hashed {}/{}: {}",
-                    processed,
-                    total,
-                    path.display()
-                ));
+                for (path, e) in &report.errors {
+                    let _ = tx.send(format!("Error copying {}: {}", path.display(), e));
+                }
+                copied += report.files_copied;
+                bytes += report.bytes_copied;
+                errors += report.errors.len();
+            }
+            Err(e) => {
+                let _ = tx.send(format!("Error: {}: {}", src, e));
+                return;
             }
         }
     }
-    let mut groups = 0usize;
-    for (_h, files) in hashes.iter() {
-        if files.len() > 1 {
-            groups += 1;
-            let _ = tx.send(format!("Duplicate group ({} files)", files.len()));
+    if errors > 0 {
+        let _ = tx.send(format!("Error: {} files could not be copied", errors));
+    } else {
+        let _ = tx.send(format!(
+            "Finished: pasted {} items -> {} ({} files, {} bytes)",
+            sources.len(),
+            dst_dir,
+            copied,
+            bytes
+        ));
+    }
+}
+
+/// Hash a file with percentage progress messages.
+pub fn calculate_hash_progress(
+    filepath: &str,
+    algo: &str,
+    tx: Sender<String>,
+    cancel: Arc<AtomicBool>,
+) {
+    let _ = tx.send(format!("Starting hash: {}", filepath));
+    let Some(algo) = HashAlgo::parse(algo) else {
+        let _ = tx.send(format!("Error: unsupported hash algorithm '{}'", algo));
+        return;
+    };
+    let total = fs::metadata(filepath).map(|m| m.len()).unwrap_or(0);
+    let mut last_pct = 0;
+    let mut on_bytes = |done: u64| {
+        let pct = (done * 100).checked_div(total).unwrap_or(100);
+        if pct >= last_pct + 5 {
+            last_pct = pct;
+            let _ = tx.send(format!("Hashing {}: {}%", filepath, pct));
+        }
+    };
+    match hash::hash_file(Path::new(filepath), algo, &mut on_bytes, &cancel) {
+        Ok(digest) => {
+            let _ = tx.send(format!("Finished: {}: {}  {}", algo, digest, filepath));
+        }
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+            let _ = tx.send("Canceled during hashing".to_string());
+        }
+        Err(e) => {
+            let _ = tx.send(format!("Error: {}", e));
         }
     }
-    if groups == 0 {
-        let _ = tx.send("No duplicates found.".to_string());
-    } else {
-        let _ = tx.send(format!("Found {} duplicate groups", groups));
+}
+
+/// Verify a checksum file, sending one message per checked line.
+pub fn verify_checksums_progress(
+    sum_file: &str,
+    algo: Option<&str>,
+    tx: Sender<String>,
+    cancel: Arc<AtomicBool>,
+) {
+    let _ = tx.send(format!("Starting verify: {}", sum_file));
+    let algo = match algo.map(|a| (a, HashAlgo::parse(a))) {
+        Some((a, None)) => {
+            let _ = tx.send(format!("Error: unsupported hash algorithm '{}'", a));
+            return;
+        }
+        Some((_, parsed)) => parsed,
+        None => None,
+    };
+    let mut on_result = |r: &hash::VerifyResult| {
+        let _ = tx.send(r.to_string());
+    };
+    match hash::verify_checksum_file(Path::new(sum_file), algo, &mut on_result, &cancel) {
+        Ok(report) if report.all_ok() => {
+            let _ = tx.send(format!("Finished: {}", report.summary()));
+        }
+        Ok(report) => {
+            let _ = tx.send(format!("Error: {}", report.summary()));
+        }
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+            let _ = tx.send("Canceled during verify".to_string());
+        }
+        Err(e) => {
+            let _ = tx.send(format!("Error: {}", e));
+        }
+    }
+}
+
+/// Split with one progress message per part written.
+pub fn split_file_progress(
+    filepath: &str,
+    mode: SplitMode,
+    tx: Sender<String>,
+    cancel: Arc<AtomicBool>,
+) {
+    let _ = tx.send(format!("Starting split: {}", filepath));
+    let mut progress = |msg: String| {
+        let _ = tx.send(msg);
+    };
+    match split::split_file(Path::new(filepath), mode, &mut progress, &cancel) {
+        Ok(manifest) => {
+            let _ = tx.send(format!(
+                "Finished: split {} into {} parts",
+                filepath,
+                manifest.parts.len()
+            ));
+        }
+        Err(e) => {
+            let _ = tx.send(format!("Error: {}", e));
+        }
+    }
+}
+
+/// Verify parts against the manifest, then join them, with progress messages.
+pub fn join_files_progress(base: &str, output: &str, tx: Sender<String>, cancel: Arc<AtomicBool>) {
+    let _ = tx.send(format!("Starting join: {} -> {}", base, output));
+    let mut progress = |msg: String| {
+        let _ = tx.send(msg);
+    };
+    match split::join_files(&split_base(base), Path::new(output), &mut progress, &cancel) {
+        Ok(n) => {
+            let _ = tx.send(format!("Finished: joined {} parts into {}", n, output));
+        }
+        Err(e) => {
+            let _ = tx.send(format!("Error: {}", e));
+        }
     }
 }
 
-/// Secure delete with progress updates: overwrites file in chunks, then deletes.
-pub fn secure_delete_progress(filepath: &str, tx: Sender<String>, cancel: Arc<AtomicBool>) {
-    let _ = tx.send(format!("Secure deleting: {}", filepath));
+/// Compress one file with a progress message per percent read.
+pub fn compress_file_progress(
+    filepath: &str,
+    options: CompressOptions,
+    tx: Sender<String>,
+    cancel: Arc<AtomicBool>,
+) {
+    let _ = tx.send(format!("Starting compress: {}", filepath));
+    let mut progress = |msg: String| {
+        let _ = tx.send(msg);
+    };
+    match compress::compress_file(Path::new(filepath), &options, &mut progress, &cancel) {
+        Ok(report) => {
+            let _ = tx.send(format!("Finished: {}", compress_summary(filepath, &report)));
+        }
+        Err(e) => {
+            let _ = tx.send(format!("Error: {}", e));
+        }
+    }
+}
+
+/// Decompress one file with a progress message per percent read.
+pub fn decompress_file_progress(
+    filepath: &str,
+    keep_original: bool,
+    tx: Sender<String>,
+    cancel: Arc<AtomicBool>,
+) {
+    let _ = tx.send(format!("Starting decompress: {}", filepath));
+    let mut progress = |msg: String| {
+        let _ = tx.send(msg);
+    };
+    match compress::decompress_file(Path::new(filepath), keep_original, &mut progress, &cancel) {
+        Ok(report) => {
+            let _ = tx.send(format!(
+                "Finished: {}",
+                decompress_summary(filepath, &report)
+            ));
+        }
+        Err(e) => {
+            let _ = tx.send(format!("Error: {}", e));
+        }
+    }
+}
+
+/// Archive create with per-entry progress messages. A canceled or failed
+/// create leaves no partial archive behind.
+pub fn archive_create_progress(
+    sources: &[String],
+    output: &str,
+    password: Option<&str>,
+    options: CreateOptions,
+    backend: BackendChoice,
+    tx: Sender<String>,
+    cancel: Arc<AtomicBool>,
+) {
+    let _ = tx.send(format!("Starting archive create -> {}", output));
     if cancel.load(Ordering::SeqCst) {
         let _ = tx.send("Canceled before start".to_string());
         return;
     }
-    if !std::path::Path::new(filepath).exists() {
-        let _ = tx.send(format!("File not found: {}", filepath));
+    let src_refs: Vec<&str> = sources.iter().map(|s| s.as_str()).collect();
+    let mut progress = |msg: String| {
+        let _ = tx.send(msg);
+    };
+    match crate::archive::create_archive(
+        &src_refs,
+        output,
+        password,
+        &options,
+        backend,
+        &mut progress,
+        &cancel,
+    ) {
+        Ok(msg) => {
+            let _ = tx.send(format!("Finished: {}", msg));
+        }
+        Err(e) if cancel.load(Ordering::SeqCst) => {
+            let _ = tx.send(format!("Canceled: {}", e));
+        }
+        Err(e) => {
+            let _ = tx.send(format!("Error: {}", e));
+        }
+    }
+}
+
+pub fn archive_update_progress(
+    path: &str,
+    sources: &[String],
+    password: Option<&str>,
+    options: UpdateOptions,
+    backend: BackendChoice,
+    tx: Sender<String>,
+    cancel: Arc<AtomicBool>,
+) {
+    let _ = tx.send(format!("Starting archive update: {}", path));
+    if cancel.load(Ordering::SeqCst) {
+        let _ = tx.send("Canceled before start".to_string());
         return;
     }
-    if let Ok(metadata) = std::fs::metadata(filepath) {
-        let size = metadata.len() as usize;
-        let mut remaining = size;
-        let mut rng = rand::thread_rng();
-        let mut file = match std::fs::OpenOptions::new().write(true).open(filepath) {
-            Ok(f) => f,
-            Err(e) => {
-                let _ = tx.send(format!("Error opening file: {}", e));
-                return;
-            }
-        };
-        let chunk = 1024 * 1024; // 1MB
-        let mut written = 0usize;
-        while remaining > 0 {
-            if cancel.load(Ordering::SeqCst) {
-                let _ = tx.send("Canceled during overwrite".to_string());
-                return;
-            }
-            let write_size = std::cmp::min(chunk, remaining);
-            let mut buf = vec![0u8; write_size];
-            rng.fill_bytes(&mut buf);
-            if let Err(e) = file.write_all(&buf) {
-                let _ = tx.send(format!("Error writing: {}", e));
-                return;
-            }
-            remaining -= write_size;
-            written += write_size;
-            let pct = (written as f64 / size as f64) * 100.0;
-            let _ = tx.send(format!("Overwrite progress: {:.1}%", pct));
-        }
-        // ensure flush
-        let _ = file.flush();
-        // delete
-        match std::fs::remove_file(filepath) {
-            Ok(_) => {
-                let _ = tx.send("Secure delete complete".to_string());
-            }
-            Err(e) => {
-                let _ = tx.send(format!("Error deleting file: {}", e));
+    let src_refs: Vec<&str> = sources.iter().map(|s| s.as_str()).collect();
+    let mut progress = |msg: String| {
+        let _ = tx.send(msg);
+    };
+    match crate::archive::update_archive(
+        path,
+        &src_refs,
+        password,
+        &options,
+        backend,
+        &mut progress,
+        &cancel,
+    ) {
+        Ok(msg) => {
+            let _ = tx.send(format!("Finished: {}", msg));
+        }
+        Err(e) if cancel.load(Ordering::SeqCst) => {
+            let _ = tx.send(format!("Canceled: {}", e));
+        }
+        Err(e) => {
+            let _ = tx.send(format!("Error: {}", e));
+        }
+    }
+}
+
+/// Find duplicates with incremental progress messages. Returns the groups
+/// unless the search failed or was canceled.
+pub fn find_duplicates_progress(
+    dir: &str,
+    tx: Sender<String>,
+    cancel: Arc<AtomicBool>,
+) -> Option<Vec<DuplicateGroup>> {
+    let _ = tx.send(format!("Scanning directory: {}", dir));
+    let mut progress = |msg: String| {
+        let _ = tx.send(msg);
+    };
+    match dupes::find_duplicates(Path::new(dir), &mut progress, &cancel) {
+        Ok(groups) => {
+            if groups.is_empty() {
+                let _ = tx.send("Finished: no duplicates found.".to_string());
+            } else {
+                let wasted: u64 = groups.iter().map(DuplicateGroup::wasted).sum();
+                let _ = tx.send(format!(
+                    "Finished: found {} duplicate groups ({} bytes reclaimable)",
+                    groups.len(),
+                    wasted
+                ));
             }
+            Some(groups)
+        }
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+            let _ = tx.send("Canceled during duplicate search".to_string());
+            None
+        }
+        Err(e) => {
+            let _ = tx.send(format!("Error: {}", e));
+            None
+        }
+    }
+}
+
+/// Secure delete with progress updates: per pass for a file, per file for a directory.
+pub fn secure_delete_progress(
+    filepath: &str,
+    options: ShredOptions,
+    tx: Sender<String>,
+    cancel: Arc<AtomicBool>,
+) {
+    let _ = tx.send(format!(
+        "Secure deleting: {} ({})",
+        filepath,
+        options.scheme.label()
+    ));
+    let mut progress = |msg: String| {
+        let _ = tx.send(msg);
+    };
+    match shred::shred_path(Path::new(filepath), &options, &mut progress, &cancel) {
+        Ok(report) => {
+            let _ = tx.send(format!(
+                "Finished: securely deleted {} files and {} directories",
+                report.files, report.dirs
+            ));
+        }
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+            let _ = tx.send("Canceled during overwrite".to_string());
+        }
+        Err(e) => {
+            let _ = tx.send(format!("Error: {}", e));
         }
-    } else {
-        let _ = tx.send(format!("Unable to read metadata for {}", filepath));
     }
 }
 
 /// Archive helpers (non-interactive) - return Ok(String) on success or Err(String) on error
-pub fn archive_list_noninteractive(path: &str) -> Result<String, String> {
-    match crate::archive::list_archive(path) {
+pub fn archive_list_noninteractive(
+    path: &str,
+    password: Option<&str>,
+    backend: BackendChoice,
+) -> Result<Vec<ArchiveEntry>, String> {
+    match crate::archive::list_archive(path, password, backend) {
         Ok(contents) => Ok(contents),
-        Err(e) => Err(format!("{}", e)),
+        Err(e) => Err(e.to_string()),
     }
 }
 
-#[allow(dead_code)]
 pub fn archive_extract_noninteractive(
     path: &str,
     dest: &str,
     password: Option<&str>,
+    options: &ExtractOptions,
+    backend: BackendChoice,
 ) -> Result<String, String> {
-    match crate::archive::extract_archive(path, dest, password) {
+    let cancel = AtomicBool::new(false);
+    match crate::archive::extract_archive(
+        path,
+        dest,
+        password,
+        options,
+        backend,
+        &mut |_| {},
+        &cancel,
+    ) {
         Ok(msg) => Ok(msg),
-        Err(e) => Err(format!("{}", e)),
+        Err(e) => Err(e.to_string()),
     }
 }
 
-#[allow(dead_code)]
+pub fn archive_test_noninteractive(
+    path: &str,
+    password: Option<&str>,
+    backend: BackendChoice,
+) -> Result<String, String> {
+    let cancel = AtomicBool::new(false);
+    crate::archive::test_archive(path, password, backend, &mut |_| {}, &cancel)
+}
+
 pub fn archive_create_noninteractive(
     sources: &[String],
     output: &str,
-    format: Option<&str>,
     password: Option<&str>,
+    options: &CreateOptions,
+    backend: BackendChoice,
 ) -> Result<String, String> {
     let src_refs: Vec<&str> = sources.iter().map(|s| s.as_str()).collect();
-    match crate::archive::create_archive(&src_refs, output, format, password) {
+    let cancel = AtomicBool::new(false);
+    match crate::archive::create_archive(
+        &src_refs,
+        output,
+        password,
+        options,
+        backend,
+        &mut |_| {},
+        &cancel,
+    ) {
         Ok(msg) => Ok(msg),
-        Err(e) => Err(format!("{}", e)),
+        Err(e) => Err(e.to_string()),
     }
 }
 
+pub fn archive_update_noninteractive(
+    path: &str,
+    sources: &[String],
+    password: Option<&str>,
+    options: &UpdateOptions,
+    backend: BackendChoice,
+) -> Result<String, String> {
+    let src_refs: Vec<&str> = sources.iter().map(|s| s.as_str()).collect();
+    let cancel = AtomicBool::new(false);
+    crate::archive::update_archive(
+        path,
+        &src_refs,
+        password,
+        options,
+        backend,
+        &mut |_| {},
+        &cancel,
+    )
+}
+
 /// Calculate file hash (non-interactive)
 pub fn calculate_hash_noninteractive(filepath: &str, algo: &str) -> Result<String, String> {
-    match std::fs::read(filepath) {
-        Ok(data) => {
-            let hash_str = if algo.eq_ignore_ascii_case("blake3") {
-                format!("BLAKE3: {}", blake3::hash(&data))
-            } else {
-                use sha2::Digest;
-                let mut hasher = sha2::Sha256::new();
-                hasher.update(&data);
-                format!("SHA256: {:x}", hasher.finalize())
-            };
-            Ok(format!("{}\n File: {}", hash_str, filepath))
-        }
+    let algo =
+        HashAlgo::parse(algo).ok_or_else(|| format!("Unsupported hash algorithm '{}'", algo))?;
+    let cancel = AtomicBool::new(false);
+    match hash::hash_file(Path::new(filepath), algo, &mut |_| {}, &cancel) {
+        Ok(digest) => Ok(format!("{}: {}\n File: {}", algo, digest, filepath)),
         Err(e) => Err(format!("Error reading file: {}", e)),
     }
 }
 
-/// Find duplicates (non-interactive) - returns vector of duplicate groups
-#[allow(dead_code)]
-pub fn find_duplicates_noninteractive(dir: &str) -> Result<Vec<Vec<String>>, String> {
-    use std::collections::HashMap;
-    let mut hashes: HashMap<String, Vec<String>> = HashMap::new();
-
-    if let Ok(entries) = std::fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_file() {
-                if let Ok(data) = std::fs::read(&path) {
-                    let hash = blake3::hash(&data).to_hex().to_string();
-                    hashes
-                        .entry(hash)
-                        .or_insert_with(Vec::new)
-                        .push(path.to_string_lossy().to_string());
-                }
-            }
+/// Verify a checksum file (non-interactive). `algo` overrides detection.
+pub fn verify_checksums_noninteractive(
+    sum_file: &str,
+    algo: Option<&str>,
+) -> Result<VerifyReport, String> {
+    let algo = match algo {
+        Some(a) => {
+            Some(HashAlgo::parse(a).ok_or_else(|| format!("Unsupported hash algorithm '{}'", a))?)
         }
-    } else {
-        return Err(format!("Unable to read directory: {}", dir));
-    }
+        None => None,
+    };
+    let cancel = AtomicBool::new(false);
+    hash::verify_checksum_file(Path::new(sum_file), algo, &mut |_| {}, &cancel)
+        .map_err(|e| format!("Error reading checksum file: {}", e))
+}
 
-    let mut groups: Vec<Vec<String>> = Vec::new();
-    for (_hash, files) in hashes.into_iter() {
-        if files.len() > 1 {
-            groups.push(files);
-        }
-    }
-    Ok(groups)
+/// Find duplicates (non-interactive) - returns vector of duplicate groups
+pub fn find_duplicates_noninteractive(dir: &str) -> Result<Vec<DuplicateGroup>, String> {
+    let cancel = AtomicBool::new(false);
+    dupes::find_duplicates(Path::new(dir), &mut |_| {}, &cancel).map_err(|e| e.to_string())
+}
+
+/// Keep `group.files[keep]` and delete, hardlink or symlink the others (non-interactive)
+pub fn resolve_duplicates_noninteractive(
+    group: &DuplicateGroup,
+    keep: usize,
+    resolution: Resolution,
+) -> Result<ResolveReport, String> {
+    dupes::resolve_group(group, keep, resolution).map_err(|e| e.to_string())
 }
 
 /// Secure delete (non-interactive)
-#[allow(dead_code)]
-pub fn secure_delete_noninteractive(filepath: &str) -> Result<(), String> {
-    if !std::path::Path::new(filepath).exists() {
+pub fn secure_delete_noninteractive(filepath: &str, options: &ShredOptions) -> Result<(), String> {
+    if fs::symlink_metadata(filepath).is_err() {
         return Err(format!("File not found: {}", filepath));
     }
+    let cancel = AtomicBool::new(false);
+    shred::shred_path(Path::new(filepath), options, &mut |_| {}, &cancel)
+        .map(|_| ())
+        .map_err(|e| format!("Error during secure delete: {}", e))
+}
 
-    use rand::RngCore;
-    if let Ok(metadata) = std::fs::metadata(filepath) {
-        let size = metadata.len() as usize;
-        let mut random_data = vec![0u8; size];
-        let mut rng = rand::thread_rng();
-        rng.fill_bytes(&mut random_data);
+/// Split file (non-interactive). Returns the number of parts written.
+pub fn split_file_noninteractive(filepath: &str, mode: SplitMode) -> Result<usize, String> {
+    let cancel = AtomicBool::new(false);
+    split::split_file(Path::new(filepath), mode, &mut |_| {}, &cancel)
+        .map(|m| m.parts.len())
+        .map_err(|e| format!("Error splitting file: {}", e))
+}
 
-        if let Err(e) = std::fs::write(filepath, random_data) {
-            return Err(format!("Error overwriting file: {}", e));
-        }
-    }
+/// Join files (non-interactive). Returns the number of parts joined.
+pub fn join_files_noninteractive(base: &str, output: &str) -> Result<usize, String> {
+    let cancel = AtomicBool::new(false);
+    split::join_files(&split_base(base), Path::new(output), &mut |_| {}, &cancel)
+        .map_err(|e| format!("Error joining files: {}", e))
+}
 
-    match std::fs::remove_file(filepath) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("Error deleting file: {}", e)),
-    }
+/// Compress file (non-interactive). Returns a one-line summary.
+pub fn compress_file_noninteractive(
+    filepath: &str,
+    options: &CompressOptions,
+) -> Result<String, String> {
+    let cancel = AtomicBool::new(false);
+    compress::compress_file(Path::new(filepath), options, &mut |_| {}, &cancel)
+        .map(|report| compress_summary(filepath, &report))
+        .map_err(|e| format!("Error compressing file: {}", e))
 }
 
-/// Split file (non-interactive)
-pub fn split_file_noninteractive(filepath: &str, chunk_size_mb: u64) -> Result<usize, String> {
-    let chunk_size = chunk_size_mb * 1024 * 1024;
-    match std::fs::read(filepath) {
-        Ok(data) => {
-            let num_chunks = (data.len() as u64 + chunk_size - 1) / chunk_size;
-            for (i, chunk) in data.chunks(chunk_size as usize).enumerate() {
-                let out_path = format!("{}.{}", filepath, i);
-                match std::fs::write(&out_path, chunk) {
-                    Ok(_) => (),
-                    Err(e) => return Err(format!("Error creating chunk: {}", e)),
-                }
-            }
-            Ok(num_chunks as usize)
-        }
-        Err(e) => Err(format!("Error reading file: {}", e)),
-    }
+/// Decompress file (non-interactive). Returns a one-line summary.
+pub fn decompress_file_noninteractive(
+    filepath: &str,
+    keep_original: bool,
+) -> Result<String, String> {
+    let cancel = AtomicBool::new(false);
+    compress::decompress_file(Path::new(filepath), keep_original, &mut |_| {}, &cancel)
+        .map(|report| decompress_summary(filepath, &report))
+        .map_err(|e| format!("Error decompressing file: {}", e))
 }
 
-/// Join files (non-interactive)
-pub fn join_files_noninteractive(base: &str, output: &str) -> Result<usize, String> {
-    use std::fs::File;
-    use std::io::Write;
-
-    match File::create(output) {
-        Ok(mut out_file) => {
-            let mut part_num = 0;
-            loop {
-                let part_path = format!("{}.{}", base, part_num);
-                match std::fs::read(&part_path) {
-                    Ok(data) => {
-                        if let Err(e) = out_file.write_all(&data) {
-                            return Err(format!("Error writing to output file: {}", e));
-                        }
-                        part_num += 1;
-                    }
-                    Err(_) => break,
-                }
-            }
-            Ok(part_num)
-        }
-        Err(e) => Err(format!("Error creating output file: {}", e)),
-    }
+/// Accept the original file name, its manifest or its first part as the join base.
+fn split_base(base: &str) -> std::path::PathBuf {
+    let base = base
+        .strip_suffix(".manifest")
+        .or_else(|| base.strip_suffix(".000"))
+        .unwrap_or(base);
+    std::path::PathBuf::from(base)
 }
 
 // Non-interactive helper functions for GUI/backend integration
-pub fn copy_file_noninteractive(src: &str, dst: &str) -> Result<(), std::io::Error> {
-    fs::copy(src, dst).map(|_| ())
+
+/// Copy a file or a directory tree. Like `cp -r`, copying onto an existing
+/// directory places the source inside it. Collisions are resolved with
+/// `options.policy` and listed in the returned report.
+pub fn copy_file_noninteractive(
+    src: &str,
+    dst: &str,
+    options: &CopyOptions,
+) -> Result<CopyReport, std::io::Error> {
+    let src_path = Path::new(src);
+    let mut target = Path::new(dst).to_path_buf();
+    if target.is_dir()
+        && let Some(name) = src_path.file_name()
+    {
+        target = target.join(name);
+    }
+    copy::copy_recursive(src_path, &target, options)
 }
 
 pub fn delete_file_noninteractive(path: &str) -> Result<(), std::io::Error> {
@@ -831,8 +1676,20 @@ pub fn rename_file_noninteractive(old: &str, new: &str) -> Result<(), std::io::E
     fs::rename(old, new)
 }
 
+/// Move a file or directory. Moving onto an existing directory places the source
+/// inside it; moves across filesystems fall back to copy, verify, delete.
 pub fn move_file_noninteractive(src: &str, dst: &str) -> Result<(), std::io::Error> {
-    fs::rename(src, dst)
+    let cancel = AtomicBool::new(false);
+    copy::move_path(Path::new(src), &move_target(src, dst), &mut |_| {}, &cancel).map(|_| ())
+}
+
+/// Resolve `dst` like `mv`: an existing directory means "move into it".
+pub fn move_target(src: &str, dst: &str) -> std::path::PathBuf {
+    let target = Path::new(dst);
+    match Path::new(src).file_name() {
+        Some(name) if target.is_dir() => target.join(name),
+        _ => target.to_path_buf(),
+    }
 }
 
 pub fn create_file_noninteractive(path: &str) -> Result<(), std::io::Error> {
@@ -847,22 +1704,45 @@ pub fn create_directory_noninteractive(path: &str) -> Result<(), std::io::Error>
 }
 
 pub fn batch_delete_noninteractive(paths: &[String]) -> Vec<Result<(), std::io::Error>> {
-    paths.iter().map(|p| fs::remove_file(p)).collect()
+    paths.iter().map(fs::remove_file).collect()
+}
+
+/// Move a file or directory to the freedesktop.org trash.
+pub fn trash_noninteractive(path: &str) -> Result<TrashItem, std::io::Error> {
+    trash::move_to_trash(Path::new(path))
+}
+
+pub fn batch_trash_noninteractive(paths: &[String]) -> Vec<Result<TrashItem, std::io::Error>> {
+    paths.iter().map(|p| trash_noninteractive(p)).collect()
+}
+
+pub fn trash_list_noninteractive() -> Result<Vec<TrashItem>, std::io::Error> {
+    trash::list_trash()
+}
+
+/// Restore a trashed item by its trash name; returns the path it was restored to.
+pub fn trash_restore_noninteractive(name: &str) -> Result<std::path::PathBuf, std::io::Error> {
+    trash::restore(name)
+}
+
+pub fn trash_empty_noninteractive() -> Result<usize, std::io::Error> {
+    trash::empty_trash()
 }
 
-/// Batch copy helper for GUI: copy each path into destination directory.
+/// Batch copy helper for GUI: copy each path (file or directory) into destination directory.
 pub fn batch_copy_noninteractive(
     paths: &[String],
     dst_dir: &str,
-) -> Vec<Result<(), std::io::Error>> {
+    options: &CopyOptions,
+) -> Vec<Result<CopyReport, std::io::Error>> {
     let mut results = Vec::new();
     for p in paths.iter() {
-        let filename = std::path::Path::new(p)
+        let filename = Path::new(p)
             .file_name()
             .map(|s| s.to_string_lossy().to_string())
             .unwrap_or_else(|| p.clone());
-        let dest = std::path::Path::new(dst_dir).join(&filename);
-        results.push(fs::copy(p, &dest).map(|_| ()));
+        let dest = Path::new(dst_dir).join(&filename);
+        results.push(copy::copy_recursive(Path::new(p), &dest, options));
     }
     results
 }
@@ -1,232 +1,731 @@
-use std::fs::File;
-use std::io::{self, Read};
-use std::path::Path;
-use std::process::Command;
-
-// Added pure-Rust archive support as a fallback when `7z` is not available.
-use flate2::Compression;
-use flate2::read::GzDecoder;
-use flate2::write::GzEncoder;
-use tar::Archive as TarArchive;
-use tar::Builder as TarBuilder;
-use zip::ZipWriter;
-use zip::read::ZipArchive;
-use zip::write::FileOptions;
-
-/// Small wrapper around the 7-Zip CLI (`7z`) to provide basic archive operations.
-/// This keeps the implementation simple and calls the external `7z` binary when available.
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use walkdir::WalkDir;
+
+use crate::hash::{DigestWriter, HashAlgo};
+
+mod create;
+mod entry;
+mod extract;
+mod format;
+mod progress;
+mod sevenzip;
+mod tarball;
+mod update;
+mod verify;
+mod zipfile;
+
+pub use create::{CompressionMethod, CreateOptions, ZipEncryption};
+pub use entry::{ArchiveEntry, children, format_listing};
+pub use extract::{ExtractOptions, ExtractReport, PathSafety, glob_match};
+pub use format::ArchiveFormat;
+use progress::Progress;
+pub use update::{ChangeCheck, UpdateOptions, UpdateReport};
+pub use verify::TestReport;
+
+/// One way of reading and writing archives. Formats are detected before a
+/// backend is chosen, so implementations never guess from file names.
+pub trait ArchiveBackend {
+    fn name(&self) -> &'static str;
+
+    /// Whether `list` and `extract` handle `format`.
+    fn supports(&self, format: ArchiveFormat) -> bool;
+
+    /// Whether `create` can write `format`.
+    fn can_create(&self, format: ArchiveFormat) -> bool {
+        self.supports(format)
+    }
+
+    /// Whether `create` can write a password-protected `format` archive.
+    fn can_encrypt(&self, format: ArchiveFormat) -> bool;
+
+    /// Every entry, in archive order. `password` is only needed where the
+    /// entry names themselves are encrypted (7z with `-mhe=on`).
+    fn list(
+        &self,
+        archive: &Path,
+        format: ArchiveFormat,
+        password: Option<&str>,
+    ) -> io::Result<Vec<ArchiveEntry>>;
+
+    /// Extract the entries chosen by `options` into `dest`, recording what
+    /// is written in `progress` and stopping once it is canceled.
+    fn extract(
+        &self,
+        archive: &Path,
+        format: ArchiveFormat,
+        dest: &Path,
+        password: Option<&str>,
+        options: &ExtractOptions,
+        progress: &Progress,
+    ) -> io::Result<ExtractReport>;
+
+    /// Stream the contents of the file entry at `name` into `out` and return
+    /// the number of bytes written.
+    fn read_entry(
+        &self,
+        archive: &Path,
+        format: ArchiveFormat,
+        name: &str,
+        password: Option<&str>,
+        out: &mut dyn Write,
+    ) -> io::Result<u64>;
+
+    /// Read every file entry to the end, checking it against the archive's
+    /// own checksums, and record the result for each in the report. Only
+    /// what stops the whole test (an unreadable archive, cancellation) is
+    /// returned as an error.
+    fn test(
+        &self,
+        archive: &Path,
+        format: ArchiveFormat,
+        password: Option<&str>,
+        progress: &Progress,
+    ) -> io::Result<TestReport>;
+
+    /// Archive every source under its `stored_name` (directories
+    /// recursively, as [`walk_sources`] lists them), keeping permissions,
+    /// mtimes and symlinks. `sources` are already resolved against
+    /// `options.base_dir` and `options` has been checked against `format`.
+    fn create(
+        &self,
+        output: &Path,
+        format: ArchiveFormat,
+        sources: &[&Path],
+        password: Option<&str>,
+        options: &CreateOptions,
+        progress: &Progress,
+    ) -> io::Result<()>;
+
+    /// Bring `archive` up to date with `sources`: every entry `options`
+    /// neither deletes nor replaces is carried over, changed files replace
+    /// their entries in place and new ones are appended. The new version is
+    /// written with [`rewrite`], so a failed update leaves the archive as it was.
+    fn update(
+        &self,
+        archive: &Path,
+        format: ArchiveFormat,
+        sources: &[&Path],
+        password: Option<&str>,
+        options: &UpdateOptions,
+        progress: &Progress,
+    ) -> io::Result<UpdateReport>;
+}
+
+/// Which backend to use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BackendChoice {
+    /// The built-in backend when it can do the job, otherwise 7z.
+    #[default]
+    Auto,
+    /// Only the built-in pure-Rust backends.
+    Native,
+    /// Only the external `7z` CLI.
+    SevenZip,
+}
+
+impl BackendChoice {
+    pub const ALL: [BackendChoice; 3] = [
+        BackendChoice::Auto,
+        BackendChoice::Native,
+        BackendChoice::SevenZip,
+    ];
+
+    pub fn parse(s: &str) -> Option<BackendChoice> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Some(BackendChoice::Auto),
+            "native" | "builtin" | "built-in" => Some(BackendChoice::Native),
+            "7z" | "7zip" | "7-zip" => Some(BackendChoice::SevenZip),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BackendChoice::Auto => "Auto",
+            BackendChoice::Native => "Built-in",
+            BackendChoice::SevenZip => "7z",
+        }
+    }
+}
+
 pub fn is_7z_available() -> bool {
-    Command::new("7z").arg("--help").output().is_ok()
+    sevenzip::is_available()
 }
 
-pub fn list_archive(path: &str) -> Result<String, String> {
-    if !Path::new(path).exists() {
-        return Err(format!("Archive not found: {}", path));
+/// What the selected backend has to be able to do.
+#[derive(Clone, Copy)]
+enum Task {
+    Read,
+    Create { encrypted: bool },
+}
+
+fn native_backend(format: ArchiveFormat) -> Option<Box<dyn ArchiveBackend>> {
+    match format {
+        ArchiveFormat::Zip => Some(Box::new(zipfile::ZipBackend)),
+        ArchiveFormat::SevenZ => None,
+        _ => Some(Box::new(tarball::TarBackend)),
     }
-    if is_7z_available() {
-        let output = Command::new("7z")
-            .arg("l")
-            .arg(path)
-            .output()
-            .map_err(|e| e.to_string())?;
-        if output.status.success() {
-            Ok(String::from_utf8_lossy(&output.stdout).to_string())
-        } else {
-            Err(String::from_utf8_lossy(&output.stderr).to_string())
+}
+
+fn capable(backend: &dyn ArchiveBackend, format: ArchiveFormat, task: Task) -> bool {
+    match task {
+        Task::Read => backend.supports(format),
+        Task::Create { encrypted } => {
+            backend.can_create(format) && (!encrypted || backend.can_encrypt(format))
         }
-    } else {
-        // Fallback to pure-Rust listing for zip and tar formats
-        let ext = Path::new(path)
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-        if ext == "zip" {
-            let file = File::open(path).map_err(|e| e.to_string())?;
-            let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
-            let mut out = String::new();
-            for i in 0..archive.len() {
-                let f = archive.by_index(i).map_err(|e| e.to_string())?;
-                out.push_str(&format!("{}\n", f.name()));
+    }
+}
+
+fn select_backend(
+    choice: BackendChoice,
+    format: ArchiveFormat,
+    task: Task,
+) -> io::Result<Box<dyn ArchiveBackend>> {
+    let what = match task {
+        Task::Read => format!("read {} archives", format),
+        Task::Create { encrypted: false } => format!("create {} archives", format),
+        Task::Create { encrypted: true } => format!("create encrypted {} archives", format),
+    };
+    let unsupported = |who: &str| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} cannot {}", who, what),
+        )
+    };
+
+    if choice != BackendChoice::SevenZip
+        && let Some(native) = native_backend(format)
+        && capable(native.as_ref(), format, task)
+    {
+        return Ok(native);
+    }
+    if choice == BackendChoice::Native {
+        return Err(unsupported("the built-in backend"));
+    }
+    let seven = sevenzip::SevenZipBackend;
+    if !capable(&seven, format, task) {
+        return Err(unsupported("no available backend"));
+    }
+    if !sevenzip::is_available() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("the 7z command is needed to {} but was not found", what),
+        ));
+    }
+    Ok(Box::new(seven))
+}
+
+/// Name a source is stored under: its final path component, even for `.` or `..`.
+fn source_name(path: &Path) -> io::Result<String> {
+    std::path::absolute(path)?
+        .components()
+        .next_back()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .filter(|n| n != "." && n != ".." && n != "/")
+        .or_else(|| {
+            path.canonicalize()
+                .ok()?
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot archive '{}' without a name", path.display()),
+            )
+        })
+}
+
+/// A file, directory or symlink under one of the sources being archived.
+pub(super) struct SourceEntry {
+    /// Name it is stored under.
+    pub(super) name: String,
+    pub(super) path: PathBuf,
+    /// Metadata of the entry itself; symlinks are not followed.
+    pub(super) meta: fs::Metadata,
+}
+
+/// Everything under `sources` in the order it is archived: each source,
+/// then what lies under it sorted by name. Entries `options` excludes are
+/// left out, directories with everything in them. A source that is the
+/// base directory itself contributes only its contents.
+pub(super) fn walk_sources(
+    sources: &[&Path],
+    options: &CreateOptions,
+) -> io::Result<Vec<SourceEntry>> {
+    let mut entries = Vec::new();
+    for source in sources {
+        let root = stored_name(source, options.base())?;
+        let name_of = |path: &Path| {
+            let rel = path.strip_prefix(source).unwrap_or(Path::new(""));
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            match (root.is_empty(), rel.is_empty()) {
+                (_, true) => root.clone(),
+                (true, false) => rel,
+                (false, false) => format!("{}/{}", root, rel),
             }
-            Ok(out)
-        } else if ext == "tar" || ext == "gz" || path.ends_with(".tar.gz") || path.ends_with(".tgz")
-        {
-            let file = File::open(path).map_err(|e| e.to_string())?;
-            let reader: Box<dyn Read> =
-                if path.ends_with(".gz") || path.ends_with(".tgz") || path.ends_with(".tar.gz") {
-                    Box::new(GzDecoder::new(file))
-                } else {
-                    Box::new(file)
-                };
-            let mut archive = TarArchive::new(reader);
-            let mut out = String::new();
-            for entry in archive.entries().map_err(|e| e.to_string())? {
-                let e = entry.map_err(|e| e.to_string())?;
-                if let Ok(path) = e.path() {
-                    out.push_str(&format!("{}\n", path.display()));
-                }
+        };
+        let walk = WalkDir::new(source)
+            .follow_links(false)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| {
+                let name = name_of(e.path());
+                name.is_empty() || !options.excludes(&name, e.file_type().is_dir())
+            });
+        for entry in walk {
+            let entry = entry?;
+            let name = name_of(entry.path());
+            if name.is_empty() {
+                continue;
             }
-            Ok(out)
-        } else {
-            Err("Unsupported archive format and 7z not available".to_string())
+            entries.push(SourceEntry {
+                name,
+                meta: entry.metadata()?,
+                path: entry.into_path(),
+            });
         }
     }
+    Ok(entries)
+}
+
+/// Write a new version of `archive` into a temporary file beside it with
+/// `write`, then move that over `archive`, keeping its permissions if it
+/// exists. If `write` fails the temporary file is removed and `archive`, or
+/// its absence, is untouched.
+pub(super) fn rewrite<T>(
+    archive: &Path,
+    write: impl FnOnce(&Path) -> io::Result<T>,
+) -> io::Result<T> {
+    let name = archive
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp = archive.with_file_name(format!(".{}.update-{}", name, std::process::id()));
+    let result = write(&temp).and_then(|value| {
+        if let Ok(meta) = fs::metadata(archive) {
+            fs::set_permissions(&temp, meta.permissions())?;
+        }
+        fs::rename(&temp, archive)?;
+        Ok(value)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Name a source is stored under. A relative path keeps its layout, so
+/// `docs/a.txt` stays `docs/a.txt` and `./docs` becomes `docs`; absolute paths
+/// and paths that climb with `..` keep only their final component.
+///
+/// Under a `base` directory the name is the path relative to it, empty for
+/// the base itself, and a source outside it is an error.
+fn stored_name(path: &Path, base: Option<&Path>) -> io::Result<String> {
+    if let Some(base) = base {
+        let outside = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "'{}' is not inside the base directory '{}'",
+                    path.display(),
+                    base.display()
+                ),
+            )
+        };
+        let rel = path.strip_prefix(base).map_err(|_| outside())?;
+        let parts: Option<Vec<String>> = rel
+            .components()
+            .filter(|c| *c != Component::CurDir)
+            .map(|c| match c {
+                Component::Normal(p) => Some(p.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+        return parts.map(|p| p.join("/")).ok_or_else(outside);
+    }
+    let parts: Option<Vec<String>> = path
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .map(|c| match c {
+            Component::Normal(p) => Some(p.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+    match parts {
+        Some(parts) if !parts.is_empty() => Ok(parts.join("/")),
+        _ => source_name(path),
+    }
 }
 
-pub fn extract_archive(path: &str, dest: &str, password: Option<&str>) -> Result<String, String> {
-    if !Path::new(path).exists() {
+fn non_empty(password: Option<&str>) -> Option<&str> {
+    password.filter(|p| !p.is_empty())
+}
+
+pub fn list_archive(
+    path: &str,
+    password: Option<&str>,
+    backend: BackendChoice,
+) -> Result<Vec<ArchiveEntry>, String> {
+    let archive = Path::new(path);
+    if !archive.exists() {
         return Err(format!("Archive not found: {}", path));
     }
-    if is_7z_available() {
-        let mut cmd = Command::new("7z");
-        cmd.arg("x").arg(path).arg(format!("-o{}", dest)).arg("-y");
-        if let Some(p) = password {
-            if !p.is_empty() {
-                cmd.arg(format!("-p{}", p));
-            }
-        }
-        let output = cmd.output().map_err(|e| e.to_string())?;
-        if output.status.success() {
-            Ok(String::from_utf8_lossy(&output.stdout).to_string())
-        } else {
-            Err(String::from_utf8_lossy(&output.stderr).to_string())
+    let run = || -> io::Result<Vec<ArchiveEntry>> {
+        let format = format::detect(archive)?;
+        select_backend(backend, format, Task::Read)?.list(archive, format, non_empty(password))
+    };
+    run().map_err(|e| e.to_string())
+}
+
+/// Extract the entries of `path` chosen by `options` into `dest`.
+pub fn extract_archive(
+    path: &str,
+    dest: &str,
+    password: Option<&str>,
+    options: &ExtractOptions,
+    backend: BackendChoice,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> Result<String, String> {
+    let archive = Path::new(path);
+    if !archive.exists() {
+        return Err(format!("Archive not found: {}", path));
+    }
+    let progress = Progress::new("Extracting", progress, cancel);
+    let run = || -> io::Result<(String, bool)> {
+        let format = format::detect(archive)?;
+        let b = select_backend(backend, format, Task::Read)?;
+        let report = b.extract(
+            archive,
+            format,
+            Path::new(dest),
+            non_empty(password),
+            options,
+            &progress,
+        )?;
+        progress.flush();
+        let msg = format!(
+            "Extracted {} archive to {} ({}): {}",
+            format,
+            dest,
+            b.name(),
+            report.summary()
+        );
+        Ok((msg, report.rejected.is_empty()))
+    };
+    // Rejected entries make the extraction count as failed, so scripts notice.
+    match run() {
+        Ok((msg, true)) => Ok(msg),
+        Ok((msg, false)) => Err(msg),
+        Err(_) if progress.is_canceled() => {
+            progress.discard();
+            Err("Extraction canceled; the files it had created were removed".to_string())
         }
-    } else {
-        // Fallback: support zip and tar.gz extraction
-        let ext = Path::new(path)
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-        if ext == "zip" {
-            let file = File::open(path).map_err(|e| e.to_string())?;
-            let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
-            for i in 0..archive.len() {
-                let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
-                let outpath = Path::new(dest).join(file.mangled_name());
-                if file.name().ends_with('/') {
-                    std::fs::create_dir_all(&outpath).map_err(|e| e.to_string())?;
-                } else {
-                    if let Some(parent) = outpath.parent() {
-                        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-                    }
-                    let mut outfile = File::create(&outpath).map_err(|e| e.to_string())?;
-                    io::copy(&mut file, &mut outfile).map_err(|e| e.to_string())?;
-                }
-            }
-            Ok("Extraction complete".to_string())
-        } else if ext == "tar" || ext == "gz" || path.ends_with(".tar.gz") || path.ends_with(".tgz")
-        {
-            let file = File::open(path).map_err(|e| e.to_string())?;
-            let reader: Box<dyn Read> =
-                if path.ends_with(".gz") || path.ends_with(".tgz") || path.ends_with(".tar.gz") {
-                    Box::new(GzDecoder::new(file))
-                } else {
-                    Box::new(file)
-                };
-            let mut archive = TarArchive::new(reader);
-            archive.unpack(dest).map_err(|e| e.to_string())?;
-            Ok("Extraction complete".to_string())
-        } else {
-            Err("Unsupported archive format and 7z not available".to_string())
+        Err(e) => {
+            progress.remove_staged();
+            Err(e.to_string())
         }
     }
 }
 
+/// Check that every entry of `path` decompresses and matches its stored
+/// checksums. Damaged entries make the test fail, listed in the message.
+pub fn test_archive(
+    path: &str,
+    password: Option<&str>,
+    backend: BackendChoice,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> Result<String, String> {
+    let archive = Path::new(path);
+    if !archive.exists() {
+        return Err(format!("Archive not found: {}", path));
+    }
+    let progress = Progress::new("Testing", progress, cancel);
+    let run = || -> io::Result<(String, bool)> {
+        let (format, b) = reader_for(archive, backend)?;
+        let report = b.test(archive, format, non_empty(password), &progress)?;
+        progress.flush();
+        let msg = format!(
+            "Tested {} archive {} ({}): {}",
+            format,
+            path,
+            b.name(),
+            report.summary()
+        );
+        Ok((msg, report.is_ok()))
+    };
+    match run() {
+        Ok((msg, true)) => Ok(msg),
+        Ok((msg, false)) => Err(msg),
+        Err(_) if progress.is_canceled() => Err("Archive test canceled".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Create `output` from `sources`, in `options.format` or else the format
+/// named by the output file's extension. The archive is written beside
+/// `output` and only moved there once complete, so failing or canceling
+/// leaves an archive that was already there as it was.
 pub fn create_archive(
     sources: &[&str],
     output: &str,
-    format: Option<&str>,
     password: Option<&str>,
+    options: &CreateOptions,
+    backend: BackendChoice,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
 ) -> Result<String, String> {
     if sources.is_empty() {
         return Err("No sources provided".to_string());
     }
-    if is_7z_available() {
-        let mut cmd = Command::new("7z");
-        cmd.arg("a").arg(output);
-        if let Some(t) = format {
-            if !t.is_empty() {
-                cmd.arg(format!("-t{}", t));
+    let out = Path::new(output);
+    let format = match options.format {
+        Some(f) => f,
+        None => ArchiveFormat::from_extension(out).ok_or_else(|| {
+            format!(
+                "Cannot tell the archive format from '{}'; give a format such as zip or tar.gz",
+                output
+            )
+        })?,
+    };
+    let password = non_empty(password);
+    let resolved: Vec<PathBuf> = sources.iter().map(|s| options.source_path(s)).collect();
+    let paths: Vec<&Path> = resolved.iter().map(PathBuf::as_path).collect();
+    check_sources(&paths, options.base())?;
+    let progress = Progress::new("Archiving", progress, cancel);
+    let run = || -> io::Result<String> {
+        options.check(format)?;
+        let b = select_backend(
+            backend,
+            format,
+            Task::Create {
+                encrypted: password.is_some(),
+            },
+        )?;
+        // Never truncate `out` up front; 7z would also add to it instead of replacing it.
+        rewrite(out, |temp| {
+            b.create(temp, format, &paths, password, options, &progress)
+        })?;
+        progress.flush();
+        Ok(format!(
+            "Created {} archive {} ({})",
+            format,
+            output,
+            b.name()
+        ))
+    };
+    match run() {
+        Ok(msg) => Ok(msg),
+        Err(e) => {
+            if progress.is_canceled() {
+                Err("Archive creation canceled".to_string())
+            } else {
+                Err(e.to_string())
             }
         }
-        if let Some(p) = password {
-            if !p.is_empty() {
-                cmd.arg(format!("-p{}", p));
-                cmd.arg("-mhe=on"); // enable header encryption when supported
-            }
-        }
-        for s in sources.iter() {
-            cmd.arg(s);
-        }
-        let output_res = cmd.output().map_err(|e| e.to_string())?;
-        if output_res.status.success() {
-            Ok(String::from_utf8_lossy(&output_res.stdout).to_string())
-        } else {
-            Err(String::from_utf8_lossy(&output_res.stderr).to_string())
+    }
+}
+
+/// Bring the archive at `path` up to date: add the sources it lacks,
+/// replace entries `options.compare` finds changed and remove those matching
+/// `options.delete`. The archive is rewritten beside itself and only replaced
+/// once that succeeds.
+pub fn update_archive(
+    path: &str,
+    sources: &[&str],
+    password: Option<&str>,
+    options: &UpdateOptions,
+    backend: BackendChoice,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> Result<String, String> {
+    let archive = Path::new(path);
+    if !archive.exists() {
+        return Err(format!("Archive not found: {}", path));
+    }
+    if sources.is_empty() && options.delete.is_empty() {
+        return Err("Nothing to update: give files to add or entries to delete".to_string());
+    }
+    let password = non_empty(password);
+    let resolved: Vec<PathBuf> = sources
+        .iter()
+        .map(|s| options.create.source_path(s))
+        .collect();
+    let paths: Vec<&Path> = resolved.iter().map(PathBuf::as_path).collect();
+    check_sources(&paths, options.create.base())?;
+    let progress = Progress::new("Updating", progress, cancel);
+    let run = || -> io::Result<String> {
+        let format = format::detect(archive)?;
+        options.create.check(format)?;
+        let b = select_backend(
+            backend,
+            format,
+            Task::Create {
+                encrypted: password.is_some(),
+            },
+        )?;
+        let report = b.update(archive, format, &paths, password, options, &progress)?;
+        progress.flush();
+        Ok(format!(
+            "Updated {} archive {} ({}): {}",
+            format,
+            path,
+            b.name(),
+            report.summary()
+        ))
+    };
+    match run() {
+        Ok(msg) => Ok(msg),
+        Err(_) if progress.is_canceled() => {
+            Err("Archive update canceled; the archive was left as it was".to_string())
         }
-    } else {
-        // Fallback: support zip and tar.gz creation
-        let out_ext = Path::new(output)
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-        if out_ext == "zip" {
-            let file = File::create(output).map_err(|e| e.to_string())?;
-            let mut zip = ZipWriter::new(file);
-            let options = FileOptions::default();
-            for s in sources.iter() {
-                let p = Path::new(s);
-                if p.is_file() {
-                    let mut f = File::open(p).map_err(|e| e.to_string())?;
-                    zip.start_file(
-                        p.file_name().and_then(|n| n.to_str()).unwrap_or("file"),
-                        options,
-                    )
-                    .map_err(|e| e.to_string())?;
-                    io::copy(&mut f, &mut zip).map_err(|e| e.to_string())?;
-                } else if p.is_dir() {
-                    // recursively add files
-                    for entry in walkdir::WalkDir::new(p) {
-                        let entry = entry.map_err(|e| e.to_string())?;
-                        let path = entry.path();
-                        if path.is_file() {
-                            let name = path
-                                .strip_prefix(p)
-                                .map_err(|e| e.to_string())?
-                                .to_string_lossy()
-                                .to_string();
-                            let mut f = File::open(path).map_err(|e| e.to_string())?;
-                            zip.start_file(name, options).map_err(|e| e.to_string())?;
-                            io::copy(&mut f, &mut zip).map_err(|e| e.to_string())?;
-                        }
-                    }
-                }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Refuse sources that would be stored under the same name or inside one
+/// another, which would store entries twice.
+fn check_sources(paths: &[&Path], base: Option<&Path>) -> Result<(), String> {
+    if let Some(base) = base
+        && !base.is_dir()
+    {
+        return Err(format!("Base directory not found: {}", base.display()));
+    }
+    let mut names: Vec<(String, &Path)> = Vec::new();
+    for path in paths {
+        let name = stored_name(path, base).map_err(|e| e.to_string())?;
+        // An empty name is the base directory, which holds everything.
+        let nested = |outer: &str, inner: &str| {
+            outer.is_empty() || inner.starts_with(&format!("{}/", outer))
+        };
+        for (other_name, other) in &names {
+            if *other_name == name {
+                return Err(format!(
+                    "'{}' and '{}' would both be stored as '{}'",
+                    other.display(),
+                    path.display(),
+                    name
+                ));
             }
-            zip.finish().map_err(|e| e.to_string())?;
-            Ok("Archive created".to_string())
-        } else if out_ext == "gz"
-            || output.ends_with(".tar.gz")
-            || output.ends_with(".tgz")
-            || out_ext == "tar"
-        {
-            let tar_gz = File::create(output).map_err(|e| e.to_string())?;
-            let enc = GzEncoder::new(tar_gz, Compression::default());
-            let mut tar = TarBuilder::new(enc);
-            for s in sources.iter() {
-                tar.append_path(s).map_err(|e| e.to_string())?;
+            if nested(other_name, &name) || nested(&name, other_name) {
+                return Err(format!(
+                    "'{}' and '{}' overlap; give only the outer one",
+                    other.display(),
+                    path.display()
+                ));
             }
-            // finish
-            let _ = tar
-                .into_inner()
-                .map_err(|e| e.to_string())?
-                .finish()
-                .map_err(|e| e.to_string())?;
-            Ok("Archive created".to_string())
-        } else {
-            Err("Unsupported output format and 7z not available".to_string())
         }
+        names.push((name, path));
+    }
+    Ok(())
+}
+
+/// Whether `path` is a file in a format we can open.
+pub fn is_archive(path: &Path) -> bool {
+    path.is_file() && format::detect(path).is_ok()
+}
+
+fn reader_for(
+    archive: &Path,
+    backend: BackendChoice,
+) -> io::Result<(ArchiveFormat, Box<dyn ArchiveBackend>)> {
+    let format = format::detect(archive)?;
+    Ok((format, select_backend(backend, format, Task::Read)?))
+}
+
+/// Stream one file entry of the archive at `path` into `out`.
+pub fn read_entry(
+    path: &str,
+    name: &str,
+    password: Option<&str>,
+    backend: BackendChoice,
+    out: &mut dyn Write,
+) -> Result<u64, String> {
+    let archive = Path::new(path);
+    let (format, b) = reader_for(archive, backend).map_err(|e| e.to_string())?;
+    b.read_entry(archive, format, name, non_empty(password), out)
+        .map_err(|e| e.to_string())
+}
+
+/// Keeps the first `limit` bytes written to it, then reports itself full.
+struct Head {
+    buf: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for Head {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let n = data.len().min(self.limit - self.buf.len());
+        self.buf.extend_from_slice(&data[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The first `limit` bytes of a file entry, without reading the rest.
+pub fn preview_entry(
+    path: &str,
+    name: &str,
+    limit: usize,
+    password: Option<&str>,
+    backend: BackendChoice,
+) -> Result<Vec<u8>, String> {
+    let archive = Path::new(path);
+    let (format, b) = reader_for(archive, backend).map_err(|e| e.to_string())?;
+    let mut head = Head {
+        buf: Vec::with_capacity(limit),
+        limit,
+    };
+    // A full `Head` accepts zero bytes, which stops the copy with WriteZero.
+    match b.read_entry(archive, format, name, non_empty(password), &mut head) {
+        Ok(_) => Ok(head.buf),
+        Err(e) if e.kind() == io::ErrorKind::WriteZero && head.buf.len() == limit => Ok(head.buf),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Hash a file entry as it is decompressed.
+pub fn hash_entry(
+    path: &str,
+    name: &str,
+    algo: HashAlgo,
+    password: Option<&str>,
+    backend: BackendChoice,
+) -> Result<String, String> {
+    let mut digest = DigestWriter::new(algo);
+    read_entry(path, name, password, backend, &mut digest)?;
+    Ok(digest.finalize_hex())
+}
+
+/// Extract a single file entry into `dest_dir` under its own name. An
+/// existing file is never overwritten.
+pub fn extract_entry(
+    path: &str,
+    name: &str,
+    dest_dir: &Path,
+    password: Option<&str>,
+    backend: BackendChoice,
+) -> Result<PathBuf, String> {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    let target = dest_dir.join(file_name);
+    let mut out = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&target)
+        .map_err(|e| format!("Cannot create {}: {}", target.display(), e))?;
+    if let Err(e) = read_entry(path, name, password, backend, &mut out) {
+        drop(out);
+        let _ = std::fs::remove_file(&target);
+        return Err(e);
     }
+    Ok(target)
 }
@@ -0,0 +1,185 @@
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use super::{ArchiveFormat, extract};
+
+/// How entries of a new zip (or 7z) archive are compressed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionMethod {
+    Store,
+    Deflate,
+    Bzip2,
+    Zstd,
+}
+
+impl CompressionMethod {
+    pub const ALL: [CompressionMethod; 4] = [
+        CompressionMethod::Store,
+        CompressionMethod::Deflate,
+        CompressionMethod::Bzip2,
+        CompressionMethod::Zstd,
+    ];
+
+    pub fn parse(s: &str) -> Option<CompressionMethod> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "store" | "stored" | "none" | "copy" => Some(CompressionMethod::Store),
+            "deflate" | "deflated" => Some(CompressionMethod::Deflate),
+            "bzip2" | "bz2" => Some(CompressionMethod::Bzip2),
+            "zstd" | "zst" => Some(CompressionMethod::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CompressionMethod::Store => "store",
+            CompressionMethod::Deflate => "deflate",
+            CompressionMethod::Bzip2 => "bzip2",
+            CompressionMethod::Zstd => "zstd",
+        }
+    }
+}
+
+/// Cipher for password-protected zip archives.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ZipEncryption {
+    /// WinZip AES-256; needs a reasonably recent unzip tool.
+    #[default]
+    Aes256,
+    /// The original PKWARE cipher. Weak, but every zip tool can open it.
+    ZipCrypto,
+}
+
+impl ZipEncryption {
+    pub const ALL: [ZipEncryption; 2] = [ZipEncryption::Aes256, ZipEncryption::ZipCrypto];
+
+    pub fn parse(s: &str) -> Option<ZipEncryption> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "aes" | "aes256" | "aes-256" => Some(ZipEncryption::Aes256),
+            "zipcrypto" | "zip-crypto" | "pkware" | "legacy" => Some(ZipEncryption::ZipCrypto),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ZipEncryption::Aes256 => "AES-256",
+            ZipEncryption::ZipCrypto => "ZipCrypto",
+        }
+    }
+}
+
+/// How a new archive is written. Entry paths, permissions, mtimes and
+/// symlinks are always kept.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CreateOptions {
+    /// Archive format; `None` takes it from the output file's extension.
+    pub format: Option<ArchiveFormat>,
+    /// Per-entry compression for zip and 7z; `None` means deflate for zip
+    /// and the 7z default for 7z. Tarballs are compressed as a whole, so
+    /// their method comes from the format.
+    pub method: Option<CompressionMethod>,
+    /// Compression level; `None` uses the default for the method or format.
+    pub level: Option<u32>,
+    /// Cipher used when a zip archive gets a password.
+    pub encryption: ZipEncryption,
+    /// Directory the sources are taken from, as with `tar -C`: relative
+    /// sources are found under it and every entry is stored by its path
+    /// relative to it, so `.` archives its contents at the top level.
+    /// `None` stores relative sources under the path given.
+    pub base_dir: Option<PathBuf>,
+    /// Entries to leave out. A pattern without a `/` matches an entry's own
+    /// name anywhere (`*.o`, `.git`); one with a `/` matches its whole
+    /// stored path (`docs/drafts`). A trailing `/` (`target/`) limits it to
+    /// directories, whose contents are skipped with them. 7z is given the
+    /// patterns as `-x` switches and does not tell directories apart.
+    pub exclude: Vec<String>,
+}
+
+impl CreateOptions {
+    /// Where the source given as `source` is read from.
+    pub(super) fn source_path(&self, source: &str) -> PathBuf {
+        match &self.base_dir {
+            Some(base) => base.join(source),
+            None => PathBuf::from(source),
+        }
+    }
+
+    /// Whether the entry stored as `name` is left out by `exclude`.
+    pub(super) fn excludes(&self, name: &str, is_dir: bool) -> bool {
+        let own_name = name.rsplit('/').next().unwrap_or(name);
+        self.exclude.iter().any(|p| {
+            let p = p.trim();
+            let dir_only = p.ends_with('/');
+            let p = p.trim_matches('/');
+            if p.is_empty() || (dir_only && !is_dir) {
+                false
+            } else if p.contains('/') {
+                extract::glob_match(p, name)
+            } else {
+                extract::glob_match(p, own_name)
+            }
+        })
+    }
+
+    /// The base directory, if one was given.
+    pub(super) fn base(&self) -> Option<&Path> {
+        self.base_dir.as_deref()
+    }
+
+    /// Check that the method and level make sense for `format`.
+    pub(super) fn check(&self, format: ArchiveFormat) -> io::Result<()> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+        if let Some(method) = self.method
+            && !matches!(format, ArchiveFormat::Zip | ArchiveFormat::SevenZ)
+        {
+            return Err(invalid(format!(
+                "compression method {} only applies to zip and 7z archives",
+                method.label()
+            )));
+        }
+        let Some(level) = self.level else {
+            return Ok(());
+        };
+        match level_range(format, self.method) {
+            Some(range) if range.contains(&level) => Ok(()),
+            Some(range) => Err(invalid(format!(
+                "compression level {} is out of range {}-{} for {}",
+                level,
+                range.start(),
+                range.end(),
+                self.describe(format)
+            ))),
+            None => Err(invalid(format!(
+                "{} takes no compression level",
+                self.describe(format)
+            ))),
+        }
+    }
+
+    fn describe(&self, format: ArchiveFormat) -> String {
+        match self.method {
+            Some(method) => format!("{} {}", format, method.label()),
+            None => format.to_string(),
+        }
+    }
+}
+
+/// Levels accepted for `format` compressed with `method`.
+fn level_range(
+    format: ArchiveFormat,
+    method: Option<CompressionMethod>,
+) -> Option<RangeInclusive<u32>> {
+    match (format, method) {
+        (ArchiveFormat::Tar, _) => None,
+        (ArchiveFormat::Zip, Some(CompressionMethod::Store)) => None,
+        (ArchiveFormat::Zip, Some(CompressionMethod::Bzip2)) | (ArchiveFormat::TarBz2, _) => {
+            Some(1..=9)
+        }
+        (ArchiveFormat::Zip, Some(CompressionMethod::Zstd)) | (ArchiveFormat::TarZst, _) => {
+            Some(1..=22)
+        }
+        _ => Some(0..=9),
+    }
+}
@@ -0,0 +1,164 @@
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+
+/// One entry of an archive, as reported by its backend.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ArchiveEntry {
+    /// Path inside the archive, `/`-separated, without a trailing slash.
+    pub path: String,
+    /// Uncompressed size in bytes (0 for directories).
+    pub size: u64,
+    /// Stored size, for formats that compress entries individually (zip, 7z).
+    pub compressed_size: Option<u64>,
+    pub modified: Option<SystemTime>,
+    pub is_dir: bool,
+    pub encrypted: bool,
+    pub crc32: Option<u32>,
+}
+
+impl ArchiveEntry {
+    /// Final path component.
+    pub fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+
+    /// Compressed size as a percentage of the original size.
+    pub fn ratio(&self) -> Option<f64> {
+        match self.compressed_size {
+            Some(packed) if self.size > 0 => Some(packed as f64 * 100.0 / self.size as f64),
+            _ => None,
+        }
+    }
+
+    pub fn modified_string(&self) -> String {
+        self.modified
+            .map(|t| {
+                DateTime::<Local>::from(t)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn ratio_string(&self) -> String {
+        self.ratio()
+            .map(|r| format!("{:.0}%", r))
+            .unwrap_or_default()
+    }
+
+    pub fn crc_string(&self) -> String {
+        self.crc32.map(|c| format!("{:08X}", c)).unwrap_or_default()
+    }
+}
+
+/// Immediate children of `dir` (`""` for the root), directories first.
+///
+/// Archives need not store an entry for every directory, so directories that
+/// only appear as a path prefix are filled in.
+pub fn children(entries: &[ArchiveEntry], dir: &str) -> Vec<ArchiveEntry> {
+    let prefix = if dir.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", dir)
+    };
+    let mut out: Vec<ArchiveEntry> = Vec::new();
+    for e in entries {
+        let Some(rest) = e.path.strip_prefix(&prefix) else {
+            continue;
+        };
+        if rest.is_empty() {
+            continue;
+        }
+        match rest.split_once('/') {
+            None => {
+                // An explicit entry wins over one implied by a deeper path.
+                out.retain(|c| c.path != e.path);
+                out.push(e.clone());
+            }
+            Some((first, _)) => {
+                let path = format!("{}{}", prefix, first);
+                if !out.iter().any(|c| c.path == path) {
+                    out.push(ArchiveEntry {
+                        path,
+                        is_dir: true,
+                        ..ArchiveEntry::default()
+                    });
+                }
+            }
+        }
+    }
+    out.sort_by(|a, b| {
+        b.is_dir
+            .cmp(&a.is_dir)
+            .then_with(|| a.name().to_lowercase().cmp(&b.name().to_lowercase()))
+    });
+    out
+}
+
+/// Strip the trailing `/` that zip and tar put on directory names.
+pub(super) fn clean_path(path: &str) -> String {
+    path.trim_end_matches('/').to_string()
+}
+
+pub(super) fn not_found(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no file '{}' in archive", name),
+    )
+}
+
+pub(super) fn from_unix(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+/// Local wall-clock time as used by zip and 7z listings.
+pub(super) fn from_local(
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    min: u32,
+    sec: u32,
+) -> Option<SystemTime> {
+    let naive = NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, min, sec)?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(SystemTime::from)
+}
+
+/// Plain-text table of `entries`, in the spirit of `7z l`.
+pub fn format_listing(entries: &[ArchiveEntry]) -> String {
+    let mut out = format!(
+        "{:<19}  {:>12}  {:>12}  {:>5}  {:>8}  {:<4}  {}\n",
+        "Modified", "Size", "Packed", "Ratio", "CRC", "Attr", "Name"
+    );
+    for e in entries {
+        let attr = format!(
+            "{}{}",
+            if e.is_dir { 'D' } else { '.' },
+            if e.encrypted { '*' } else { '.' }
+        );
+        out.push_str(&format!(
+            "{:<19}  {:>12}  {:>12}  {:>5}  {:>8}  {:<4}  {}\n",
+            e.modified_string(),
+            e.size,
+            e.compressed_size.map(|s| s.to_string()).unwrap_or_default(),
+            e.ratio_string(),
+            e.crc_string(),
+            attr,
+            e.path
+        ));
+    }
+    let files = entries.iter().filter(|e| !e.is_dir).count();
+    let total: u64 = entries.iter().map(|e| e.size).sum();
+    out.push_str(&format!(
+        "{} files, {} directories, {} bytes\n",
+        files,
+        entries.len() - files,
+        total
+    ));
+    out
+}
@@ -0,0 +1,868 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use walkdir::WalkDir;
+
+use super::progress::Progress;
+use crate::copy::{self, ConflictPolicy};
+
+/// Which entries to extract and where they land.
+#[derive(Clone, Debug)]
+pub struct ExtractOptions {
+    /// Entry paths or glob patterns (`*`, `?`); empty means everything. A
+    /// pattern that matches a directory selects everything under it.
+    pub patterns: Vec<String>,
+    /// Leading path components dropped from every entry, like `tar --strip-components`.
+    pub strip_components: usize,
+    /// What to do when the target file already exists.
+    pub policy: ConflictPolicy,
+    /// What to do with entries whose paths would escape the destination.
+    pub safety: PathSafety,
+}
+
+/// Handling of entries with absolute paths or `..` components. Symlinks
+/// that point outside the destination are always rejected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathSafety {
+    /// Skip such entries and report them.
+    #[default]
+    Reject,
+    /// Drop the leading `/` and resolve `..` within the entry's own path, so
+    /// the entry lands inside the destination; the rewrite is reported.
+    Rewrite,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        ExtractOptions {
+            patterns: Vec::new(),
+            strip_components: 0,
+            policy: ConflictPolicy::Overwrite,
+            safety: PathSafety::Reject,
+        }
+    }
+}
+
+impl ExtractOptions {
+    /// Whether the entry at `path` (already `/`-separated, no trailing slash) is wanted.
+    pub fn selects(&self, path: &str) -> bool {
+        self.patterns.is_empty() || matches_any(&self.patterns, path)
+    }
+
+    /// Whether the entry at `path` may be extracted under the safety
+    /// setting, recording it in `report` if it is unsafe.
+    pub(super) fn screen(&self, path: &str, report: &mut ExtractReport) -> bool {
+        match safe_components(path) {
+            Ok(_) => true,
+            Err(reason) if self.safety == PathSafety::Reject => {
+                report.reject(path, reason);
+                false
+            }
+            Err(_) => {
+                let used = rewritten_components(path).join("/");
+                report.rewritten.push((path.to_string(), used));
+                true
+            }
+        }
+    }
+
+    /// Where the entry at `path` goes under `dest`, or `None` if it must not
+    /// be written: it is unsafe (and recorded in `report`), or stripping
+    /// leaves nothing of it. `dest` must already exist.
+    pub(super) fn place(
+        &self,
+        dest: &Path,
+        path: &str,
+        report: &mut ExtractReport,
+    ) -> Option<PathBuf> {
+        if !self.screen(path, report) {
+            return None;
+        }
+        let parts = rewritten_components(path);
+        let parts = parts
+            .get(self.strip_components..)
+            .filter(|p| !p.is_empty())?;
+        let mut target = dest.to_path_buf();
+        target.extend(parts);
+        if let Err(reason) = inside(dest, &target) {
+            report.reject(path, reason);
+            return None;
+        }
+        Some(target)
+    }
+}
+
+/// Split an entry path into its components, or say why it is unsafe.
+/// Backslashes count as separators, since Windows tools write them.
+fn safe_components(path: &str) -> Result<Vec<String>, &'static str> {
+    let path = path.replace('\\', "/");
+    if is_absolute(&path) {
+        return Err("absolute path");
+    }
+    let mut parts = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => return Err("path contains '..'"),
+            p if p.contains('\0') => return Err("path contains a NUL byte"),
+            p => parts.push(p.to_string()),
+        }
+    }
+    Ok(parts)
+}
+
+/// `/...` or `C:...`; expects `/` separators.
+fn is_absolute(path: &str) -> bool {
+    path.starts_with('/') || matches!(path.as_bytes(), [d, b':', ..] if d.is_ascii_alphabetic())
+}
+
+/// Lexically resolve `path` as if `/` were the destination: leading
+/// separators and drive letters are dropped and `..` never climbs above it.
+fn rewritten_components(path: &str) -> Vec<String> {
+    let path = path.replace('\\', "/").replace('\0', "");
+    let path = match path.as_bytes() {
+        [d, b':', ..] if d.is_ascii_alphabetic() => &path[2..],
+        _ => &path[..],
+    };
+    let mut parts: Vec<String> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            p => parts.push(p.to_string()),
+        }
+    }
+    parts
+}
+
+/// Check that no directory between `dest` and `target` is a symlink leading
+/// out of `dest`, which would make writing `target` land somewhere else.
+fn inside(dest: &Path, target: &Path) -> Result<(), &'static str> {
+    let root = dest
+        .canonicalize()
+        .map_err(|_| "destination is not accessible")?;
+    let rel = target
+        .strip_prefix(dest)
+        .map_err(|_| "outside the destination")?;
+    let mut current = dest.to_path_buf();
+    let mut components = rel.components().peekable();
+    while let Some(c) = components.next() {
+        if components.peek().is_none() {
+            // The entry itself; an existing file or link there is replaced, not followed.
+            break;
+        }
+        current.push(c);
+        match fs::symlink_metadata(&current) {
+            Ok(m) if m.file_type().is_symlink() => match current.canonicalize() {
+                Ok(real) if real.starts_with(&root) => {}
+                _ => return Err("parent directory is a symlink leading outside the destination"),
+            },
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    Ok(())
+}
+
+/// Check that a symlink stored at `link` (a path under `dest`) pointing at
+/// `target` stays inside `dest`. The link's directory is resolved on disk, so
+/// links written by earlier entries are followed; the target itself may not
+/// pass through an existing symlink, nor climb out of a directory that does
+/// not exist yet, since a later entry could still make that a link.
+pub(super) fn link_inside(dest: &Path, link: &Path, target: &Path) -> Result<(), &'static str> {
+    const OUTSIDE: &str = "symlink points outside the destination";
+    let text = target.to_string_lossy().replace('\\', "/");
+    if is_absolute(&text) {
+        return Err(OUTSIDE);
+    }
+    let root = dest
+        .canonicalize()
+        .map_err(|_| "destination is not accessible")?;
+    let rel = link
+        .parent()
+        .and_then(|p| p.strip_prefix(dest).ok())
+        .ok_or(OUTSIDE)?;
+    // Directories that do not exist yet are created as plain directories.
+    let mut current = root.clone();
+    for c in rel.components() {
+        current.push(c);
+        if let Ok(real) = current.canonicalize() {
+            current = real;
+        }
+    }
+    if !current.starts_with(&root) {
+        return Err(OUTSIDE);
+    }
+    let mut missing = false;
+    for part in text.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if missing {
+                    return Err("symlink climbs out of a directory that does not exist yet");
+                }
+                current.pop();
+                if !current.starts_with(&root) {
+                    return Err(OUTSIDE);
+                }
+            }
+            p => {
+                current.push(p);
+                match fs::symlink_metadata(&current) {
+                    Ok(m) if m.file_type().is_symlink() => {
+                        return Err("symlink target passes through another symlink");
+                    }
+                    Ok(_) => {}
+                    Err(_) => missing = true,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether one of `patterns` matches the entry at `path` or any directory it
+/// sits in.
+pub(super) fn matches_any(patterns: &[String], path: &str) -> bool {
+    patterns.iter().any(|p| {
+        let p = p.trim().trim_matches('/');
+        glob_match(p, path)
+            || path
+                .match_indices('/')
+                .any(|(i, _)| glob_match(p, &path[..i]))
+    })
+}
+
+/// `*` matches any run of characters (including `/`, as in `tar`), `?` any one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// What an extraction did.
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    /// Files written (directories are not counted).
+    pub extracted: usize,
+    /// Existing files left alone because of the conflict policy.
+    pub skipped: Vec<PathBuf>,
+    /// Files written under a new name: (wanted, used).
+    pub renamed: Vec<(PathBuf, PathBuf)>,
+    /// Unsafe entries that were not written: (entry path, reason).
+    pub rejected: Vec<(String, String)>,
+    /// Unsafe entry paths written under a rewritten path: (entry path, used).
+    pub rewritten: Vec<(String, String)>,
+}
+
+impl ExtractReport {
+    pub fn summary(&self) -> String {
+        let mut out = format!("{} files extracted", self.extracted);
+        if !self.skipped.is_empty() {
+            out.push_str(&format!(", {} existing files skipped", self.skipped.len()));
+        }
+        if !self.renamed.is_empty() {
+            out.push_str(&format!(", {} renamed", self.renamed.len()));
+        }
+        if !self.rewritten.is_empty() {
+            out.push_str(&format!(
+                ", {} unsafe paths rewritten",
+                self.rewritten.len()
+            ));
+        }
+        if !self.rejected.is_empty() {
+            out.push_str(&format!(
+                ", {} unsafe entries rejected",
+                self.rejected.len()
+            ));
+        }
+        for (path, used) in &self.rewritten {
+            out.push_str(&format!("\n  rewrote {} -> {}", path, used));
+        }
+        for (path, reason) in &self.rejected {
+            out.push_str(&format!("\n  rejected {}: {}", path, reason));
+        }
+        out
+    }
+
+    pub(super) fn reject(&mut self, path: &str, reason: &str) {
+        self.rejected.push((path.to_string(), reason.to_string()));
+    }
+
+    /// Apply `policy` to `target` and return the path to write to, or `None`
+    /// to skip this entry. `modified` is the entry's mtime, if known.
+    pub(super) fn claim(
+        &mut self,
+        target: PathBuf,
+        modified: Option<SystemTime>,
+        policy: ConflictPolicy,
+    ) -> Option<PathBuf> {
+        let Ok(existing) = fs::symlink_metadata(&target) else {
+            return Some(target);
+        };
+        match policy {
+            ConflictPolicy::Overwrite => Some(target),
+            ConflictPolicy::OverwriteIfNewer => {
+                let newer = match (modified, existing.modified()) {
+                    (Some(entry), Ok(current)) => entry > current,
+                    _ => false,
+                };
+                if newer {
+                    Some(target)
+                } else {
+                    self.skipped.push(target);
+                    None
+                }
+            }
+            ConflictPolicy::Skip => {
+                self.skipped.push(target);
+                None
+            }
+            ConflictPolicy::Rename => {
+                let renamed = copy::unique_name(&target);
+                self.renamed.push((target, renamed.clone()));
+                Some(renamed)
+            }
+        }
+    }
+}
+
+/// `fs::create_dir_all`, recording the directories it had to create in
+/// `progress` so a canceled extraction can remove them again.
+pub(super) fn create_dirs(dir: &Path, progress: &Progress) -> io::Result<()> {
+    let missing: Vec<&Path> = dir
+        .ancestors()
+        .take_while(|d| !d.as_os_str().is_empty() && fs::symlink_metadata(d).is_err())
+        .collect();
+    fs::create_dir_all(dir)?;
+    for d in missing.into_iter().rev() {
+        progress.created(d);
+    }
+    Ok(())
+}
+
+/// Get ready to write the file at `target`: create its parent directories
+/// and return a path beside it to write the entry to. [`commit`] then
+/// renames it over `target` (the conflict policy has already allowed that),
+/// so an existing file is only replaced once the entry is complete, and an
+/// existing symlink there is replaced rather than written through. The
+/// staged path is recorded in `progress`, so a failed or canceled entry
+/// leaves nothing behind.
+pub(super) fn prepare(target: &Path, progress: &Progress) -> io::Result<PathBuf> {
+    if let Some(parent) = target.parent() {
+        create_dirs(parent, progress)?;
+    }
+    refuse_dir(target)?;
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let staged = target.with_file_name(format!(".{}.extract-{}", name, std::process::id()));
+    progress.staged(&staged);
+    Ok(staged)
+}
+
+/// Move the finished entry at `staged` to `target`. Only a target that did
+/// not exist before is recorded in `progress` as created by this
+/// extraction; a file it replaced is not removed again on cancel.
+pub(super) fn commit(staged: &Path, target: &Path, progress: &Progress) -> io::Result<()> {
+    refuse_dir(target)?;
+    let existed = fs::symlink_metadata(target).is_ok();
+    fs::rename(staged, target)?;
+    if !existed {
+        progress.created(target);
+    }
+    Ok(())
+}
+
+fn refuse_dir(target: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(target) {
+        Ok(m) if m.is_dir() => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} is a directory", target.display()),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+pub(super) fn make_symlink(link: &str, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(link, target)
+}
+
+#[cfg(not(unix))]
+pub(super) fn make_symlink(link: &str, target: &Path) -> io::Result<()> {
+    // No portable way to create the link; keep its text like zip tools do.
+    fs::write(target, link)
+}
+
+/// Move everything a backend unpacked into `staging` over to `dest`,
+/// applying `options.strip_components` and the conflict policy on the way.
+pub(super) fn place_staged(
+    staging: &Path,
+    dest: &Path,
+    options: &ExtractOptions,
+    report: &mut ExtractReport,
+    progress: &Progress,
+) -> io::Result<()> {
+    for entry in WalkDir::new(staging).min_depth(1).sort_by_file_name() {
+        let entry = entry?;
+        progress.check()?;
+        let rel = entry
+            .path()
+            .strip_prefix(staging)
+            .unwrap_or(entry.path())
+            .to_string_lossy()
+            .replace('\\', "/");
+        let Some(target) = options.place(dest, &rel, report) else {
+            continue;
+        };
+        if entry.file_type().is_dir() {
+            create_dirs(&target, progress)?;
+            continue;
+        }
+        if entry.file_type().is_symlink() {
+            let link = fs::read_link(entry.path())?;
+            if let Err(reason) = link_inside(dest, &target, &link) {
+                report.reject(&rel, reason);
+                continue;
+            }
+        }
+        let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+        let Some(target) = report.claim(target, modified, options.policy) else {
+            continue;
+        };
+        // The staging directory is beside `dest`'s files, so the entry is
+        // already complete and can be renamed into place directly.
+        if let Some(parent) = target.parent() {
+            create_dirs(parent, progress)?;
+        }
+        commit(entry.path(), &target, progress)?;
+        report.extracted += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::Path;
+    use std::sync::atomic::AtomicBool;
+
+    use tar::{EntryType, Header};
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+    use crate::archive::{BackendChoice, extract_archive};
+
+    /// A zip holding `names`, each containing its own name.
+    fn write_zip(path: &Path, names: &[&str]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for name in names {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(name.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    enum TarItem<'a> {
+        File(&'a str),
+        Symlink(&'a str, &'a str),
+        HardLink(&'a str, &'a str),
+    }
+
+    /// A tar with raw header names; `tar::Builder` refuses to write `..`.
+    fn write_tar(path: &Path, items: &[TarItem]) {
+        fn set(field: &mut [u8], value: &str) {
+            field.fill(0);
+            field[..value.len()].copy_from_slice(value.as_bytes());
+        }
+        let mut builder = tar::Builder::new(File::create(path).unwrap());
+        for item in items {
+            let mut header = Header::new_old();
+            header.set_mode(0o644);
+            let (name, data) = match *item {
+                TarItem::File(name) => {
+                    header.set_entry_type(EntryType::Regular);
+                    (name, name.as_bytes())
+                }
+                TarItem::Symlink(name, link) => {
+                    header.set_entry_type(EntryType::Symlink);
+                    set(&mut header.as_old_mut().linkname, link);
+                    (name, &[][..])
+                }
+                TarItem::HardLink(name, link) => {
+                    header.set_entry_type(EntryType::Link);
+                    set(&mut header.as_old_mut().linkname, link);
+                    (name, &[][..])
+                }
+            };
+            set(&mut header.as_old_mut().name, name);
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, data).unwrap();
+        }
+        builder.finish().unwrap();
+    }
+
+    fn extract(archive: &Path, dest: &Path, safety: PathSafety) -> Result<String, String> {
+        let options = ExtractOptions {
+            safety,
+            ..ExtractOptions::default()
+        };
+        extract_archive(
+            archive.to_str().unwrap(),
+            dest.to_str().unwrap(),
+            None,
+            &options,
+            BackendChoice::Native,
+            &mut |_| {},
+            &AtomicBool::new(false),
+        )
+    }
+
+    /// Every file under `root`, relative and `/`-separated.
+    fn files_under(root: &Path) -> Vec<String> {
+        let mut files: Vec<String> = WalkDir::new(root)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| !e.file_type().is_dir())
+            .map(|e| {
+                e.path()
+                    .strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    const TRAVERSAL: [&str; 5] = [
+        "../evil.txt",
+        "ok/../../evil2.txt",
+        "/tmp-zip-slip-abs.txt",
+        "..\\win.txt",
+        "C:/drive.txt",
+    ];
+
+    #[test]
+    fn zip_traversal_is_rejected() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("evil.zip");
+        let mut names = TRAVERSAL.to_vec();
+        names.push("ok/good.txt");
+        write_zip(&archive, &names);
+        let dest = tmp.path().join("out");
+
+        let err = extract(&archive, &dest, PathSafety::Reject).unwrap_err();
+        for name in TRAVERSAL {
+            assert!(err.contains(&format!("rejected {}", name)), "{}", err);
+        }
+        assert_eq!(files_under(&dest), ["ok/good.txt"]);
+        assert_eq!(files_under(tmp.path()), ["evil.zip", "out/ok/good.txt"]);
+        assert!(!Path::new("/tmp-zip-slip-abs.txt").exists());
+    }
+
+    #[test]
+    fn zip_traversal_is_rewritten_inside_dest() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("evil.zip");
+        write_zip(&archive, &TRAVERSAL);
+        let dest = tmp.path().join("out");
+
+        let msg = extract(&archive, &dest, PathSafety::Rewrite).unwrap();
+        assert!(msg.contains("5 unsafe paths rewritten"), "{}", msg);
+        assert_eq!(
+            files_under(&dest),
+            [
+                "drive.txt",
+                "evil.txt",
+                "evil2.txt",
+                "tmp-zip-slip-abs.txt",
+                "win.txt"
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(dest.join("evil2.txt")).unwrap(),
+            "ok/../../evil2.txt"
+        );
+        assert_eq!(files_under(tmp.path()).len(), 6);
+    }
+
+    #[test]
+    fn tar_traversal_is_rejected_or_rewritten() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("evil.tar");
+        let mut items: Vec<TarItem> = TRAVERSAL.iter().map(|n| TarItem::File(n)).collect();
+        items.push(TarItem::File("ok/good.txt"));
+        write_tar(&archive, &items);
+
+        let rejected = tmp.path().join("rejected");
+        let err = extract(&archive, &rejected, PathSafety::Reject).unwrap_err();
+        assert!(err.contains("5 unsafe entries rejected"), "{}", err);
+        assert_eq!(files_under(&rejected), ["ok/good.txt"]);
+
+        let rewritten = tmp.path().join("rewritten");
+        extract(&archive, &rewritten, PathSafety::Rewrite).unwrap();
+        assert_eq!(files_under(&rewritten).len(), 6);
+        assert_eq!(files_under(tmp.path()).len(), 8);
+    }
+
+    #[test]
+    fn tar_escaping_symlinks_are_rejected() {
+        let tmp = tempfile::tempdir().unwrap();
+        let outside = tmp.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        let archive = tmp.path().join("links.tar");
+        write_tar(
+            &archive,
+            &[
+                TarItem::Symlink("abs", outside.to_str().unwrap()),
+                TarItem::File("abs/pwned.txt"),
+                TarItem::Symlink("up", "../outside"),
+                TarItem::File("up/pwned2.txt"),
+                TarItem::Symlink("deep/up", "../../outside"),
+                TarItem::HardLink("hard", "../outside/secret"),
+                TarItem::File("sub/file.txt"),
+                TarItem::Symlink("sub/ok", "file.txt"),
+                TarItem::Symlink("sub/ok2", "../sub/file.txt"),
+            ],
+        );
+        let dest = tmp.path().join("out");
+
+        let err = extract(&archive, &dest, PathSafety::Reject).unwrap_err();
+        assert!(
+            err.contains("rejected abs: symlink points outside"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("rejected up: symlink points outside"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("rejected deep/up: symlink points outside"),
+            "{}",
+            err
+        );
+        assert!(err.contains("rejected hard: hard link target"), "{}", err);
+        assert!(files_under(&outside).is_empty());
+        // Files meant to go through the links land in plain directories instead.
+        assert!(dest.join("abs/pwned.txt").is_file());
+        assert!(!dest.join("abs").is_symlink());
+        assert_eq!(
+            fs::read_link(dest.join("sub/ok")).unwrap(),
+            Path::new("file.txt")
+        );
+        assert_eq!(
+            fs::read_to_string(dest.join("sub/ok2")).unwrap(),
+            "sub/file.txt"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn chained_symlinks_are_rejected() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let tar = tmp.path().join("chain.tar");
+        write_tar(
+            &tar,
+            &[TarItem::Symlink("a", "."), TarItem::Symlink("b", "a/..")],
+        );
+        let dest = tmp.path().join("tar");
+        let err = extract(&tar, &dest, PathSafety::Reject).unwrap_err();
+        assert!(
+            err.contains("rejected b: symlink target passes through"),
+            "{}",
+            err
+        );
+        assert!(!dest.join("b").is_symlink());
+
+        let zip = tmp.path().join("chain.zip");
+        let mut writer = ZipWriter::new(File::create(&zip).unwrap());
+        writer
+            .add_symlink("a", ".", SimpleFileOptions::default())
+            .unwrap();
+        writer
+            .add_symlink("b", "a/..", SimpleFileOptions::default())
+            .unwrap();
+        writer.finish().unwrap();
+        let dest = tmp.path().join("zip");
+        let err = extract(&zip, &dest, PathSafety::Reject).unwrap_err();
+        assert!(
+            err.contains("rejected b: symlink target passes through"),
+            "{}",
+            err
+        );
+        assert!(!dest.join("b").is_symlink());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn existing_symlink_in_dest_is_not_followed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let outside = tmp.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        let dest = tmp.path().join("out");
+        fs::create_dir(&dest).unwrap();
+        std::os::unix::fs::symlink(&outside, dest.join("sneaky")).unwrap();
+        std::os::unix::fs::symlink(&outside, dest.join("target.txt")).unwrap();
+
+        let zip = tmp.path().join("sneaky.zip");
+        write_zip(&zip, &["sneaky/file.txt", "target.txt"]);
+        let err = extract(&zip, &dest, PathSafety::Rewrite).unwrap_err();
+        assert!(err.contains("rejected sneaky/file.txt: parent directory is a symlink"));
+        // An existing link at the entry itself is replaced, not written through.
+        assert!(dest.join("target.txt").is_file());
+        assert!(!dest.join("target.txt").is_symlink());
+
+        let tar = tmp.path().join("sneaky.tar");
+        write_tar(&tar, &[TarItem::File("sneaky/file.txt")]);
+        extract(&tar, &dest, PathSafety::Reject).unwrap_err();
+        assert!(files_under(&outside).is_empty());
+    }
+
+    #[test]
+    fn canceled_extraction_removes_what_it_wrote() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("big.zip");
+        let mut zip = ZipWriter::new(File::create(&archive).unwrap());
+        for name in ["a/one.bin", "a/two.bin", "b/three.bin"] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(&vec![7u8; 1 << 20]).unwrap();
+        }
+        zip.finish().unwrap();
+        let dest = tmp.path().join("out");
+        fs::create_dir(&dest).unwrap();
+        fs::write(dest.join("keep.txt"), "mine").unwrap();
+
+        let cancel = AtomicBool::new(false);
+        let mut messages = Vec::new();
+        let result = extract_archive(
+            archive.to_str().unwrap(),
+            dest.to_str().unwrap(),
+            None,
+            &ExtractOptions::default(),
+            BackendChoice::Native,
+            &mut |msg| {
+                if msg.starts_with("Extracting 2/3 entries") {
+                    cancel.store(true, std::sync::atomic::Ordering::SeqCst);
+                }
+                messages.push(msg);
+            },
+            &cancel,
+        );
+        assert!(result.unwrap_err().contains("canceled"));
+        assert!(messages[0].starts_with("Extracting 1/3 entries: a/one.bin"));
+        assert_eq!(files_under(&dest), ["keep.txt"]);
+        assert!(!dest.join("a").exists());
+    }
+
+    #[test]
+    fn canceled_overwrite_keeps_existing_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("big.zip");
+        let mut zip = ZipWriter::new(File::create(&archive).unwrap());
+        for name in ["new.bin", "one.bin", "two.bin"] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(&vec![7u8; 1 << 20]).unwrap();
+        }
+        zip.finish().unwrap();
+        let dest = tmp.path().join("out");
+        fs::create_dir(&dest).unwrap();
+        fs::write(dest.join("one.bin"), "old one").unwrap();
+        fs::write(dest.join("two.bin"), "old two").unwrap();
+
+        // Cancel while the last entry is being written over its old file.
+        let cancel = AtomicBool::new(false);
+        let result = extract_archive(
+            archive.to_str().unwrap(),
+            dest.to_str().unwrap(),
+            None,
+            &ExtractOptions::default(),
+            BackendChoice::Native,
+            &mut |msg| {
+                if msg.starts_with("Extracting 3/3 entries") {
+                    cancel.store(true, std::sync::atomic::Ordering::SeqCst);
+                }
+            },
+            &cancel,
+        );
+        assert!(result.unwrap_err().contains("canceled"));
+        // The new file is removed again, the completed overwrite stays and
+        // the interrupted one leaves the old file as it was.
+        assert_eq!(files_under(&dest), ["one.bin", "two.bin"]);
+        assert_eq!(fs::read(dest.join("one.bin")).unwrap(), vec![7u8; 1 << 20]);
+        assert_eq!(fs::read_to_string(dest.join("two.bin")).unwrap(), "old two");
+    }
+
+    #[test]
+    fn unsafe_components_are_reported() {
+        assert_eq!(safe_components("a/./b//c/").unwrap(), ["a", "b", "c"]);
+        assert_eq!(safe_components("a\\b").unwrap(), ["a", "b"]);
+        assert!(safe_components("a/../../b").is_err());
+        assert!(safe_components("/etc/passwd").is_err());
+        assert!(safe_components("c:\\windows").is_err());
+        assert!(safe_components("a\0b").is_err());
+        assert_eq!(rewritten_components("/a/../../b/./c"), ["b", "c"]);
+        assert_eq!(rewritten_components("D:\\x\\..\\y"), ["y"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn link_targets_are_resolved_on_disk() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dest = tmp.path().join("dest");
+        fs::create_dir_all(dest.join("a/c")).unwrap();
+        std::os::unix::fs::symlink(".", dest.join("here")).unwrap();
+        assert!(link_inside(&dest, &dest.join("a/b"), Path::new("../c")).is_ok());
+        assert!(link_inside(&dest, &dest.join("a/b"), Path::new("c/../../d")).is_ok());
+        assert!(link_inside(&dest, &dest.join("x/y/b"), Path::new("../../d")).is_ok());
+        assert!(link_inside(&dest, &dest.join("a/b"), Path::new("../../c")).is_err());
+        assert!(link_inside(&dest, &dest.join("b"), Path::new("/dest/c")).is_err());
+        assert!(link_inside(&dest, &dest.join("b"), Path::new("C:\\x")).is_err());
+        assert!(link_inside(&dest, &dest.join("b"), Path::new("here/..")).is_err());
+        assert!(link_inside(&dest, &dest.join("here/b"), Path::new("..")).is_err());
+        assert!(link_inside(&dest, &dest.join("b"), Path::new("new/..")).is_err());
+    }
+
+    #[test]
+    fn patterns_match_entries_and_their_directories() {
+        assert!(glob_match("*.txt", "a/b.txt"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a?c", "ac"));
+        let options = ExtractOptions {
+            patterns: vec!["docs/".to_string()],
+            ..ExtractOptions::default()
+        };
+        assert!(options.selects("docs/a/b.md"));
+        assert!(!options.selects("docsx/a.md"));
+    }
+}
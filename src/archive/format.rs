@@ -0,0 +1,266 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Archive container formats understood by at least one backend.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarBz2,
+    TarXz,
+    TarZst,
+    SevenZ,
+}
+
+impl ArchiveFormat {
+    pub fn label(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::TarBz2 => "tar.bz2",
+            ArchiveFormat::TarXz => "tar.xz",
+            ArchiveFormat::TarZst => "tar.zst",
+            ArchiveFormat::SevenZ => "7z",
+        }
+    }
+
+    /// Parse a format name as given to `--format`.
+    pub fn parse(s: &str) -> Option<ArchiveFormat> {
+        match s
+            .trim()
+            .trim_start_matches('.')
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "zip" => Some(ArchiveFormat::Zip),
+            "tar" => Some(ArchiveFormat::Tar),
+            "tar.gz" | "tgz" | "gz" | "gzip" => Some(ArchiveFormat::TarGz),
+            "tar.bz2" | "tbz2" | "tbz" | "bz2" | "bzip2" => Some(ArchiveFormat::TarBz2),
+            "tar.xz" | "txz" | "xz" => Some(ArchiveFormat::TarXz),
+            "tar.zst" | "tzst" | "zst" | "zstd" => Some(ArchiveFormat::TarZst),
+            "7z" => Some(ArchiveFormat::SevenZ),
+            _ => None,
+        }
+    }
+
+    /// Guess the format of an archive that is about to be created from its name.
+    pub fn from_extension(path: &Path) -> Option<ArchiveFormat> {
+        let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+        [
+            ".tar.gz", ".tgz", ".tar.bz2", ".tbz2", ".tar.xz", ".txz", ".tar.zst", ".tzst", ".zip",
+            ".tar", ".7z",
+        ]
+        .iter()
+        .find(|ext| name.ends_with(*ext))
+        .and_then(|ext| ArchiveFormat::parse(ext))
+    }
+
+    /// Whether the format is a tar stream, possibly compressed.
+    pub fn is_tar(self) -> bool {
+        !matches!(self, ArchiveFormat::Zip | ArchiveFormat::SevenZ)
+    }
+}
+
+impl fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// Identify an existing archive from its leading bytes, ignoring its name.
+///
+/// Compressed streams are only reported as tar archives when the first
+/// decompressed block is a valid tar header.
+pub fn detect(path: &Path) -> io::Result<ArchiveFormat> {
+    let mut head = Vec::with_capacity(512);
+    File::open(path)?.take(512).read_to_end(&mut head)?;
+    let unknown = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: unrecognized archive format", path.display()),
+        )
+    };
+
+    if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") {
+        return Ok(ArchiveFormat::Zip);
+    }
+    if head.starts_with(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C]) {
+        return Ok(ArchiveFormat::SevenZ);
+    }
+    if is_tar_header(&head) {
+        return Ok(ArchiveFormat::Tar);
+    }
+    let compressed = if head.starts_with(&[0x1F, 0x8B]) {
+        ArchiveFormat::TarGz
+    } else if head.starts_with(b"BZh") {
+        ArchiveFormat::TarBz2
+    } else if head.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+        ArchiveFormat::TarXz
+    } else if head.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+        ArchiveFormat::TarZst
+    } else {
+        return Err(unknown());
+    };
+
+    let mut inner = Vec::with_capacity(512);
+    super::tarball::decoder(compressed, File::open(path)?)?
+        .take(512)
+        .read_to_end(&mut inner)?;
+    if is_tar_header(&inner) {
+        Ok(compressed)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{}: compressed file does not contain a tar archive",
+                path.display()
+            ),
+        ))
+    }
+}
+
+/// Check the `ustar` magic, or failing that the header checksum (old v7 tars).
+fn is_tar_header(block: &[u8]) -> bool {
+    if block.len() < 512 {
+        return false;
+    }
+    if &block[257..262] == b"ustar" {
+        return true;
+    }
+    let field = String::from_utf8_lossy(&block[148..156]);
+    let Ok(expected) = u32::from_str_radix(field.trim_matches(|c: char| c == '\0' || c == ' '), 8)
+    else {
+        return false;
+    };
+    let sum: u32 = block
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            if (148..156).contains(&i) {
+                b' ' as u32
+            } else {
+                *b as u32
+            }
+        })
+        .sum();
+    sum == expected
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+
+    use super::*;
+
+    fn tar_bytes(header: tar::Header) -> Vec<u8> {
+        let mut tar = tar::Builder::new(Vec::new());
+        let mut header = header;
+        header.set_size(5);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, "hello.txt", &b"hello"[..])
+            .unwrap();
+        tar.into_inner().unwrap()
+    }
+
+    fn zip_bytes(empty: bool) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        if !empty {
+            zip.start_file("hello.txt", zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(b"hello").unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn compress(format: ArchiveFormat, data: &[u8]) -> Vec<u8> {
+        fn done<W: Write>(mut w: W, data: &[u8]) -> W {
+            w.write_all(data).unwrap();
+            w
+        }
+        match format {
+            ArchiveFormat::TarGz => done(
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()),
+                data,
+            )
+            .finish()
+            .unwrap(),
+            ArchiveFormat::TarBz2 => done(
+                bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default()),
+                data,
+            )
+            .finish()
+            .unwrap(),
+            ArchiveFormat::TarXz => done(xz2::write::XzEncoder::new(Vec::new(), 6), data)
+                .finish()
+                .unwrap(),
+            ArchiveFormat::TarZst => zstd::encode_all(data, 0).unwrap(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn detect_bytes(data: &[u8]) -> io::Result<ArchiveFormat> {
+        let tmp = tempfile::tempdir().unwrap();
+        // The name says nothing, so only the contents can tell.
+        let path = tmp.path().join("archive.bin");
+        fs::write(&path, data).unwrap();
+        detect(&path)
+    }
+
+    #[test]
+    fn zip_and_7z_are_told_by_their_magic() {
+        assert_eq!(detect_bytes(&zip_bytes(false)).unwrap(), ArchiveFormat::Zip);
+        // An empty zip is only its end-of-central-directory record.
+        let empty = zip_bytes(true);
+        assert!(empty.starts_with(b"PK\x05\x06"));
+        assert_eq!(detect_bytes(&empty).unwrap(), ArchiveFormat::Zip);
+        let mut seven = vec![0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C, 0x00, 0x04];
+        seven.resize(32, 0);
+        assert_eq!(detect_bytes(&seven).unwrap(), ArchiveFormat::SevenZ);
+    }
+
+    #[test]
+    fn plain_tars_are_told_by_their_header() {
+        for header in [
+            tar::Header::new_ustar(),
+            tar::Header::new_gnu(),
+            // Old v7 tars have no magic, only the checksum.
+            tar::Header::new_old(),
+        ] {
+            assert_eq!(
+                detect_bytes(&tar_bytes(header)).unwrap(),
+                ArchiveFormat::Tar
+            );
+        }
+    }
+
+    #[test]
+    fn compressed_tars_are_looked_into() {
+        let tar = tar_bytes(tar::Header::new_gnu());
+        for format in [
+            ArchiveFormat::TarGz,
+            ArchiveFormat::TarBz2,
+            ArchiveFormat::TarXz,
+            ArchiveFormat::TarZst,
+        ] {
+            assert_eq!(detect_bytes(&compress(format, &tar)).unwrap(), format);
+            // A compressed file that is not a tar is not an archive.
+            let e = detect_bytes(&compress(format, &[b'x'; 1000])).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+            assert!(e.to_string().contains("does not contain a tar"), "{}", e);
+        }
+    }
+
+    #[test]
+    fn anything_else_is_unrecognized() {
+        for data in [&b""[..], b"PK", b"plain text, not an archive", &[0u8; 600]] {
+            let e = detect_bytes(data).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+            assert!(e.to_string().contains("unrecognized"), "{}", e);
+        }
+    }
+}
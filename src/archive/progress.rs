@@ -0,0 +1,261 @@
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use super::SourceEntry;
+
+/// Least time between two messages about the same percentage.
+const REPORT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How far one archive operation has got. Backends count entries and bytes
+/// against it; it sends throttled status lines with an ETA, stops the work
+/// once `cancel` is set and remembers what was written so a canceled
+/// extraction can be undone.
+///
+/// Methods take `&self` so a backend can count bytes inside a reader or
+/// writer (see [`Tracked`]) while its own loop counts entries.
+pub struct Progress<'a> {
+    verb: &'static str,
+    report: RefCell<&'a mut dyn FnMut(String)>,
+    cancel: &'a AtomicBool,
+    started: Instant,
+    /// When the last message went out, and at what percentage.
+    last: Cell<Option<(Instant, u64)>>,
+    entries: Cell<usize>,
+    total_entries: Cell<Option<usize>>,
+    done: Cell<u64>,
+    total: Cell<u64>,
+    current: RefCell<String>,
+    created: RefCell<Vec<PathBuf>>,
+    staged: RefCell<Vec<PathBuf>>,
+}
+
+impl<'a> Progress<'a> {
+    pub fn new(
+        verb: &'static str,
+        report: &'a mut dyn FnMut(String),
+        cancel: &'a AtomicBool,
+    ) -> Progress<'a> {
+        Progress {
+            verb,
+            report: RefCell::new(report),
+            cancel,
+            started: Instant::now(),
+            last: Cell::new(None),
+            entries: Cell::new(0),
+            total_entries: Cell::new(None),
+            done: Cell::new(0),
+            total: Cell::new(0),
+            current: RefCell::new(String::new()),
+            created: RefCell::new(Vec::new()),
+            staged: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Amount of work to expect, in whatever unit `advance` counts (bytes
+    /// for the built-in backends, percent for 7z), and the number of entries
+    /// if it is known up front.
+    pub(super) fn set_total(&self, total: u64, entries: Option<usize>) {
+        self.total.set(total);
+        self.total_entries.set(entries);
+    }
+
+    /// Set the totals for archiving `entries`: their number and the bytes
+    /// of the regular files among them.
+    pub(super) fn count_sources(&self, entries: &[SourceEntry]) {
+        let bytes = entries
+            .iter()
+            .filter(|e| e.meta.is_file())
+            .map(|e| e.meta.len())
+            .sum();
+        self.set_total(bytes, Some(entries.len()));
+    }
+
+    pub(super) fn is_canceled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    /// Fail with a "canceled" error once cancellation was requested. The
+    /// error is deliberately not `Interrupted`, which `io::copy` retries.
+    pub(super) fn check(&self) -> io::Result<()> {
+        if self.is_canceled() {
+            Err(io::Error::other(format!(
+                "{} canceled",
+                self.verb.to_lowercase()
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// A new entry is being processed.
+    pub(super) fn start_entry(&self, name: &str) -> io::Result<()> {
+        self.check()?;
+        self.entries.set(self.entries.get() + 1);
+        *self.current.borrow_mut() = name.to_string();
+        self.send(false);
+        Ok(())
+    }
+
+    /// `n` more units of work are done.
+    pub(super) fn advance(&self, n: u64) -> io::Result<()> {
+        self.check()?;
+        self.done.set(self.done.get() + n);
+        self.send(false);
+        Ok(())
+    }
+
+    /// Progress as reported by an external tool: `done` units, plus the
+    /// entry count so far and the entry being worked on if it said.
+    pub(super) fn set_done(&self, done: u64, entries: Option<usize>, name: Option<&str>) {
+        self.done.set(done);
+        if let Some(entries) = entries {
+            self.entries.set(entries);
+        }
+        if let Some(name) = name {
+            *self.current.borrow_mut() = name.to_string();
+        }
+        self.send(false);
+    }
+
+    /// Send a line of its own, such as the result for one entry, right away.
+    pub(super) fn note(&self, msg: String) {
+        (self.report.borrow_mut())(msg);
+    }
+
+    /// Send the final count regardless of throttling.
+    pub(super) fn flush(&self) {
+        self.send(true);
+    }
+
+    /// Remember that `path` did not exist and was created by this operation.
+    pub(super) fn created(&self, path: &Path) {
+        self.created.borrow_mut().push(path.to_path_buf());
+    }
+
+    /// Remember that `path` is a temporary file an entry is written to
+    /// before it is renamed into place.
+    pub(super) fn staged(&self, path: &Path) {
+        self.staged.borrow_mut().push(path.to_path_buf());
+    }
+
+    /// Remove the temporary files recorded with `staged` that are still
+    /// there, i.e. entries that were never completed.
+    pub(super) fn remove_staged(&self) {
+        for path in self.staged.borrow_mut().drain(..) {
+            let _ = fs::remove_file(&path);
+        }
+    }
+
+    /// Remove unfinished entries and everything recorded with `created`,
+    /// newest first. Directories are only removed if they are empty by then.
+    /// Files that existed before are never recorded, so they are left alone.
+    pub(super) fn discard(&self) {
+        self.remove_staged();
+        for path in self.created.borrow_mut().drain(..).rev() {
+            match fs::symlink_metadata(&path) {
+                Ok(m) if m.is_dir() => {
+                    let _ = fs::remove_dir(&path);
+                }
+                Ok(_) => {
+                    let _ = fs::remove_file(&path);
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    fn percent(&self) -> u64 {
+        (self.done.get().min(self.total.get()) * 100)
+            .checked_div(self.total.get())
+            .unwrap_or(0)
+    }
+
+    fn send(&self, force: bool) {
+        let pct = self.percent();
+        let now = Instant::now();
+        if !force
+            && let Some((at, last_pct)) = self.last.get()
+            && last_pct == pct
+            && now.duration_since(at) < REPORT_INTERVAL
+        {
+            return;
+        }
+        self.last.set(Some((now, pct)));
+
+        let mut msg = format!("{} {}", self.verb, self.entries.get());
+        if let Some(total) = self.total_entries.get() {
+            msg.push_str(&format!("/{}", total));
+        }
+        msg.push_str(" entries");
+        let current = self.current.borrow();
+        if !current.is_empty() {
+            msg.push_str(&format!(": {}", current));
+        }
+        if self.total.get() > 0 {
+            msg.push_str(&format!(" ({}%", pct));
+            if let Some(eta) = self.eta() {
+                msg.push_str(&format!(", ETA {}", format_duration(eta)));
+            }
+            msg.push(')');
+        }
+        (self.report.borrow_mut())(msg);
+    }
+
+    /// Remaining time at the average rate so far, once there is enough to go on.
+    fn eta(&self) -> Option<Duration> {
+        let (done, total) = (self.done.get(), self.total.get());
+        let elapsed = self.started.elapsed();
+        if done == 0 || done >= total || elapsed < Duration::from_secs(1) {
+            return None;
+        }
+        Some(elapsed.mul_f64((total - done) as f64 / done as f64))
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// A reader or writer that counts the bytes passing through it against a
+/// [`Progress`], failing once the operation is canceled.
+pub(super) struct Tracked<'p, 'a, T> {
+    inner: T,
+    progress: &'p Progress<'a>,
+}
+
+impl<'p, 'a, T> Tracked<'p, 'a, T> {
+    pub(super) fn new(inner: T, progress: &'p Progress<'a>) -> Self {
+        Tracked { inner, progress }
+    }
+}
+
+impl<T: Read> Read for Tracked<'_, '_, T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.progress.check()?;
+        let n = self.inner.read(buf)?;
+        self.progress.advance(n as u64)?;
+        Ok(n)
+    }
+}
+
+impl<T: Write> Write for Tracked<'_, '_, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.progress.check()?;
+        let n = self.inner.write(buf)?;
+        self.progress.advance(n as u64)?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,811 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::OnceLock;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use super::entry::{self, ArchiveEntry};
+use super::extract::{self, ExtractOptions, ExtractReport};
+use super::progress::Progress;
+use super::update::{self, ChangeCheck, UpdateOptions, UpdateReport};
+use super::verify::TestReport;
+use super::{
+    ArchiveBackend, ArchiveFormat, CompressionMethod, CreateOptions, rewrite, walk_sources,
+};
+
+/// Backend that drives the external 7-Zip CLI (`7z`).
+pub struct SevenZipBackend;
+
+/// A 7-Zip command line program and which port of 7-Zip it is.
+struct Tool {
+    program: &'static str,
+    /// p7zip reads passwords with `getpass()`, which takes them from the
+    /// terminal rather than stdin whenever there is one.
+    p7zip: bool,
+}
+
+impl Tool {
+    /// Run `program` to see whether it is installed and which port it is.
+    fn detect(program: &'static str) -> Option<Tool> {
+        let output = Command::new(program)
+            .arg("--help")
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        let banner = String::from_utf8_lossy(&output.stdout);
+        Some(Tool {
+            program,
+            p7zip: banner.contains("p7zip"),
+        })
+    }
+
+    /// `<program> <subcommand>` with the password switch, which comes before
+    /// any other argument since everything after `--` is a file name.
+    ///
+    /// 7-Zip reads the password from stdin, so it is answered through a pipe
+    /// and never appears in `ps`. p7zip would read it from the terminal and
+    /// wait there, and its only other way is `-p<password>` on the command
+    /// line, where other users can read it; passwords are refused for it.
+    /// Without a password stdin is closed, so 7-Zip fails instead of waiting
+    /// at a prompt.
+    fn command(&self, subcommand: &str, password: Option<&str>) -> io::Result<Command> {
+        let mut cmd = Command::new(self.program);
+        cmd.arg(subcommand);
+        match password {
+            Some(_) if self.p7zip => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "p7zip can only take a password on its command line, where other users \
+                     can see it; install 7-Zip (7zz) for password-protected 7z archives, or \
+                     use the built-in backend for zip",
+                ));
+            }
+            Some(_) => cmd.arg("-p").stdin(Stdio::piped()),
+            None => cmd.stdin(Stdio::null()),
+        };
+        Ok(cmd)
+    }
+}
+
+/// `7z`, or else 7-Zip's own `7zz`; looked up once.
+fn tool() -> Option<&'static Tool> {
+    static TOOL: OnceLock<Option<Tool>> = OnceLock::new();
+    TOOL.get_or_init(|| ["7z", "7zz"].into_iter().find_map(Tool::detect))
+        .as_ref()
+}
+
+pub fn is_available() -> bool {
+    tool().is_some()
+}
+
+/// `7z <subcommand>` for the installed 7-Zip, see [`Tool::command`].
+fn seven_zip(subcommand: &str, password: Option<&str>) -> io::Result<Command> {
+    static MISSING: Tool = Tool {
+        program: "7z",
+        p7zip: false,
+    };
+    tool().unwrap_or(&MISSING).command(subcommand, password)
+}
+
+/// Run a command from `seven_zip` and collect its output, answering the
+/// password prompt if there is one.
+fn run(cmd: &mut Command, password: Option<&str>) -> io::Result<String> {
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let _ = send_password(&mut child, password);
+    let output = child.wait_with_output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if output.status.success() {
+        Ok(stdout.to_string())
+    } else {
+        Err(failed(&stdout, &String::from_utf8_lossy(&output.stderr)))
+    }
+}
+
+fn failed(stdout: &str, stderr: &str) -> io::Error {
+    let msg = if stderr.trim().is_empty() {
+        stdout.trim()
+    } else {
+        stderr.trim()
+    };
+    // Kept apart from damaged-archive errors, as the built-in backends do.
+    if msg.contains("Wrong password") {
+        return io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("wrong password: {}", msg),
+        );
+    }
+    io::Error::other(format!("7z failed: {}", msg))
+}
+
+/// Answer the password prompts [`Tool::command`] set up, if it piped stdin;
+/// twice, since `7z a` asks for it again to verify it.
+fn send_password(child: &mut Child, password: Option<&str>) -> io::Result<()> {
+    if let (Some(mut stdin), Some(p)) = (child.stdin.take(), password) {
+        write!(stdin, "{}\n{}\n", p, p)?;
+    }
+    Ok(())
+}
+
+/// Like `run`, for a command given `-bsp1`: the percentage 7z prints to
+/// stdout is fed into `progress`, and canceling kills the process. The
+/// password prompt, if there is one, is answered as in `run`.
+fn run_tracked(cmd: &mut Command, password: Option<&str>, progress: &Progress) -> io::Result<()> {
+    let (success, log, stderr) = run_logged(cmd, password, progress)?;
+    if success {
+        Ok(())
+    } else {
+        Err(failed(&log, &stderr))
+    }
+}
+
+/// The work of `run_tracked`, returning whether 7z succeeded along with
+/// the non-progress part of its stdout and its stderr.
+fn run_logged(
+    cmd: &mut Command,
+    password: Option<&str>,
+    progress: &Progress,
+) -> io::Result<(bool, String, String)> {
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    // 7z may exit before reading the prompt answer; its exit status says why.
+    let _ = send_password(&mut child, password);
+    let (tx, rx) = mpsc::channel();
+    let stdout = child.stdout.take().map(|mut out| {
+        thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            while let Ok(n) = out.read(&mut buf) {
+                if n == 0 || tx.send(buf[..n].to_vec()).is_err() {
+                    break;
+                }
+            }
+        })
+    });
+    let stderr = child.stderr.take().map(|mut err| {
+        thread::spawn(move || {
+            let mut text = String::new();
+            let _ = err.read_to_string(&mut text);
+            text
+        })
+    });
+
+    // 7z redraws its progress line with backspaces and carriage returns.
+    let mut pending = String::new();
+    let mut log = String::new();
+    loop {
+        if progress.is_canceled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            progress.check()?;
+        }
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(chunk) => pending.push_str(&String::from_utf8_lossy(&chunk)),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        while let Some(i) = pending.find(['\r', '\n', '\u{8}']) {
+            let line: String = pending.drain(..=i).collect();
+            match parse_progress(&line) {
+                Some((pct, count, name)) => progress.set_done(pct, count, name),
+                None if !line.trim().is_empty() => {
+                    log.push_str(line.trim());
+                    log.push('\n');
+                }
+                None => {}
+            }
+        }
+    }
+    log.push_str(&pending);
+    if let Some(t) = stdout {
+        let _ = t.join();
+    }
+    let stderr = stderr.and_then(|t| t.join().ok()).unwrap_or_default();
+    Ok((child.wait()?.success(), log, stderr))
+}
+
+/// Switches for `7z a` and `7z u`: the archive type, how entries are
+/// stored and compressed, and header encryption for password-protected 7z.
+fn add_switches(
+    cmd: &mut Command,
+    format: ArchiveFormat,
+    password: Option<&str>,
+    options: &CreateOptions,
+) -> io::Result<()> {
+    // -snl stores symlinks as links instead of following them.
+    cmd.arg(format!("-t{}", format.label()))
+        .arg("-snl")
+        .arg("-bsp1");
+    if let Some(method) = options.method {
+        let name = match method {
+            CompressionMethod::Store => "Copy",
+            CompressionMethod::Deflate => "Deflate",
+            CompressionMethod::Bzip2 => "BZip2",
+            CompressionMethod::Zstd => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "7z cannot write zstd-compressed entries",
+                ));
+            }
+        };
+        // Zip takes the method with -mm, 7z with -m0 (the first coder).
+        let switch = if format == ArchiveFormat::Zip {
+            "mm"
+        } else {
+            "m0"
+        };
+        cmd.arg(format!("-{}={}", switch, name));
+    }
+    if let Some(level) = options.level {
+        cmd.arg(format!("-mx={}", level));
+    }
+    if password.is_some() && format == ArchiveFormat::SevenZ {
+        cmd.arg("-mhe=on");
+    }
+    Ok(())
+}
+
+/// Add `output` and `sources` to `cmd`, run from the base directory if
+/// there is one so 7z stores paths relative to it, with the exclude
+/// patterns as `-x` switches.
+fn add_sources(
+    cmd: &mut Command,
+    output: &Path,
+    sources: &[&Path],
+    options: &CreateOptions,
+) -> io::Result<()> {
+    for pattern in &options.exclude {
+        let pattern = pattern.trim().trim_matches('/');
+        if pattern.is_empty() {
+            continue;
+        }
+        // -xr! matches a name at any depth, -x! a path from the top.
+        let recurse = if pattern.contains('/') { "" } else { "r" };
+        cmd.arg(format!("-x{}!{}", recurse, pattern));
+    }
+    let Some(base) = options.base() else {
+        cmd.arg(output).args(sources);
+        return Ok(());
+    };
+    cmd.current_dir(base).arg(std::path::absolute(output)?);
+    for source in sources {
+        let rel = source.strip_prefix(base).unwrap_or(source);
+        if rel.as_os_str().is_empty() {
+            cmd.arg(".");
+        } else {
+            cmd.arg(rel);
+        }
+    }
+    Ok(())
+}
+
+/// One `-bsp1` progress line such as ` 42% 7 - docs/a.txt`: the percentage,
+/// the number of files done and the file being worked on.
+fn parse_progress(line: &str) -> Option<(u64, Option<usize>, Option<&str>)> {
+    let (pct, rest) = line.trim().split_once('%')?;
+    let pct = pct.trim().parse().ok()?;
+    let mut rest = rest.trim().splitn(2, ' ');
+    let count = rest.next().and_then(|c| c.parse().ok());
+    // The name follows a one-letter operation marker (`-` extract, `+` add, `U` update).
+    let name = rest
+        .next()
+        .and_then(|r| r.split_once(' '))
+        .map(|(_, name)| name.trim())
+        .filter(|name| !name.is_empty());
+    Some((pct, count, name))
+}
+
+/// One entry of a `7z l -slt` listing, with what 7z says about symlinks.
+struct Listed {
+    entry: ArchiveEntry,
+    symlink: bool,
+    /// The link text, for formats that list it (tar); zip keeps it as the
+    /// entry's content.
+    link: Option<String>,
+}
+
+/// `7z l -slt`, with the password for archives whose headers are encrypted.
+fn list_entries(archive: &Path, password: Option<&str>) -> io::Result<Vec<Listed>> {
+    let mut cmd = seven_zip("l", password)?;
+    cmd.arg("-slt").arg("--").arg(archive);
+    let out = run(&mut cmd, password)?;
+    // Entries follow the "----------" line, one blank-line separated
+    // block each; the block before it describes the archive itself.
+    let body = out
+        .split_once("\n----------")
+        .map(|(_, rest)| rest)
+        .unwrap_or("");
+    Ok(body.split("\n\n").filter_map(parse_slt_block).collect())
+}
+
+/// Whether an `Attributes` value marks a symlink: either a Unix mode string
+/// such as `A_ lrwxrwxrwx`, or the raw hex value with the Unix mode in its
+/// high 16 bits (flagged by 0x8000).
+fn is_link_attribute(value: &str) -> bool {
+    value.split_whitespace().any(|word| {
+        if let Some(hex) = word.strip_prefix("0x") {
+            return u32::from_str_radix(hex, 16)
+                .is_ok_and(|a| a & 0x8000 != 0 && (a >> 16) & 0o170000 == 0o120000);
+        }
+        word.len() == 10 && word.starts_with('l')
+    })
+}
+
+/// Parse one `Key = Value` block of `7z l -slt` output.
+fn parse_slt_block(block: &str) -> Option<Listed> {
+    let mut e = ArchiveEntry::default();
+    let (mut symlink, mut link) = (false, None);
+    let mut has_path = false;
+    for line in block.lines() {
+        let Some((key, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        match key.trim() {
+            "Path" => {
+                e.path = entry::clean_path(&value.replace('\\', "/"));
+                has_path = true;
+            }
+            "Size" => e.size = value.parse().unwrap_or(0),
+            "Packed Size" => e.compressed_size = value.parse().ok(),
+            "Modified" => e.modified = parse_slt_time(value),
+            "Folder" => e.is_dir = value == "+",
+            "Attributes" => {
+                e.is_dir |= value.starts_with('D');
+                symlink |= is_link_attribute(value);
+            }
+            "Symbolic Link" if !value.is_empty() => {
+                symlink = true;
+                link = Some(value.to_string());
+            }
+            "Encrypted" => e.encrypted = value == "+",
+            "CRC" => e.crc32 = u32::from_str_radix(value, 16).ok(),
+            _ => {}
+        }
+    }
+    has_path.then_some(Listed {
+        entry: e,
+        symlink,
+        link,
+    })
+}
+
+/// `2024-01-31 12:34:56` or with a fractional second, in local time.
+fn parse_slt_time(value: &str) -> Option<std::time::SystemTime> {
+    let (date, time) = value.split_once(' ')?;
+    let mut d = date.split('-').map(|p| p.parse::<u32>().ok());
+    let mut t = time
+        .split('.')
+        .next()?
+        .split(':')
+        .map(|p| p.parse::<u32>().ok());
+    entry::from_local(
+        d.next()?? as i32,
+        d.next()??,
+        d.next()??,
+        t.next()??,
+        t.next()??,
+        t.next()??,
+    )
+}
+
+/// Create the symlink entry `e` pointing at `link` under `dest`, through the
+/// same checks the built-in backends apply.
+fn place_link(
+    dest: &Path,
+    e: &ArchiveEntry,
+    link: &str,
+    options: &ExtractOptions,
+    report: &mut ExtractReport,
+    progress: &Progress,
+) -> io::Result<()> {
+    let Some(target) = options.place(dest, &e.path, report) else {
+        return Ok(());
+    };
+    if link.is_empty() {
+        report.reject(&e.path, "symlink target is missing");
+        return Ok(());
+    }
+    if let Err(reason) = extract::link_inside(dest, &target, Path::new(link)) {
+        report.reject(&e.path, reason);
+        return Ok(());
+    }
+    if fs::symlink_metadata(&target).is_ok_and(|m| m.is_dir()) {
+        report.reject(&e.path, "a directory was extracted at the symlink's path");
+        return Ok(());
+    }
+    let Some(target) = report.claim(target, e.modified, options.policy) else {
+        return Ok(());
+    };
+    let staged = extract::prepare(&target, progress)?;
+    extract::make_symlink(link, &staged)?;
+    extract::commit(&staged, &target, progress)?;
+    report.extracted += 1;
+    Ok(())
+}
+
+impl ArchiveBackend for SevenZipBackend {
+    fn name(&self) -> &'static str {
+        "7z"
+    }
+
+    fn supports(&self, _format: ArchiveFormat) -> bool {
+        true
+    }
+
+    fn can_create(&self, format: ArchiveFormat) -> bool {
+        // 7z writes compressed tars as two separate steps, which we don't do.
+        matches!(
+            format,
+            ArchiveFormat::Zip | ArchiveFormat::Tar | ArchiveFormat::SevenZ
+        )
+    }
+
+    fn can_encrypt(&self, format: ArchiveFormat) -> bool {
+        matches!(format, ArchiveFormat::Zip | ArchiveFormat::SevenZ)
+    }
+
+    fn list(
+        &self,
+        archive: &Path,
+        _format: ArchiveFormat,
+        password: Option<&str>,
+    ) -> io::Result<Vec<ArchiveEntry>> {
+        Ok(list_entries(archive, password)?
+            .into_iter()
+            .map(|l| l.entry)
+            .collect())
+    }
+
+    fn extract(
+        &self,
+        archive: &Path,
+        format: ArchiveFormat,
+        dest: &Path,
+        password: Option<&str>,
+        options: &ExtractOptions,
+        progress: &Progress,
+    ) -> io::Result<ExtractReport> {
+        // Screen our own listing first: patterns then mean the same thing for
+        // every backend, and unsafe paths never reach 7z. Chosen entries are
+        // named literally (-spd) unless everything is wanted. Symlinks are
+        // never left to 7z, which would write later entries through them
+        // before anything could check where they point; they are made here.
+        let mut report = ExtractReport::default();
+        let mut names = Vec::new();
+        let mut links = Vec::new();
+        let mut everything = true;
+        for l in list_entries(archive, password)? {
+            let e = l.entry;
+            if e.is_dir {
+                continue;
+            }
+            if !options.selects(&e.path) || !options.screen(&e.path, &mut report) {
+                everything = false;
+            } else if l.symlink {
+                everything = false;
+                links.push((e, l.link));
+            } else {
+                names.push(e.path);
+            }
+        }
+        if names.is_empty() && links.is_empty() {
+            return Ok(report);
+        }
+        progress.set_total(100, Some(names.len() + links.len()));
+        extract::create_dirs(dest, progress)?;
+        if !names.is_empty() {
+            if everything {
+                names.clear();
+            }
+            // 7z cannot strip components or skip older files itself, so unpack
+            // into a hidden directory under `dest` and move files out from there.
+            let staging = dest.join(format!(".extract-{}", std::process::id()));
+            let mut cmd = seven_zip("x", password)?;
+            cmd.arg(format!("-o{}", staging.display()))
+                .arg("-y")
+                .arg("-spd")
+                .arg("-bsp1");
+            cmd.arg("--").arg(archive).args(&names);
+            let result = run_tracked(&mut cmd, password, progress).and_then(|_| {
+                extract::place_staged(&staging, dest, options, &mut report, progress)
+            });
+            let _ = fs::remove_dir_all(&staging);
+            result?;
+        }
+        for (e, link) in links {
+            progress.check()?;
+            let link = match link {
+                Some(link) => link,
+                None => {
+                    let mut text = Vec::new();
+                    self.read_entry(archive, format, &e.path, password, &mut text)?;
+                    String::from_utf8_lossy(&text).into_owned()
+                }
+            };
+            place_link(dest, &e, &link, options, &mut report, progress)?;
+        }
+        Ok(report)
+    }
+
+    fn test(
+        &self,
+        archive: &Path,
+        _format: ArchiveFormat,
+        password: Option<&str>,
+        progress: &Progress,
+    ) -> io::Result<TestReport> {
+        let names: Vec<String> = list_entries(archive, password)?
+            .into_iter()
+            .filter(|l| !l.entry.is_dir)
+            .map(|l| l.entry.path)
+            .collect();
+        progress.set_total(100, Some(names.len()));
+        let mut cmd = seven_zip("t", password)?;
+        cmd.arg("-bsp1").arg("--").arg(archive);
+        let (success, log, stderr) = run_logged(&mut cmd, password, progress)?;
+
+        // 7z names each damaged file on a line such as
+        // `ERROR: CRC Failed : docs/a.txt`.
+        let errors: Vec<&str> = log
+            .lines()
+            .chain(stderr.lines())
+            .map(str::trim)
+            .filter(|l| l.starts_with("ERROR"))
+            .collect();
+        let mut report = TestReport::default();
+        for name in &names {
+            let error = errors.iter().find(|l| {
+                l.strip_suffix(name.as_str())
+                    .is_some_and(|rest| rest.ends_with([' ', ':']))
+            });
+            match error {
+                Some(line) => {
+                    let reason = line[..line.len() - name.len()]
+                        .trim_start_matches("ERROR:")
+                        .trim_matches([' ', ':']);
+                    report.fail(name, reason, progress);
+                }
+                None => report.pass(name, progress),
+            }
+        }
+        // A failure 7z did not pin on an entry is about the whole archive.
+        if !success && report.failed.is_empty() {
+            return Err(failed(&log, &stderr));
+        }
+        Ok(report)
+    }
+
+    fn read_entry(
+        &self,
+        archive: &Path,
+        _format: ArchiveFormat,
+        name: &str,
+        password: Option<&str>,
+        out: &mut dyn Write,
+    ) -> io::Result<u64> {
+        // -so writes the entry to stdout; -spd makes the name literal, not a wildcard.
+        let mut cmd = seven_zip("e", password)?;
+        cmd.arg("-so").arg("-spd");
+        let mut child = cmd
+            .arg("--")
+            .arg(archive)
+            .arg(name)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let _ = send_password(&mut child, password);
+        let copied = match child.stdout.as_mut() {
+            Some(stdout) => io::copy(stdout, out),
+            None => Ok(0),
+        };
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(failed("", &String::from_utf8_lossy(&output.stderr)));
+        }
+        copied
+    }
+
+    fn create(
+        &self,
+        output: &Path,
+        format: ArchiveFormat,
+        sources: &[&Path],
+        password: Option<&str>,
+        options: &CreateOptions,
+        progress: &Progress,
+    ) -> io::Result<()> {
+        progress.set_total(100, None);
+        let mut cmd = seven_zip("a", password)?;
+        add_switches(&mut cmd, format, password, options)?;
+        add_sources(&mut cmd, output, sources, options)?;
+        run_tracked(&mut cmd, password, progress)
+    }
+
+    fn update(
+        &self,
+        archive: &Path,
+        format: ArchiveFormat,
+        sources: &[&Path],
+        password: Option<&str>,
+        options: &UpdateOptions,
+        progress: &Progress,
+    ) -> io::Result<UpdateReport> {
+        if options.compare != ChangeCheck::Mtime {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "7z only finds changed files by mtime; use the built-in backend to compare hashes",
+            ));
+        }
+        // Plan from our own listing, so the report reads like the built-in
+        // backends' and deletions name exact entries rather than 7z wildcards.
+        let entries: Vec<ArchiveEntry> = list_entries(archive, password)?
+            .into_iter()
+            .map(|l| l.entry)
+            .collect();
+        let walked = walk_sources(sources, &options.create)?;
+        let mut report = UpdateReport::default();
+        for e in &entries {
+            if options.deletes(&e.path) {
+                report.deleted.push(e.path.clone());
+                continue;
+            }
+            let source = walked.iter().find(|s| s.name == e.path);
+            match source {
+                Some(s)
+                    if update::changed(ChangeCheck::Mtime, s, e.size, e.modified, || {
+                        Ok(String::new())
+                    })? =>
+                {
+                    report.replaced.push(e.path.clone())
+                }
+                _ => report.unchanged += 1,
+            }
+        }
+        report.added = walked
+            .iter()
+            .filter(|s| !entries.iter().any(|e| e.path == s.name))
+            .count();
+
+        // 7z changes archives in place, so it works on a copy.
+        rewrite(archive, |output| {
+            fs::copy(archive, output)?;
+            if !report.deleted.is_empty() {
+                progress.set_total(100, Some(report.deleted.len()));
+                let mut cmd = seven_zip("d", password)?;
+                cmd.arg("-bsp1").arg("-spd");
+                cmd.arg("--").arg(output).args(&report.deleted);
+                run_tracked(&mut cmd, password, progress)?;
+            }
+            if !sources.is_empty() {
+                progress.set_total(100, None);
+                // `u` adds missing files and replaces those newer than their entries.
+                let mut cmd = seven_zip("u", password)?;
+                add_switches(&mut cmd, format, password, &options.create)?;
+                add_sources(&mut cmd, output, sources, &options.create)?;
+                run_tracked(&mut cmd, password, progress)?;
+            }
+            Ok(())
+        })?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::atomic::AtomicBool;
+
+    use super::*;
+    use crate::archive::{
+        BackendChoice, create_archive, extract_archive, list_archive, test_archive,
+    };
+
+    #[test]
+    fn slt_blocks_mark_symlinks() {
+        let tar = parse_slt_block("Path = a/link\nSize = 0\nSymbolic Link = ../b\n").unwrap();
+        assert!(tar.symlink);
+        assert_eq!(tar.link.as_deref(), Some("../b"));
+        let zip = parse_slt_block("Path = link\nAttributes = A_ lrwxrwxrwx\n").unwrap();
+        assert!(zip.symlink && zip.link.is_none());
+        let hex = parse_slt_block("Path = link\nAttributes = 0xA1FF8020\n").unwrap();
+        assert!(hex.symlink);
+        let file = parse_slt_block("Path = f\nAttributes = A_ -rw-r--r--\n").unwrap();
+        assert!(!file.symlink);
+        assert_eq!(file.entry.path, "f");
+    }
+
+    /// Each 7-Zip port that is installed takes the password without a
+    /// terminal prompt, even for an archive whose headers are encrypted, or
+    /// refuses it rather than putting it on the command line.
+    #[test]
+    fn every_installed_port_takes_the_password() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("a.txt");
+        fs::write(&source, "secret").unwrap();
+        for program in ["7z", "7zz", "7za"] {
+            let Some(tool) = Tool::detect(program) else {
+                continue;
+            };
+            if tool.p7zip {
+                let err = tool.command("a", Some("pw")).unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::Unsupported, "{}", program);
+                continue;
+            }
+            let archive = tmp.path().join(format!("{}.7z", program));
+            let mut cmd = tool.command("a", Some("pw")).unwrap();
+            cmd.arg("-t7z").arg("-mhe=on").arg(&archive).arg(&source);
+            run(&mut cmd, Some("pw")).unwrap();
+
+            let mut cmd = tool.command("e", Some("pw")).unwrap();
+            cmd.arg("-so").arg("--").arg(&archive).arg("a.txt");
+            assert_eq!(run(&mut cmd, Some("pw")).unwrap(), "secret", "{}", program);
+            let mut cmd = tool.command("l", Some("wrong")).unwrap();
+            cmd.arg("--").arg(&archive);
+            let err = run(&mut cmd, Some("wrong")).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied, "{}", program);
+        }
+    }
+
+    #[test]
+    fn password_7z_round_trip() {
+        if tool().is_none_or(|t| t.p7zip) {
+            return;
+        }
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("docs");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("a.txt"), "secret").unwrap();
+        let archive = tmp.path().join("docs.7z");
+        let (archive, src) = (archive.to_str().unwrap(), src.to_str().unwrap());
+        let cancel = AtomicBool::new(false);
+        create_archive(
+            &[src],
+            archive,
+            Some("pw"),
+            &CreateOptions::default(),
+            BackendChoice::SevenZip,
+            &mut |_| {},
+            &cancel,
+        )
+        .unwrap();
+
+        let dest = tmp.path().join("out");
+        let extract = |password| {
+            extract_archive(
+                archive,
+                dest.to_str().unwrap(),
+                password,
+                &ExtractOptions::default(),
+                BackendChoice::SevenZip,
+                &mut |_| {},
+                &cancel,
+            )
+        };
+        // The headers are encrypted, so even listing needs the password.
+        assert!(extract(None).is_err());
+        extract(Some("pw")).unwrap();
+        assert_eq!(
+            fs::read_to_string(dest.join("docs/a.txt")).unwrap(),
+            "secret"
+        );
+        let tested = test_archive(
+            archive,
+            Some("pw"),
+            BackendChoice::SevenZip,
+            &mut |_| {},
+            &cancel,
+        )
+        .unwrap();
+        assert!(tested.contains("1 entries OK, 0 failed"), "{}", tested);
+
+        assert!(list_archive(archive, None, BackendChoice::SevenZip).is_err());
+        let mut names: Vec<_> = list_archive(archive, Some("pw"), BackendChoice::SevenZip)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["docs", "docs/a.txt"]);
+    }
+}
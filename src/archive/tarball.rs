@@ -0,0 +1,747 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use flate2::Compression;
+use tar::{Archive, Builder, EntryType, Header};
+
+use super::entry::{self, ArchiveEntry};
+use super::extract::{self, ExtractOptions, ExtractReport};
+use super::progress::{Progress, Tracked};
+use super::update::{self, UpdateOptions, UpdateReport};
+use super::verify::TestReport;
+use super::{ArchiveBackend, ArchiveFormat, CreateOptions, SourceEntry, rewrite, walk_sources};
+use crate::hash::{DigestWriter, HashAlgo};
+
+/// Built-in tar backend, with gzip, bzip2, xz and zstd compression.
+pub struct TarBackend;
+
+/// Wrap `file` in the decompressor for `format`.
+pub(super) fn decoder<'r>(
+    format: ArchiveFormat,
+    file: impl Read + 'r,
+) -> io::Result<Box<dyn Read + 'r>> {
+    Ok(match format {
+        ArchiveFormat::TarGz => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        ArchiveFormat::TarBz2 => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+        ArchiveFormat::TarXz => Box::new(xz2::read::XzDecoder::new_multi_decoder(file)),
+        ArchiveFormat::TarZst => Box::new(zstd::stream::read::Decoder::new(file)?),
+        _ => Box::new(file),
+    })
+}
+
+/// Compressing writer that must be finished explicitly to write its trailer.
+enum Encoder {
+    Plain(File),
+    Gz(flate2::write::GzEncoder<File>),
+    Bz2(bzip2::write::BzEncoder<File>),
+    Xz(xz2::write::XzEncoder<File>),
+    Zst(zstd::stream::write::Encoder<'static, File>),
+}
+
+impl Encoder {
+    /// `level` is the compressor's own scale; `None` uses its default.
+    fn new(format: ArchiveFormat, file: File, level: Option<u32>) -> io::Result<Encoder> {
+        Ok(match format {
+            ArchiveFormat::TarGz => Encoder::Gz(flate2::write::GzEncoder::new(
+                file,
+                level.map(Compression::new).unwrap_or_default(),
+            )),
+            ArchiveFormat::TarBz2 => Encoder::Bz2(bzip2::write::BzEncoder::new(
+                file,
+                level.map(bzip2::Compression::new).unwrap_or_default(),
+            )),
+            ArchiveFormat::TarXz => {
+                Encoder::Xz(xz2::write::XzEncoder::new(file, level.unwrap_or(6)))
+            }
+            ArchiveFormat::TarZst => Encoder::Zst(zstd::stream::write::Encoder::new(
+                file,
+                level.map_or(0, |l| l as i32),
+            )?),
+            _ => Encoder::Plain(file),
+        })
+    }
+
+    fn finish(self) -> io::Result<File> {
+        match self {
+            Encoder::Plain(f) => Ok(f),
+            Encoder::Gz(e) => e.finish(),
+            Encoder::Bz2(e) => e.finish(),
+            Encoder::Xz(e) => e.finish(),
+            Encoder::Zst(e) => e.finish(),
+        }
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(f) => f.write(buf),
+            Encoder::Gz(e) => e.write(buf),
+            Encoder::Bz2(e) => e.write(buf),
+            Encoder::Xz(e) => e.write(buf),
+            Encoder::Zst(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(f) => f.flush(),
+            Encoder::Gz(e) => e.flush(),
+            Encoder::Bz2(e) => e.flush(),
+            Encoder::Xz(e) => e.flush(),
+            Encoder::Zst(e) => e.flush(),
+        }
+    }
+}
+
+/// Writes a tar stream in GNU format. Every entry gets its header from the
+/// source's own metadata and is stored under its archive name; names and
+/// link targets too long for the header go into GNU long-name records.
+/// Symlinks are stored as links, and a file with several hard links is
+/// stored once, its other names as hard links to the first.
+struct TarWriter {
+    builder: Builder<Encoder>,
+    /// Name each multiply-linked file was first stored under, by device and inode.
+    stored: HashMap<(u64, u64), String>,
+}
+
+impl TarWriter {
+    fn new(format: ArchiveFormat, file: File, level: Option<u32>) -> io::Result<TarWriter> {
+        Ok(TarWriter {
+            builder: Builder::new(Encoder::new(format, file, level)?),
+            stored: HashMap::new(),
+        })
+    }
+
+    /// Append `entry`, streaming file contents so they are counted.
+    fn add(&mut self, entry: &SourceEntry, progress: &Progress) -> io::Result<()> {
+        progress.start_entry(&entry.name)?;
+        let mut header = Header::new_gnu();
+        header.set_metadata(&entry.meta);
+        let kind = entry.meta.file_type();
+        if kind.is_symlink() {
+            let target = fs::read_link(&entry.path)?;
+            return self.builder.append_link(&mut header, &entry.name, target);
+        }
+        if kind.is_file() {
+            if let Some(id) = link_id(&entry.meta) {
+                match self.stored.entry(id) {
+                    Entry::Occupied(first) => {
+                        header.set_entry_type(EntryType::Link);
+                        header.set_size(0);
+                        progress.advance(entry.meta.len())?;
+                        return self
+                            .builder
+                            .append_link(&mut header, &entry.name, first.get());
+                    }
+                    Entry::Vacant(slot) => {
+                        slot.insert(entry.name.clone());
+                    }
+                }
+            }
+            let source = Tracked::new(File::open(&entry.path)?, progress);
+            return self.builder.append_data(&mut header, &entry.name, source);
+        }
+        if !kind.is_dir() && !special_header(&mut header, &entry.meta) {
+            progress.note(format!(
+                "Skipped {}: sockets cannot be archived",
+                entry.name
+            ));
+            return Ok(());
+        }
+        // Directories, FIFOs and devices have no data.
+        self.builder
+            .append_data(&mut header, &entry.name, io::empty())
+    }
+
+    fn finish(self) -> io::Result<()> {
+        self.builder.into_inner()?.finish()?.sync_all()
+    }
+}
+
+/// Device and inode of a file with more than one hard link.
+/// Reader that also hashes everything read through it.
+struct Digesting<'d, R> {
+    inner: R,
+    digest: &'d mut DigestWriter,
+}
+
+impl<R: Read> Read for Digesting<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.digest.write_all(&buf[..n])?;
+        Ok(n)
+    }
+}
+
+#[cfg(unix)]
+fn link_id(meta: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (meta.nlink() > 1).then(|| (meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn link_id(_meta: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Fill in the device numbers of a character or block device; false for
+/// a socket, which tar has no entry type for. FIFOs need nothing more.
+#[cfg(unix)]
+fn special_header(header: &mut Header, meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+    let kind = meta.file_type();
+    if kind.is_socket() {
+        return false;
+    }
+    if kind.is_char_device() || kind.is_block_device() {
+        // glibc's encoding of major and minor numbers in dev_t.
+        let dev = meta.rdev();
+        let major = ((dev >> 32) & 0xffff_f000) | ((dev >> 8) & 0x0000_0fff);
+        let minor = ((dev >> 12) & 0xffff_ff00) | (dev & 0x0000_00ff);
+        // GNU headers always have the fields, and the numbers fit them.
+        let _ = header.set_device_major(major as u32);
+        let _ = header.set_device_minor(minor as u32);
+    }
+    true
+}
+
+#[cfg(not(unix))]
+fn special_header(_header: &mut Header, _meta: &fs::Metadata) -> bool {
+    false
+}
+
+fn open(archive: &Path, format: ArchiveFormat) -> io::Result<Archive<Box<dyn Read>>> {
+    Ok(Archive::new(decoder(format, File::open(archive)?)?))
+}
+
+impl ArchiveBackend for TarBackend {
+    fn name(&self) -> &'static str {
+        "built-in tar"
+    }
+
+    fn supports(&self, format: ArchiveFormat) -> bool {
+        format.is_tar()
+    }
+
+    fn can_encrypt(&self, _format: ArchiveFormat) -> bool {
+        false
+    }
+
+    fn list(
+        &self,
+        archive: &Path,
+        format: ArchiveFormat,
+        _password: Option<&str>,
+    ) -> io::Result<Vec<ArchiveEntry>> {
+        let mut entries = Vec::new();
+        for e in open(archive, format)?.entries()? {
+            let e = e?;
+            let header = e.header();
+            // Tar compresses the stream as a whole and stores no per-entry CRC.
+            entries.push(ArchiveEntry {
+                path: entry::clean_path(&e.path()?.to_string_lossy()),
+                size: e.size(),
+                compressed_size: None,
+                modified: header.mtime().ok().map(entry::from_unix),
+                is_dir: header.entry_type().is_dir(),
+                encrypted: false,
+                crc32: None,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn extract(
+        &self,
+        archive: &Path,
+        format: ArchiveFormat,
+        dest: &Path,
+        _password: Option<&str>,
+        options: &ExtractOptions,
+        progress: &Progress,
+    ) -> io::Result<ExtractReport> {
+        let mut report = ExtractReport::default();
+        // The entry count is unknown until the end of the stream, so measure
+        // progress by how much of the (compressed) file has been read.
+        let file = File::open(archive)?;
+        progress.set_total(file.metadata()?.len(), None);
+        let mut tar = Archive::new(decoder(format, Tracked::new(file, progress))?);
+        extract::create_dirs(dest, progress)?;
+        for e in tar.entries()? {
+            let mut e = e?;
+            let path = entry::clean_path(&e.path()?.to_string_lossy());
+            if !options.selects(&path) {
+                continue;
+            }
+            let Some(target) = options.place(dest, &path, &mut report) else {
+                continue;
+            };
+            let kind = e.header().entry_type();
+            if kind.is_dir() {
+                extract::create_dirs(&target, progress)?;
+                continue;
+            }
+            progress.start_entry(&path)?;
+            if kind.is_symlink()
+                && let Some(link) = e.link_name()?
+                && let Err(reason) = extract::link_inside(dest, &target, &link)
+            {
+                report.reject(&path, reason);
+                continue;
+            }
+            // Link names are archive paths; resolve them under `dest` like any entry.
+            let link_source = if kind.is_hard_link() {
+                let mut scratch = ExtractReport::default();
+                let source = e.link_name()?.and_then(|l| {
+                    options.place(dest, &entry::clean_path(&l.to_string_lossy()), &mut scratch)
+                });
+                match source {
+                    Some(source) => Some(source),
+                    None => {
+                        report.reject(&path, "hard link target is unsafe or missing");
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
+            let modified = e.header().mtime().ok().map(entry::from_unix);
+            let Some(target) = report.claim(target, modified, options.policy) else {
+                continue;
+            };
+            let staged = extract::prepare(&target, progress)?;
+            match link_source {
+                Some(source) => fs::hard_link(source, &staged)?,
+                None => {
+                    e.unpack(&staged)?;
+                }
+            }
+            extract::commit(&staged, &target, progress)?;
+            report.extracted += 1;
+        }
+        Ok(report)
+    }
+
+    fn test(
+        &self,
+        archive: &Path,
+        format: ArchiveFormat,
+        _password: Option<&str>,
+        progress: &Progress,
+    ) -> io::Result<TestReport> {
+        // Tar checksums only its headers; the data is covered by the
+        // compressor's own checks (gzip CRC, xz and zstd checksums), which
+        // are verified as the stream is read to its end.
+        let mut report = TestReport::default();
+        let file = File::open(archive)?;
+        progress.set_total(file.metadata()?.len(), None);
+        let mut tar = Archive::new(decoder(format, Tracked::new(file, progress))?);
+        // The entry being read, or a description of where an unreadable
+        // header sits.
+        let mut current = "first entry".to_string();
+        for e in tar.entries()? {
+            // Directories are checked for a valid header but, as for zip,
+            // only the files are reported.
+            let result = e.and_then(|mut e| {
+                current = entry::clean_path(&e.path()?.to_string_lossy());
+                if e.header().entry_type().is_dir() {
+                    return Ok(false);
+                }
+                progress.start_entry(&current)?;
+                io::copy(&mut e, &mut io::sink()).map(|_| true)
+            });
+            match result {
+                Ok(is_file) => {
+                    if is_file {
+                        report.pass(&current, progress);
+                    }
+                    current = format!("entry after {}", current);
+                }
+                Err(e) if progress.is_canceled() => return Err(e),
+                Err(e) => {
+                    // Nothing after a bad header or a broken stream can be trusted.
+                    report.fail(&current, e, progress);
+                    report.incomplete = true;
+                    return Ok(report);
+                }
+            }
+        }
+        // Read past the end-of-archive blocks so the compressor's trailer is checked.
+        if let Err(e) = io::copy(&mut tar.into_inner(), &mut io::sink()) {
+            if progress.is_canceled() {
+                return Err(e);
+            }
+            report.fail("compressed stream", e, progress);
+        }
+        Ok(report)
+    }
+
+    fn read_entry(
+        &self,
+        archive: &Path,
+        format: ArchiveFormat,
+        name: &str,
+        _password: Option<&str>,
+        out: &mut dyn Write,
+    ) -> io::Result<u64> {
+        // Tar has no index, so this reads (and decompresses) up to the entry.
+        for e in open(archive, format)?.entries()? {
+            let mut e = e?;
+            if !e.header().entry_type().is_dir()
+                && entry::clean_path(&e.path()?.to_string_lossy()) == name
+            {
+                return io::copy(&mut e, out);
+            }
+        }
+        Err(entry::not_found(name))
+    }
+
+    fn create(
+        &self,
+        output: &Path,
+        format: ArchiveFormat,
+        sources: &[&Path],
+        _password: Option<&str>,
+        options: &CreateOptions,
+        progress: &Progress,
+    ) -> io::Result<()> {
+        let entries = walk_sources(sources, options)?;
+        progress.count_sources(&entries);
+        let mut tar = TarWriter::new(format, File::create(output)?, options.level)?;
+        for entry in &entries {
+            tar.add(entry, progress)?;
+        }
+        tar.finish()
+    }
+
+    fn update(
+        &self,
+        archive: &Path,
+        format: ArchiveFormat,
+        sources: &[&Path],
+        _password: Option<&str>,
+        options: &UpdateOptions,
+        progress: &Progress,
+    ) -> io::Result<UpdateReport> {
+        let sources = walk_sources(sources, &options.create)?;
+        progress.count_sources(&sources);
+        let by_name: HashMap<&str, &SourceEntry> =
+            sources.iter().map(|s| (s.name.as_str(), s)).collect();
+        let mut report = UpdateReport::default();
+        let mut present = HashSet::new();
+        // Hard links that are copied as they are, by the entry they link to:
+        // if that entry is deleted or replaced, its old data goes to the
+        // first of them instead and the others link to that.
+        let mut kept_links: HashMap<String, String> = HashMap::new();
+        for e in open(archive, format)?.entries()? {
+            let e = e?;
+            if !e.header().entry_type().is_hard_link() {
+                continue;
+            }
+            let path = entry::clean_path(&e.path()?.to_string_lossy());
+            if let Some(target) = e.link_name()?
+                && !options.deletes(&path)
+                && !by_name.contains_key(path.as_str())
+            {
+                kept_links
+                    .entry(entry::clean_path(&target.to_string_lossy()))
+                    .or_insert(path);
+            }
+        }
+        // Entries whose data was moved to one of their links, and where to.
+        let mut moved: HashMap<String, String> = HashMap::new();
+        // A tarball is compressed as a whole, so every kept entry is
+        // decompressed and written again.
+        rewrite(archive, |output| {
+            let mut old = open(archive, format)?;
+            let mut tar = TarWriter::new(format, File::create(output)?, options.create.level)?;
+            for e in old.entries()? {
+                progress.check()?;
+                let mut e = e?;
+                let name = e.path()?.into_owned();
+                let path = entry::clean_path(&name.to_string_lossy());
+                present.insert(path.clone());
+                let mut header = e.header().clone();
+                let heir = kept_links
+                    .get(&path)
+                    .filter(|_| !header.entry_type().is_hard_link());
+                if options.deletes(&path) {
+                    if let Some(heir) = heir {
+                        tar.builder.append_data(&mut header, heir, &mut e)?;
+                        moved.insert(path.clone(), heir.clone());
+                    }
+                    report.deleted.push(path);
+                    continue;
+                }
+                if let Some(source) = by_name.get(path.as_str()) {
+                    // A hard link has no data of its own; it stays as long as
+                    // the file is still linked to its target's source, whose
+                    // own entry is checked for changes.
+                    if header.entry_type().is_hard_link() {
+                        let target = e
+                            .link_name()?
+                            .map(|l| entry::clean_path(&l.to_string_lossy()));
+                        let still_linked = target
+                            .and_then(|t| by_name.get(t.as_str()))
+                            .is_some_and(|t| {
+                                link_id(&source.meta).is_some_and(|id| link_id(&t.meta) == Some(id))
+                            });
+                        if !still_linked {
+                            tar.add(source, progress)?;
+                            report.replaced.push(path);
+                            continue;
+                        }
+                    } else {
+                        let (size, modified) =
+                            (e.size(), header.mtime().ok().map(entry::from_unix));
+                        let mut read = false;
+                        let changed =
+                            update::changed(options.compare, source, size, modified, || {
+                                read = true;
+                                let mut digest = DigestWriter::new(HashAlgo::Crc32);
+                                match heir {
+                                    // The data can only be read once, so it is
+                                    // stored for the links while being compared.
+                                    Some(heir) => tar.builder.append_data(
+                                        &mut header.clone(),
+                                        heir,
+                                        Digesting {
+                                            inner: &mut e,
+                                            digest: &mut digest,
+                                        },
+                                    )?,
+                                    None => {
+                                        io::copy(&mut e, &mut digest)?;
+                                    }
+                                }
+                                Ok(digest.finalize_hex())
+                            })?;
+                        if let Some(heir) = heir
+                            && (changed || read)
+                        {
+                            if !read {
+                                tar.builder.append_data(&mut header.clone(), heir, &mut e)?;
+                            }
+                            moved.insert(path.clone(), heir.clone());
+                        }
+                        if changed {
+                            tar.add(source, progress)?;
+                            report.replaced.push(path);
+                            continue;
+                        }
+                        if read {
+                            // The entry's data was used up comparing it with the
+                            // file, which holds the same bytes.
+                            tar.builder.append_data(
+                                &mut header,
+                                &name,
+                                File::open(&source.path)?,
+                            )?;
+                            report.unchanged += 1;
+                            continue;
+                        }
+                    }
+                }
+                let kind = header.entry_type();
+                match e.link_name()?.map(|l| l.into_owned()) {
+                    Some(link) if kind.is_hard_link() => {
+                        match moved.get(&entry::clean_path(&link.to_string_lossy())) {
+                            // Already written with the data of its target.
+                            Some(heir) if *heir == path => {}
+                            Some(heir) => tar.builder.append_link(&mut header, &name, heir)?,
+                            None => tar.builder.append_link(&mut header, &name, link)?,
+                        }
+                    }
+                    Some(link) if kind.is_symlink() => {
+                        tar.builder.append_link(&mut header, &name, link)?
+                    }
+                    _ => tar.builder.append_data(&mut header, &name, &mut e)?,
+                }
+                report.unchanged += 1;
+            }
+            for source in sources.iter().filter(|s| !present.contains(&s.name)) {
+                tar.add(source, progress)?;
+                report.added += 1;
+            }
+            tar.finish()
+        })?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use super::*;
+    use crate::archive::update::ChangeCheck;
+
+    #[test]
+    fn create_stores_clean_paths_links_and_long_names() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base = tmp.path().join("proj");
+        let deep = format!("{}/{}", "d".repeat(80), "e".repeat(80));
+        fs::create_dir_all(base.join(&deep)).unwrap();
+        fs::create_dir_all(base.join("target/debug")).unwrap();
+        fs::create_dir_all(base.join(".git")).unwrap();
+        fs::write(base.join(&deep).join("notes.txt"), "deep").unwrap();
+        fs::write(base.join("target/debug/app"), "binary").unwrap();
+        fs::write(base.join(".git/HEAD"), "ref").unwrap();
+        fs::write(base.join("main.rs"), "fn main() {}").unwrap();
+        fs::write(base.join("main.o"), "object").unwrap();
+        fs::hard_link(base.join("main.rs"), base.join("same.rs")).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(format!("{}/notes.txt", deep), base.join("notes")).unwrap();
+
+        let output = tmp.path().join("out.tar");
+        let options = CreateOptions {
+            base_dir: Some(base.clone()),
+            exclude: vec!["target/".into(), ".git".into(), "*.o".into()],
+            ..CreateOptions::default()
+        };
+        let cancel = AtomicBool::new(false);
+        let mut report = |_| {};
+        let progress = Progress::new("Archiving", &mut report, &cancel);
+        TarBackend
+            .create(
+                &output,
+                ArchiveFormat::Tar,
+                &[&base],
+                None,
+                &options,
+                &progress,
+            )
+            .unwrap();
+
+        let mut entries = HashMap::new();
+        for e in Archive::new(File::open(&output).unwrap())
+            .entries()
+            .unwrap()
+        {
+            let e = e.unwrap();
+            let path = e.path().unwrap().to_string_lossy().to_string();
+            let link = e
+                .link_name()
+                .unwrap()
+                .map(|l| l.to_string_lossy().to_string());
+            entries.insert(path, (e.header().entry_type(), link));
+        }
+        let mut names: Vec<_> = entries.keys().cloned().collect();
+        names.sort();
+        assert!(names.iter().all(|n| !n.starts_with("target")
+            && !n.starts_with(".git")
+            && !n.ends_with(".o")
+            && !n.starts_with('/')
+            && !n.starts_with("proj")));
+        assert!(
+            entries.contains_key(&format!("{}/notes.txt", deep)),
+            "{:?}",
+            names
+        );
+        // Files are walked by name, so main.rs is stored first.
+        assert_eq!(
+            entries["same.rs"],
+            (EntryType::Link, Some("main.rs".to_string()))
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            entries["notes"],
+            (EntryType::Symlink, Some(format!("{}/notes.txt", deep)))
+        );
+    }
+
+    /// Type, link target and data of every entry in a plain tarball.
+    fn read_tar(path: &Path) -> HashMap<String, (EntryType, Option<String>, String)> {
+        let mut entries = HashMap::new();
+        for e in Archive::new(File::open(path).unwrap()).entries().unwrap() {
+            let mut e = e.unwrap();
+            let path = e.path().unwrap().to_string_lossy().to_string();
+            let link = e
+                .link_name()
+                .unwrap()
+                .map(|l| l.to_string_lossy().to_string());
+            let mut data = String::new();
+            e.read_to_string(&mut data).unwrap();
+            entries.insert(path, (e.header().entry_type(), link, data));
+        }
+        entries
+    }
+
+    #[test]
+    fn update_keeps_hard_links_whose_target_goes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = tmp.path().join("a.txt");
+        let output = tmp.path().join("out.tar");
+        let write_tar = || {
+            let mut tar = Builder::new(File::create(&output).unwrap());
+            let mut header = Header::new_gnu();
+            header.set_size(4);
+            header.set_mode(0o644);
+            tar.append_data(&mut header, "a.txt", &b"OLD!"[..]).unwrap();
+            for link in ["b.txt", "c.txt"] {
+                let mut header = Header::new_gnu();
+                header.set_entry_type(EntryType::Link);
+                header.set_size(0);
+                tar.append_link(&mut header, link, "a.txt").unwrap();
+            }
+            tar.finish().unwrap();
+        };
+        let cancel = AtomicBool::new(false);
+        let mut report = |_| {};
+        let progress = Progress::new("Updating", &mut report, &cancel);
+
+        let deleted = UpdateOptions {
+            delete: vec!["a.txt".to_string()],
+            ..UpdateOptions::default()
+        };
+        let hashed = UpdateOptions {
+            compare: ChangeCheck::Hash,
+            ..UpdateOptions::default()
+        };
+        // Deleted; replaced as told by size, or by a hash of the old data;
+        // and unchanged, which also reads the old data.
+        for (options, new, a) in [
+            (&deleted, None, None),
+            (
+                &UpdateOptions::default(),
+                Some("NEW DATA"),
+                Some("NEW DATA"),
+            ),
+            (&hashed, Some("NEW!"), Some("NEW!")),
+            (&hashed, Some("OLD!"), Some("OLD!")),
+        ] {
+            write_tar();
+            let sources: Vec<&Path> = match new {
+                Some(data) => {
+                    fs::write(&source, data).unwrap();
+                    vec![&source]
+                }
+                None => vec![],
+            };
+            TarBackend
+                .update(
+                    &output,
+                    ArchiveFormat::Tar,
+                    &sources,
+                    None,
+                    options,
+                    &progress,
+                )
+                .unwrap();
+            let entries = read_tar(&output);
+            assert_eq!(entries.get("a.txt").map(|e| e.2.as_str()), a);
+            assert_eq!(
+                entries["b.txt"],
+                (EntryType::Regular, None, "OLD!".to_string())
+            );
+            assert_eq!(
+                entries["c.txt"],
+                (EntryType::Link, Some("b.txt".to_string()), String::new())
+            );
+        }
+    }
+}
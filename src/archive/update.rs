@@ -0,0 +1,121 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use super::{CreateOptions, SourceEntry, extract};
+use crate::hash::{DigestWriter, HashAlgo};
+
+/// How an update decides that a source file differs from the entry it
+/// would replace. A different size always counts as a change.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChangeCheck {
+    /// The file is newer than the entry, as `zip -u` and `7z u` decide.
+    #[default]
+    Mtime,
+    /// The file's CRC-32 differs from the entry's.
+    Hash,
+}
+
+impl ChangeCheck {
+    pub const ALL: [ChangeCheck; 2] = [ChangeCheck::Mtime, ChangeCheck::Hash];
+
+    pub fn parse(s: &str) -> Option<ChangeCheck> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mtime" | "time" | "newer" => Some(ChangeCheck::Mtime),
+            "hash" | "crc" | "crc32" | "content" => Some(ChangeCheck::Hash),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChangeCheck::Mtime => "newer mtime",
+            ChangeCheck::Hash => "content hash",
+        }
+    }
+}
+
+/// What updating an existing archive does besides adding missing entries.
+#[derive(Clone, Debug, Default)]
+pub struct UpdateOptions {
+    /// How changed files are told apart from unchanged ones.
+    pub compare: ChangeCheck,
+    /// Entry paths or patterns (`*`, `?`) to remove; a pattern that matches
+    /// a directory removes everything under it.
+    pub delete: Vec<String>,
+    /// How added and replaced entries are written. The format is the
+    /// archive's own, so `format` is ignored.
+    pub create: CreateOptions,
+}
+
+impl UpdateOptions {
+    pub(super) fn deletes(&self, path: &str) -> bool {
+        !self.delete.is_empty() && extract::matches_any(&self.delete, path)
+    }
+}
+
+/// What an update did.
+#[derive(Debug, Default)]
+pub struct UpdateReport {
+    /// Entries that were not in the archive before.
+    pub added: usize,
+    /// Entries written again from a changed file.
+    pub replaced: Vec<String>,
+    /// Entries removed.
+    pub deleted: Vec<String>,
+    /// Entries kept as they were.
+    pub unchanged: usize,
+}
+
+impl UpdateReport {
+    pub fn summary(&self) -> String {
+        let mut out = format!(
+            "{} added, {} replaced, {} deleted, {} unchanged",
+            self.added,
+            self.replaced.len(),
+            self.deleted.len(),
+            self.unchanged
+        );
+        for path in &self.replaced {
+            out.push_str(&format!("\n  replaced {}", path));
+        }
+        for path in &self.deleted {
+            out.push_str(&format!("\n  deleted {}", path));
+        }
+        out
+    }
+}
+
+/// Whether `source` should replace an entry of `size` bytes last modified
+/// at `modified`. `entry_crc` reads the entry's CRC-32 (as lowercase hex)
+/// and is only called for a hash check. Directories and symlinks are only
+/// ever added, never replaced.
+pub(super) fn changed(
+    check: ChangeCheck,
+    source: &SourceEntry,
+    size: u64,
+    modified: Option<SystemTime>,
+    entry_crc: impl FnOnce() -> io::Result<String>,
+) -> io::Result<bool> {
+    if !source.meta.is_file() {
+        return Ok(false);
+    }
+    if source.meta.len() != size {
+        return Ok(true);
+    }
+    match check {
+        // Zip keeps mtimes to two seconds, so closer than that is the same.
+        ChangeCheck::Mtime => Ok(match (source.meta.modified().ok(), modified) {
+            (Some(file), Some(entry)) => file > entry + Duration::from_secs(2),
+            _ => true,
+        }),
+        ChangeCheck::Hash => Ok(file_crc(&source.path)? != entry_crc()?),
+    }
+}
+
+fn file_crc(path: &Path) -> io::Result<String> {
+    let mut digest = DigestWriter::new(HashAlgo::Crc32);
+    io::copy(&mut File::open(path)?, &mut digest)?;
+    Ok(digest.finalize_hex())
+}
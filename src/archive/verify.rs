@@ -0,0 +1,43 @@
+use std::fmt::Display;
+
+use super::progress::Progress;
+
+/// What testing an archive found.
+#[derive(Debug, Default)]
+pub struct TestReport {
+    /// File entries that decompressed cleanly and matched their checksums.
+    pub passed: usize,
+    /// File entries that did not: (entry path, reason).
+    pub failed: Vec<(String, String)>,
+    /// Whether damage stopped the test before the end of the archive, as
+    /// it does in a compressed tarball.
+    pub incomplete: bool,
+}
+
+impl TestReport {
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty() && !self.incomplete
+    }
+
+    pub fn summary(&self) -> String {
+        let mut out = format!("{} entries OK, {} failed", self.passed, self.failed.len());
+        if self.incomplete {
+            out.push_str("; the rest of the archive could not be read");
+        }
+        for (path, reason) in &self.failed {
+            out.push_str(&format!("\n  failed {}: {}", path, reason));
+        }
+        out
+    }
+
+    pub(super) fn pass(&mut self, path: &str, progress: &Progress) {
+        self.passed += 1;
+        progress.note(format!("OK {}", path));
+    }
+
+    pub(super) fn fail(&mut self, path: &str, reason: impl Display, progress: &Progress) {
+        let reason = reason.to_string();
+        progress.note(format!("FAILED {}: {}", path, reason));
+        self.failed.push((path.to_string(), reason));
+    }
+}
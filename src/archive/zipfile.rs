@@ -0,0 +1,835 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Datelike, Local, Timelike};
+use filetime::FileTime;
+use zip::read::{ZipArchive, ZipFile};
+use zip::result::ZipError;
+use zip::unstable::write::FileOptionsExt;
+use zip::write::FileOptions;
+use zip::{AesMode, ZipWriter};
+
+use super::entry::{self, ArchiveEntry};
+use super::extract::{self, ExtractOptions, ExtractReport};
+use super::progress::{Progress, Tracked};
+use super::update::{self, UpdateOptions, UpdateReport};
+use super::verify::TestReport;
+use super::{
+    ArchiveBackend, ArchiveFormat, CompressionMethod, CreateOptions, SourceEntry, ZipEncryption,
+    rewrite, walk_sources,
+};
+use crate::hash::{DigestWriter, HashAlgo};
+
+/// Built-in zip backend. Reads and writes ZipCrypto and AES-256 encrypted
+/// entries without any external tool. Unix permissions, mtimes and symlinks
+/// are kept both ways.
+pub struct ZipBackend;
+
+fn zip_err(e: ZipError) -> io::Error {
+    match e {
+        ZipError::Io(e) => e,
+        other => io::Error::new(io::ErrorKind::InvalidData, other.to_string()),
+    }
+}
+
+/// Entry mtime; zip stores local wall-clock time.
+fn modified(file: &ZipFile) -> Option<SystemTime> {
+    let t = file.last_modified()?;
+    entry::from_local(
+        t.year().into(),
+        t.month().into(),
+        t.day().into(),
+        t.hour().into(),
+        t.minute().into(),
+        t.second().into(),
+    )
+}
+
+/// Zip timestamp for `time`, or `None` outside the format's 1980-2107 range.
+fn zip_time(time: SystemTime) -> Option<zip::DateTime> {
+    let t = DateTime::<Local>::from(time);
+    zip::DateTime::from_date_and_time(
+        t.year().try_into().ok()?,
+        t.month() as u8,
+        t.day() as u8,
+        t.hour() as u8,
+        t.minute() as u8,
+        t.second() as u8,
+    )
+    .ok()
+}
+
+/// Options for one entry: the archive-wide compression settings, the
+/// chosen cipher if there is a password, and the entry's own mtime and
+/// permissions.
+fn file_options<'k>(
+    options: &CreateOptions,
+    password: Option<&'k str>,
+    meta: &fs::Metadata,
+) -> FileOptions<'k, ()> {
+    let method = match options.method.unwrap_or(CompressionMethod::Deflate) {
+        CompressionMethod::Store => zip::CompressionMethod::Stored,
+        CompressionMethod::Deflate => zip::CompressionMethod::Deflated,
+        CompressionMethod::Bzip2 => zip::CompressionMethod::Bzip2,
+        CompressionMethod::Zstd => zip::CompressionMethod::Zstd,
+    };
+    let mut file = FileOptions::default()
+        .compression_method(method)
+        .compression_level(options.level.map(i64::from))
+        .large_file(meta.len() >= u32::MAX as u64);
+    if let Some(p) = password {
+        file = match options.encryption {
+            ZipEncryption::Aes256 => file.with_aes_encryption(AesMode::Aes256, p),
+            ZipEncryption::ZipCrypto => file.with_deprecated_encryption(p.as_bytes()),
+        };
+    }
+    if let Some(t) = meta.modified().ok().and_then(zip_time) {
+        file = file.last_modified_time(t);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file = file.unix_permissions(meta.permissions().mode());
+    }
+    file
+}
+
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Give an extracted file the permissions and mtime stored for it.
+fn restore_metadata(
+    target: &Path,
+    mode: Option<u32>,
+    modified: Option<SystemTime>,
+) -> io::Result<()> {
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(target, fs::Permissions::from_mode(mode & 0o777))?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    if let Some(t) = modified {
+        filetime::set_file_mtime(target, FileTime::from_system_time(t))?;
+    }
+    Ok(())
+}
+
+/// Open entry `index`, decrypting it with `password` if it is encrypted.
+/// A missing or wrong password is `PermissionDenied`, so callers can tell
+/// it from a damaged archive (`InvalidData`).
+fn open_entry<'a>(
+    archive: &'a mut ZipArchive<File>,
+    index: usize,
+    password: Option<&str>,
+) -> io::Result<ZipFile<'a>> {
+    let (name, encrypted) = {
+        let file = archive.by_index_raw(index).map_err(zip_err)?;
+        (file.name().to_string(), file.encrypted())
+    };
+    let file = match password {
+        Some(p) if encrypted => archive.by_index_decrypt(index, p.as_bytes()),
+        _ => archive.by_index(index),
+    };
+    file.map_err(|e| match e {
+        ZipError::InvalidPassword => io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("wrong password for {}", name),
+        ),
+        ZipError::UnsupportedArchive(msg) if msg == ZipError::PASSWORD_REQUIRED => io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is encrypted; a password is needed", name),
+        ),
+        other => zip_err(other),
+    })
+}
+
+/// Explain a failed read of an entry. ZipCrypto's password check lets about
+/// one wrong password in 256 through, which then shows up as a checksum
+/// mismatch, so for encrypted entries bad data may mean either.
+fn read_failed(file: &ZipFile, e: io::Error) -> io::Error {
+    if file.encrypted() && e.kind() == io::ErrorKind::InvalidData {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: wrong password or damaged data ({})", file.name(), e),
+        )
+    } else {
+        e
+    }
+}
+
+/// Copy an opened entry to `out`, reporting bad data as [`read_failed`] does.
+fn copy_entry(file: &mut ZipFile, out: &mut dyn Write) -> io::Result<u64> {
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut copied = 0;
+    loop {
+        let n = match file.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(read_failed(file, e)),
+        };
+        out.write_all(&buf[..n])?;
+        copied += n as u64;
+    }
+}
+
+/// Add `entry` to `zip`, compressed and (with a password) encrypted as
+/// `options` says.
+fn write_entry(
+    zip: &mut ZipWriter<File>,
+    entry: &SourceEntry,
+    password: Option<&str>,
+    options: &CreateOptions,
+    progress: &Progress,
+) -> io::Result<()> {
+    progress.start_entry(&entry.name)?;
+    let file_options = file_options(options, password, &entry.meta);
+    let kind = entry.meta.file_type();
+    if kind.is_dir() {
+        zip.add_directory(entry.name.as_str(), file_options)
+            .map_err(zip_err)?;
+    } else if kind.is_symlink() {
+        let link = fs::read_link(&entry.path)?;
+        zip.add_symlink(entry.name.as_str(), link.to_string_lossy(), file_options)
+            .map_err(zip_err)?;
+    } else if kind.is_file() {
+        zip.start_file(entry.name.as_str(), file_options)
+            .map_err(zip_err)?;
+        let source = File::open(&entry.path)?;
+        io::copy(&mut Tracked::new(source, progress), zip)?;
+    }
+    Ok(())
+}
+
+/// CRC-32 of entry `index` as lowercase hex. Entries written with AES
+/// (AE-2) leave the stored CRC at zero, so those are read to compute it.
+fn stored_crc(
+    archive: &mut ZipArchive<File>,
+    index: usize,
+    password: Option<&str>,
+) -> io::Result<String> {
+    let (crc, encrypted) = {
+        let file = archive.by_index_raw(index).map_err(zip_err)?;
+        (file.crc32(), file.encrypted())
+    };
+    if crc != 0 || !encrypted {
+        return Ok(format!("{:08x}", crc));
+    }
+    let mut digest = DigestWriter::new(HashAlgo::Crc32);
+    copy_entry(&mut open_entry(archive, index, password)?, &mut digest)?;
+    Ok(digest.finalize_hex())
+}
+
+/// Copy the unchanged entry `index` of `old` into `zip`. Plain entries keep
+/// their compressed bytes. The zip crate's raw copy drops the encryption
+/// flag and AES header of an encrypted entry, which leaves it unreadable, so
+/// those are decrypted and encrypted again with the cipher they had; that
+/// needs the password.
+fn carry_over(
+    zip: &mut ZipWriter<File>,
+    old: &mut ZipArchive<File>,
+    index: usize,
+    password: Option<&str>,
+) -> io::Result<()> {
+    let (name, encrypted, options, mode) = {
+        let file = old.by_index_raw(index).map_err(zip_err)?;
+        (
+            file.name().to_string(),
+            file.encrypted(),
+            file.options(),
+            file.unix_mode(),
+        )
+    };
+    if !encrypted {
+        let file = old.by_index_raw(index).map_err(zip_err)?;
+        return zip.raw_copy_file(file).map_err(zip_err);
+    }
+    let aes = old
+        .get_aes_verification_key_and_salt(index)
+        .map_err(zip_err)?;
+    let mut file = open_entry(old, index, password)?;
+    // Opening an encrypted entry fails without a password.
+    let password = password.unwrap_or_default();
+    let options = match aes {
+        Some(info) => options.with_aes_encryption(info.aes_mode, password),
+        None => options.with_deprecated_encryption(password.as_bytes()),
+    };
+    if mode.is_some_and(|m| m & S_IFMT == S_IFLNK) {
+        let mut link = Vec::new();
+        copy_entry(&mut file, &mut link)?;
+        zip.add_symlink(name, String::from_utf8_lossy(&link), options)
+            .map_err(zip_err)?;
+    } else {
+        zip.start_file(name, options).map_err(zip_err)?;
+        copy_entry(&mut file, zip)?;
+    }
+    Ok(())
+}
+
+impl ArchiveBackend for ZipBackend {
+    fn name(&self) -> &'static str {
+        "built-in zip"
+    }
+
+    fn supports(&self, format: ArchiveFormat) -> bool {
+        format == ArchiveFormat::Zip
+    }
+
+    fn can_encrypt(&self, format: ArchiveFormat) -> bool {
+        format == ArchiveFormat::Zip
+    }
+
+    fn list(
+        &self,
+        archive: &Path,
+        _format: ArchiveFormat,
+        _password: Option<&str>,
+    ) -> io::Result<Vec<ArchiveEntry>> {
+        let mut archive = ZipArchive::new(File::open(archive)?).map_err(zip_err)?;
+        let mut entries = Vec::with_capacity(archive.len());
+        for i in 0..archive.len() {
+            let file = archive.by_index_raw(i).map_err(zip_err)?;
+            entries.push(ArchiveEntry {
+                path: entry::clean_path(file.name()),
+                size: file.size(),
+                compressed_size: Some(file.compressed_size()),
+                modified: modified(&file),
+                is_dir: file.is_dir(),
+                encrypted: file.encrypted(),
+                crc32: (!file.is_dir()).then(|| file.crc32()),
+            });
+        }
+        Ok(entries)
+    }
+
+    fn extract(
+        &self,
+        archive: &Path,
+        _format: ArchiveFormat,
+        dest: &Path,
+        password: Option<&str>,
+        options: &ExtractOptions,
+        progress: &Progress,
+    ) -> io::Result<ExtractReport> {
+        let mut archive = ZipArchive::new(File::open(archive)?).map_err(zip_err)?;
+        let mut report = ExtractReport::default();
+        let (mut bytes, mut files) = (0, 0);
+        for i in 0..archive.len() {
+            let file = archive.by_index_raw(i).map_err(zip_err)?;
+            if !file.is_dir() && options.selects(&entry::clean_path(file.name())) {
+                bytes += file.size();
+                files += 1;
+            }
+        }
+        progress.set_total(bytes, Some(files));
+        extract::create_dirs(dest, progress)?;
+        for i in 0..archive.len() {
+            let (path, is_dir, modified, mode) = {
+                let file = archive.by_index_raw(i).map_err(zip_err)?;
+                (
+                    entry::clean_path(file.name()),
+                    file.is_dir(),
+                    modified(&file),
+                    file.unix_mode(),
+                )
+            };
+            if !options.selects(&path) {
+                continue;
+            }
+            let Some(target) = options.place(dest, &path, &mut report) else {
+                continue;
+            };
+            if is_dir {
+                extract::create_dirs(&target, progress)?;
+                continue;
+            }
+            progress.start_entry(&path)?;
+            // A symlink is stored as an entry whose content is the link text.
+            let link = if mode.is_some_and(|m| m & S_IFMT == S_IFLNK) {
+                let mut link = Vec::new();
+                copy_entry(&mut open_entry(&mut archive, i, password)?, &mut link)?;
+                let link = String::from_utf8_lossy(&link).into_owned();
+                if let Err(reason) = extract::link_inside(dest, &target, Path::new(&link)) {
+                    report.reject(&path, reason);
+                    continue;
+                }
+                Some(link)
+            } else {
+                None
+            };
+            let Some(target) = report.claim(target, modified, options.policy) else {
+                continue;
+            };
+            let staged = extract::prepare(&target, progress)?;
+            match link {
+                Some(link) => extract::make_symlink(&link, &staged)?,
+                None => {
+                    let mut file = open_entry(&mut archive, i, password)?;
+                    copy_entry(
+                        &mut file,
+                        &mut Tracked::new(File::create(&staged)?, progress),
+                    )?;
+                    restore_metadata(&staged, mode, modified)?;
+                }
+            }
+            extract::commit(&staged, &target, progress)?;
+            report.extracted += 1;
+        }
+        Ok(report)
+    }
+
+    fn test(
+        &self,
+        archive: &Path,
+        _format: ArchiveFormat,
+        password: Option<&str>,
+        progress: &Progress,
+    ) -> io::Result<TestReport> {
+        let mut archive = ZipArchive::new(File::open(archive)?).map_err(zip_err)?;
+        let mut report = TestReport::default();
+        let (mut bytes, mut files) = (0, 0);
+        for i in 0..archive.len() {
+            let file = archive.by_index_raw(i).map_err(zip_err)?;
+            if !file.is_dir() {
+                bytes += file.size();
+                files += 1;
+            }
+        }
+        progress.set_total(bytes, Some(files));
+        for i in 0..archive.len() {
+            let (path, is_dir) = {
+                let file = archive.by_index_raw(i).map_err(zip_err)?;
+                (entry::clean_path(file.name()), file.is_dir())
+            };
+            if is_dir {
+                continue;
+            }
+            progress.start_entry(&path)?;
+            // Reading to the end makes the zip crate compare the CRC (and,
+            // for AES, the authentication code).
+            let result = open_entry(&mut archive, i, password).and_then(|mut file| {
+                copy_entry(&mut file, &mut Tracked::new(io::sink(), progress))
+            });
+            match result {
+                Ok(_) => report.pass(&path, progress),
+                Err(e) if progress.is_canceled() => return Err(e),
+                Err(e) => report.fail(&path, e, progress),
+            }
+        }
+        Ok(report)
+    }
+
+    fn read_entry(
+        &self,
+        archive: &Path,
+        _format: ArchiveFormat,
+        name: &str,
+        password: Option<&str>,
+        out: &mut dyn Write,
+    ) -> io::Result<u64> {
+        let mut archive = ZipArchive::new(File::open(archive)?).map_err(zip_err)?;
+        let index = (0..archive.len())
+            .find(|&i| {
+                archive
+                    .by_index_raw(i)
+                    .is_ok_and(|f| !f.is_dir() && entry::clean_path(f.name()) == name)
+            })
+            .ok_or_else(|| entry::not_found(name))?;
+        copy_entry(&mut open_entry(&mut archive, index, password)?, out)
+    }
+
+    fn create(
+        &self,
+        output: &Path,
+        _format: ArchiveFormat,
+        sources: &[&Path],
+        password: Option<&str>,
+        options: &CreateOptions,
+        progress: &Progress,
+    ) -> io::Result<()> {
+        let entries = walk_sources(sources, options)?;
+        progress.count_sources(&entries);
+        let mut zip = ZipWriter::new(File::create(output)?);
+        for entry in &entries {
+            write_entry(&mut zip, entry, password, options, progress)?;
+        }
+        zip.finish().map_err(zip_err)?.sync_all()
+    }
+
+    fn update(
+        &self,
+        archive: &Path,
+        _format: ArchiveFormat,
+        sources: &[&Path],
+        password: Option<&str>,
+        options: &UpdateOptions,
+        progress: &Progress,
+    ) -> io::Result<UpdateReport> {
+        let sources = walk_sources(sources, &options.create)?;
+        progress.count_sources(&sources);
+        let by_name: HashMap<&str, &SourceEntry> =
+            sources.iter().map(|s| (s.name.as_str(), s)).collect();
+        let mut old = ZipArchive::new(File::open(archive)?).map_err(zip_err)?;
+        let mut report = UpdateReport::default();
+        let mut present = HashSet::new();
+        rewrite(archive, |output| {
+            let mut zip = ZipWriter::new(File::create(output)?);
+            for i in 0..old.len() {
+                progress.check()?;
+                let (path, size, modified) = {
+                    let file = old.by_index_raw(i).map_err(zip_err)?;
+                    (entry::clean_path(file.name()), file.size(), modified(&file))
+                };
+                present.insert(path.clone());
+                if options.deletes(&path) {
+                    report.deleted.push(path);
+                    continue;
+                }
+                if let Some(source) = by_name.get(path.as_str())
+                    && update::changed(options.compare, source, size, modified, || {
+                        stored_crc(&mut old, i, password)
+                    })?
+                {
+                    write_entry(&mut zip, source, password, &options.create, progress)?;
+                    report.replaced.push(path);
+                    continue;
+                }
+                carry_over(&mut zip, &mut old, i, password)?;
+                report.unchanged += 1;
+            }
+            for source in sources.iter().filter(|s| !present.contains(&s.name)) {
+                write_entry(&mut zip, source, password, &options.create, progress)?;
+                report.added += 1;
+            }
+            zip.finish().map_err(zip_err)?.sync_all()
+        })?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use super::*;
+    use crate::archive::{BackendChoice, ChangeCheck};
+
+    /// A zip of `dir/a.txt` and `dir/sub/b.txt` under `root`, made with `password`.
+    fn write_encrypted(root: &Path, options: &CreateOptions, password: &str) -> std::path::PathBuf {
+        let dir = root.join("dir");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), "alpha ".repeat(100)).unwrap();
+        fs::write(dir.join("sub/b.txt"), "beta").unwrap();
+        let output = root.join("out.zip");
+        let cancel = AtomicBool::new(false);
+        let mut report = |_| {};
+        let progress = Progress::new("Archiving", &mut report, &cancel);
+        ZipBackend
+            .create(
+                &output,
+                ArchiveFormat::Zip,
+                &[&dir],
+                Some(password),
+                options,
+                &progress,
+            )
+            .unwrap();
+        output
+    }
+
+    fn read(archive: &Path, name: &str, password: Option<&str>) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        ZipBackend
+            .read_entry(archive, ArchiveFormat::Zip, name, password, &mut out)
+            .map(|_| out)
+    }
+
+    #[test]
+    fn encrypted_archives_round_trip() {
+        for encryption in ZipEncryption::ALL {
+            for method in CompressionMethod::ALL {
+                let tmp = tempfile::tempdir().unwrap();
+                let options = CreateOptions {
+                    method: Some(method),
+                    encryption,
+                    ..CreateOptions::default()
+                };
+                let zip = write_encrypted(tmp.path(), &options, "s3cret");
+                let entries = ZipBackend.list(&zip, ArchiveFormat::Zip, None).unwrap();
+                let files: Vec<_> = entries.iter().filter(|e| !e.is_dir).collect();
+                assert_eq!(files.len(), 2, "{:?}", encryption);
+                assert!(files.iter().all(|e| e.encrypted), "{:?}", encryption);
+                assert_eq!(
+                    read(&zip, "dir/sub/b.txt", Some("s3cret")).unwrap(),
+                    b"beta",
+                    "{:?} {:?}",
+                    encryption,
+                    method
+                );
+                assert_eq!(
+                    read(&zip, "dir/a.txt", Some("s3cret")).unwrap(),
+                    "alpha ".repeat(100).as_bytes()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn zipcrypto_archives_can_be_updated() {
+        let tmp = tempfile::tempdir().unwrap();
+        let options = CreateOptions {
+            encryption: ZipEncryption::ZipCrypto,
+            ..CreateOptions::default()
+        };
+        let zip = write_encrypted(tmp.path(), &options, "s3cret");
+        let dir = tmp.path().join("dir");
+        fs::write(dir.join("c.txt"), "gamma").unwrap();
+
+        let cancel = AtomicBool::new(false);
+        let mut report = |_| {};
+        let progress = Progress::new("Updating", &mut report, &cancel);
+        let options = UpdateOptions {
+            create: options,
+            ..UpdateOptions::default()
+        };
+        let done = ZipBackend
+            .update(
+                &zip,
+                ArchiveFormat::Zip,
+                &[&dir],
+                Some("s3cret"),
+                &options,
+                &progress,
+            )
+            .unwrap();
+        assert_eq!(done.added, 1);
+        let entries = ZipBackend.list(&zip, ArchiveFormat::Zip, None).unwrap();
+        assert!(entries.iter().filter(|e| !e.is_dir).all(|e| e.encrypted));
+        assert_eq!(read(&zip, "dir/c.txt", Some("s3cret")).unwrap(), b"gamma");
+        assert_eq!(
+            read(&zip, "dir/sub/b.txt", Some("s3cret")).unwrap(),
+            b"beta"
+        );
+        assert!(read(&zip, "dir/c.txt", None).is_err());
+    }
+
+    #[test]
+    fn wrong_or_missing_password_is_not_corruption() {
+        for encryption in ZipEncryption::ALL {
+            let tmp = tempfile::tempdir().unwrap();
+            let options = CreateOptions {
+                encryption,
+                ..CreateOptions::default()
+            };
+            let zip = write_encrypted(tmp.path(), &options, "s3cret");
+            let missing = read(&zip, "dir/a.txt", None).unwrap_err();
+            assert_eq!(missing.kind(), io::ErrorKind::PermissionDenied);
+            assert!(missing.to_string().contains("password is needed"));
+            // ZipCrypto's one-byte check lets some wrong passwords through;
+            // those must still fail, if only on the checksum.
+            for wrong in ["wrong", "S3CRET", "s3cre", "hunter2"] {
+                let e = read(&zip, "dir/a.txt", Some(wrong)).unwrap_err();
+                assert!(
+                    e.kind() == io::ErrorKind::PermissionDenied
+                        || e.to_string().contains("wrong password"),
+                    "{:?} {}: {}",
+                    encryption,
+                    wrong,
+                    e
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_reports_each_damaged_entry() {
+        let tmp = tempfile::tempdir().unwrap();
+        let options = CreateOptions {
+            method: Some(CompressionMethod::Store),
+            ..CreateOptions::default()
+        };
+        let zip = write_encrypted(tmp.path(), &options, "s3cret");
+        let mut bytes = fs::read(&zip).unwrap();
+        let at = bytes.windows(9).position(|w| w == b"dir/a.txt").unwrap() + 300;
+        bytes[at] ^= 0xff;
+        fs::write(&zip, bytes).unwrap();
+
+        let cancel = AtomicBool::new(false);
+        let mut lines = Vec::new();
+        let mut record = |msg: String| lines.push(msg);
+        let progress = Progress::new("Testing", &mut record, &cancel);
+        let report = ZipBackend
+            .test(&zip, ArchiveFormat::Zip, Some("s3cret"), &progress)
+            .unwrap();
+        drop(progress);
+        assert_eq!(report.passed, 1);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "dir/a.txt");
+        assert!(lines.iter().any(|l| l == "OK dir/sub/b.txt"));
+        assert!(lines.iter().any(|l| l.starts_with("FAILED dir/a.txt")));
+    }
+
+    #[test]
+    fn damaged_encrypted_data_is_reported_as_such() {
+        let tmp = tempfile::tempdir().unwrap();
+        let options = CreateOptions {
+            method: Some(CompressionMethod::Store),
+            ..CreateOptions::default()
+        };
+        let zip = write_encrypted(tmp.path(), &options, "s3cret");
+        let mut bytes = fs::read(&zip).unwrap();
+        // Flip a byte in the middle of a.txt's 600 stored bytes.
+        let at = bytes.windows(9).position(|w| w == b"dir/a.txt").unwrap() + 300;
+        bytes[at] ^= 0xff;
+        fs::write(&zip, bytes).unwrap();
+        let e = read(&zip, "dir/a.txt", Some("s3cret")).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData, "{}", e);
+    }
+
+    /// The stored (compressed, possibly encrypted) bytes of `name`.
+    fn raw_bytes(archive: &Path, name: &str) -> Vec<u8> {
+        let mut zip = ZipArchive::new(File::open(archive).unwrap()).unwrap();
+        let index = zip.index_for_name(name).unwrap();
+        let mut out = Vec::new();
+        zip.by_index_raw(index)
+            .unwrap()
+            .read_to_end(&mut out)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn canceled_overwrite_keeps_the_previous_archive() {
+        let tmp = tempfile::tempdir().unwrap();
+        let zip = write_encrypted(tmp.path(), &CreateOptions::default(), "s3cret");
+        let before = fs::read(&zip).unwrap();
+        let big = tmp.path().join("big");
+        fs::create_dir(&big).unwrap();
+        for name in ["one.bin", "two.bin", "three.bin"] {
+            fs::write(big.join(name), vec![7u8; 1 << 20]).unwrap();
+        }
+
+        let cancel = AtomicBool::new(false);
+        let result = crate::archive::create_archive(
+            &[big.to_str().unwrap()],
+            zip.to_str().unwrap(),
+            None,
+            &CreateOptions::default(),
+            BackendChoice::Native,
+            &mut |_| cancel.store(true, std::sync::atomic::Ordering::SeqCst),
+            &cancel,
+        );
+        assert!(result.unwrap_err().contains("canceled"));
+        assert_eq!(fs::read(&zip).unwrap(), before);
+        // Nothing is left beside it but the sources.
+        assert_eq!(fs::read_dir(tmp.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn update_replaces_changed_entries_and_copies_the_rest() {
+        let tmp = tempfile::tempdir().unwrap();
+        let zip = write_encrypted(tmp.path(), &CreateOptions::default(), "s3cret");
+        let dir = tmp.path().join("dir");
+        // Same size, so only the hash check can tell.
+        fs::write(dir.join("sub/b.txt"), "BETA").unwrap();
+        fs::write(dir.join("c.txt"), "gamma").unwrap();
+
+        let cancel = AtomicBool::new(false);
+        let mut report = |_| {};
+        let progress = Progress::new("Updating", &mut report, &cancel);
+        let options = UpdateOptions {
+            compare: ChangeCheck::Hash,
+            ..UpdateOptions::default()
+        };
+        let done = ZipBackend
+            .update(
+                &zip,
+                ArchiveFormat::Zip,
+                &[&dir],
+                Some("s3cret"),
+                &options,
+                &progress,
+            )
+            .unwrap();
+        assert_eq!(done.added, 1);
+        assert_eq!(done.replaced, ["dir/sub/b.txt"]);
+        // Carried over, still encrypted.
+        assert_eq!(
+            read(&zip, "dir/a.txt", Some("s3cret")).unwrap(),
+            "alpha ".repeat(100).as_bytes()
+        );
+        assert!(read(&zip, "dir/a.txt", None).is_err());
+        assert_eq!(
+            read(&zip, "dir/sub/b.txt", Some("s3cret")).unwrap(),
+            b"BETA"
+        );
+        assert_eq!(read(&zip, "dir/c.txt", Some("s3cret")).unwrap(), b"gamma");
+
+        let options = UpdateOptions {
+            delete: vec!["dir/sub".to_string()],
+            ..UpdateOptions::default()
+        };
+        // Encrypted entries can only be carried over with the password.
+        let before = fs::read(&zip).unwrap();
+        let e = ZipBackend
+            .update(&zip, ArchiveFormat::Zip, &[], None, &options, &progress)
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(fs::read(&zip).unwrap(), before);
+        let done = ZipBackend
+            .update(
+                &zip,
+                ArchiveFormat::Zip,
+                &[],
+                Some("s3cret"),
+                &options,
+                &progress,
+            )
+            .unwrap();
+        assert_eq!(done.deleted, ["dir/sub", "dir/sub/b.txt"]);
+        let names: Vec<_> = ZipBackend
+            .list(&zip, ArchiveFormat::Zip, None)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert!(
+            !names.iter().any(|n| n.starts_with("dir/sub")),
+            "{:?}",
+            names
+        );
+        assert_eq!(read(&zip, "dir/c.txt", Some("s3cret")).unwrap(), b"gamma");
+
+        // Plain entries keep their compressed bytes.
+        let plain = tmp.path().join("plain.zip");
+        ZipBackend
+            .create(
+                &plain,
+                ArchiveFormat::Zip,
+                &[&dir],
+                None,
+                &CreateOptions::default(),
+                &progress,
+            )
+            .unwrap();
+        let before = raw_bytes(&plain, "dir/a.txt");
+        fs::write(dir.join("d.txt"), "delta").unwrap();
+        ZipBackend
+            .update(
+                &plain,
+                ArchiveFormat::Zip,
+                &[&dir],
+                None,
+                &UpdateOptions::default(),
+                &progress,
+            )
+            .unwrap();
+        assert_eq!(raw_bytes(&plain, "dir/a.txt"), before);
+        assert_eq!(read(&plain, "dir/d.txt", None).unwrap(), b"delta");
+    }
+}
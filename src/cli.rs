@@ -0,0 +1,959 @@
+use crate::actions;
+use crate::archive::{
+    ArchiveFormat, BackendChoice, ChangeCheck, CompressionMethod, CreateOptions, ExtractOptions,
+    PathSafety, UpdateOptions, ZipEncryption,
+};
+use crate::compress::{Codec, CompressOptions};
+use crate::copy::{ConflictPolicy, CopyOptions};
+use crate::dupes::Resolution;
+use crate::hash::HashAlgo;
+use crate::shred::{Scheme, ShredOptions};
+use crate::split::SplitMode;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Exit code for a command that ran but failed.
+const EXIT_FAILURE: i32 = 1;
+/// Exit code for malformed command lines.
+const EXIT_USAGE: i32 = 2;
+
+const USAGE: &str = "\
+Usage: redox-filemanager [COMMAND] [ARGS...]
+
+Run without a command to start the interactive menu. An argument of --
+ends the options, so later arguments starting with -- are taken as paths.
+
+Commands:
+  copy [--on-conflict POLICY] [--no-preserve] SRC DST
+                                        Copy a file or directory tree; POLICY is
+                                        skip, overwrite (default), newer or rename
+  delete [--permanent] PATH...          Move files or directories to the trash
+  rmdir [--permanent] DIR               Move a directory to the trash
+  rename OLD NEW                        Rename a file
+  move SRC DST                          Move a file or directory
+  touch PATH                            Create an empty file
+  mkdir PATH                            Create a directory
+  hash [--algo ALGO] FILE               Print the hash of a file; ALGO is sha256
+                                        (default), blake3, sha1, sha512, md5 or crc32
+  hash --check [--algo ALGO] SUMFILE    Verify files listed in a sha256sum/b3sum-style
+                                        checksum file; relative paths in it are taken
+                                        from SUMFILE's directory, not the current one
+  duplicates [--resolve ACTION] DIR     List groups of duplicate files under DIR; ACTION
+                                        (delete, hardlink or symlink) keeps the first
+                                        file of each group and replaces the rest
+  shred [--scheme S] [--verify] PATH    Overwrite a file (or every file in a directory)
+                                        and delete it; S is zero, random (default), dod
+                                        or a number of random passes
+  split [--size MB | --parts N] FILE    Split a file into FILE.000, FILE.001, ... (default
+                                        100 MB chunks) and write FILE.manifest
+  join FILE OUTPUT                      Check FILE.000, FILE.001, ... against FILE.manifest
+                                        and join them into OUTPUT
+  compress [--format F] [--level N] [--keep] FILE
+                                        Compress FILE into FILE.gz (default), .bz2, .xz or
+                                        .zst and remove FILE unless --keep is given
+  decompress [--keep] FILE              Decompress a .gz, .bz2, .xz or .zst file next to
+                                        it and remove FILE unless --keep is given
+  archive list [--backend B] [--password-file F] ARCHIVE
+                                        List archive contents; the password is only
+                                        needed for 7z archives with encrypted names
+  archive test [--backend B] [--password-file F] ARCHIVE
+                                        Decompress every entry and check its CRC (tar:
+                                        header checksums and the compressed stream);
+                                        fails, listing the damaged entries, if any is bad
+  archive extract [--backend B] [--password-file F] [--strip-components N]
+          [--on-conflict POLICY] [--rewrite-unsafe] ARCHIVE DEST [ENTRY...]
+                                        Extract an archive into DEST; ENTRY limits it to
+                                        the given paths or patterns (* and ? wildcards).
+                                        Entries with absolute or '..' paths are rejected
+                                        unless --rewrite-unsafe keeps them inside DEST
+  archive create [--backend B] [--format F] [--password-file F]
+          [--encryption E] [--method M] [--level N] [--base-dir DIR] [--exclude PATTERNS]
+          OUTPUT SRC...
+                                        Create an archive from one or more sources; F is
+                                        zip, tar, tar.gz, tar.bz2, tar.xz, tar.zst or 7z
+                                        (default: from OUTPUT's extension). B is auto
+                                        (default), native or 7z. M is the zip/7z entry
+                                        compression: store, deflate, bzip2 or zstd. E is
+                                        the zip cipher: aes256 (default) or zipcrypto.
+                                        Relative sources keep their path in the archive;
+                                        with --base-dir they are read from DIR and stored
+                                        relative to it (. for all of DIR). PATTERNS is a
+                                        comma-separated list such as target/,.git/,*.o: a
+                                        name matches at any depth, a path from the top,
+                                        and a trailing / only matches directories
+                                        --password-file reads the password from the first
+                                        line of F (- for stdin), keeping it out of ps
+  archive update [--backend B] [--password-file F] [--encryption E]
+          [--method M] [--level N] [--base-dir DIR] [--exclude PATTERNS]
+          [--compare mtime|hash] [--delete PATTERNS] ARCHIVE [SRC...]
+                                        Add SRC to an existing archive: new files are
+                                        appended, files newer than their entry (or with a
+                                        different CRC, with --compare hash) replace it, and
+                                        entries matching the comma-separated PATTERNS are
+                                        removed. Unchanged zip entries are copied without
+                                        recompressing; encrypted ones are re-encrypted, so
+                                        they need the password
+  trash list                            List trashed items (name, date, original path)
+  trash restore NAME...                 Restore trashed items to their original paths
+  trash empty                           Permanently delete everything in the trash
+  gui                                   Launch the graphical interface
+  help                                  Show this message";
+
+/// Parsed command line: positional arguments, `--name value` options and bare `--flag`s.
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    /// Split `raw` into positionals, options and flags. Every name in `allowed`
+    /// takes a value; names in `flags` take none. Everything after a bare `--`
+    /// is positional.
+    fn parse(raw: &[String], allowed: &[&str], flags: &[&str]) -> Result<Args, String> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+        let mut set_flags = Vec::new();
+        let mut iter = raw.iter();
+        while let Some(arg) = iter.next() {
+            if arg == "--" {
+                positional.extend(iter.cloned());
+                break;
+            }
+            if let Some(name) = arg.strip_prefix("--") {
+                if flags.contains(&name) {
+                    set_flags.push(name.to_string());
+                    continue;
+                }
+                let (name, inline) = match name.split_once('=') {
+                    Some((n, v)) => (n, Some(v.to_string())),
+                    None => (name, None),
+                };
+                if !allowed.contains(&name) {
+                    return Err(format!("Unknown option '--{}'", name));
+                }
+                let value = match inline {
+                    Some(v) => v,
+                    None => iter
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| format!("Option '--{}' requires a value", name))?,
+                };
+                options.insert(name.to_string(), value);
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+        Ok(Args {
+            positional,
+            options,
+            flags: set_flags,
+        })
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(|s| s.as_str())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    /// Require exactly `n` positional arguments.
+    fn expect(&self, n: usize, usage: &str) -> Result<(), String> {
+        if self.positional.len() == n {
+            Ok(())
+        } else {
+            Err(format!("Usage: redox-filemanager {}", usage))
+        }
+    }
+}
+
+/// Outcome of a subcommand: `Ok` on success, `Failed` for operation errors and
+/// `Usage` for bad command lines.
+enum CliError {
+    Failed(String),
+    Usage(String),
+}
+
+impl From<String> for CliError {
+    fn from(msg: String) -> Self {
+        CliError::Usage(msg)
+    }
+}
+
+/// Run a subcommand from the process arguments (without the program name).
+/// Returns the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    let (command, rest) = match args.split_first() {
+        Some((c, r)) => (c.as_str(), r),
+        None => {
+            println!("{}", USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    let result = match command {
+        "copy" => copy(rest),
+        "delete" => delete(rest),
+        "rmdir" => rmdir(rest),
+        "rename" => rename(rest),
+        "move" => move_file(rest),
+        "touch" => touch(rest),
+        "mkdir" => mkdir(rest),
+        "hash" => hash(rest),
+        "duplicates" => duplicates(rest),
+        "shred" => shred(rest),
+        "split" => split(rest),
+        "join" => join(rest),
+        "compress" => compress(rest),
+        "decompress" => decompress(rest),
+        "archive" => archive(rest),
+        "trash" => trash(rest),
+        "gui" => gui(rest),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(CliError::Usage(format!("Unknown command '{}'", other))),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(CliError::Failed(msg)) => {
+            eprintln!("{}", msg);
+            EXIT_FAILURE
+        }
+        Err(CliError::Usage(msg)) => {
+            eprintln!("{}", msg);
+            eprintln!("Run 'redox-filemanager help' for a list of commands.");
+            EXIT_USAGE
+        }
+    }
+}
+
+fn copy(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &["on-conflict"], &["no-preserve"])?;
+    args.expect(
+        2,
+        "copy [--on-conflict skip|overwrite|newer|rename] [--no-preserve] SRC DST",
+    )?;
+    let policy = match args.option("on-conflict") {
+        Some(p) => ConflictPolicy::parse(p)
+            .ok_or_else(|| CliError::Usage(format!("Unknown conflict policy '{}'", p)))?,
+        None => ConflictPolicy::Overwrite,
+    };
+    let preserve = !args.flag("no-preserve");
+    let options = CopyOptions {
+        policy,
+        preserve_permissions: preserve,
+        preserve_times: preserve,
+    };
+    let (src, dst) = (&args.positional[0], &args.positional[1]);
+    let report = actions::copy_file_noninteractive(src, dst, &options)
+        .map_err(|e| CliError::Failed(format!("Error copying file: {}", e)))?;
+    for conflict in &report.conflicts {
+        println!("Conflict: {}", conflict);
+    }
+    for (path, e) in &report.errors {
+        eprintln!("Error copying '{}': {}", path.display(), e);
+    }
+    if !report.errors.is_empty() {
+        return Err(CliError::Failed(format!(
+            "Copy incomplete: {}",
+            report.summary()
+        )));
+    }
+    println!("Copied '{}' -> '{}' ({})", src, dst, report.summary());
+    Ok(())
+}
+
+fn delete(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &[], &["permanent"])?;
+    if args.positional.is_empty() {
+        return Err(CliError::Usage(
+            "Usage: redox-filemanager delete [--permanent] PATH...".to_string(),
+        ));
+    }
+    let permanent = args.flag("permanent");
+    let results: Vec<Result<(), std::io::Error>> = if permanent {
+        actions::batch_delete_noninteractive(&args.positional)
+    } else {
+        actions::batch_trash_noninteractive(&args.positional)
+            .into_iter()
+            .map(|r| r.map(|_| ()))
+            .collect()
+    };
+    let mut failed = 0;
+    for (path, res) in args.positional.iter().zip(results) {
+        match res {
+            Ok(_) if permanent => println!("Deleted '{}'", path),
+            Ok(_) => println!("Moved '{}' to trash", path),
+            Err(e) => {
+                eprintln!("Error deleting '{}': {}", path, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(CliError::Failed(format!(
+            "{} of {} deletions failed",
+            failed,
+            args.positional.len()
+        )));
+    }
+    Ok(())
+}
+
+fn rmdir(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &[], &["permanent"])?;
+    args.expect(1, "rmdir [--permanent] DIR")?;
+    let dir = &args.positional[0];
+    if args.flag("permanent") {
+        actions::delete_directory_noninteractive(dir)
+            .map_err(|e| CliError::Failed(format!("Error deleting directory '{}': {}", dir, e)))?;
+        println!("Directory '{}' deleted successfully.", dir);
+    } else {
+        actions::trash_noninteractive(dir)
+            .map_err(|e| CliError::Failed(format!("Error deleting directory '{}': {}", dir, e)))?;
+        println!("Directory '{}' moved to trash.", dir);
+    }
+    Ok(())
+}
+
+fn trash(raw: &[String]) -> Result<(), CliError> {
+    let (sub, rest) = match raw.split_first() {
+        Some((s, r)) => (s.as_str(), r),
+        None => {
+            return Err(CliError::Usage(
+                "Usage: redox-filemanager trash list|restore|empty ...".to_string(),
+            ));
+        }
+    };
+    match sub {
+        "list" => {
+            Args::parse(rest, &[], &[])?.expect(0, "trash list")?;
+            let items = actions::trash_list_noninteractive()
+                .map_err(|e| CliError::Failed(format!("Error reading trash: {}", e)))?;
+            for item in items {
+                println!(
+                    "{}\t{}\t{}",
+                    item.name,
+                    item.deletion_date,
+                    item.original_path.display()
+                );
+            }
+            Ok(())
+        }
+        "restore" => {
+            let args = Args::parse(rest, &[], &[])?;
+            if args.positional.is_empty() {
+                return Err(CliError::Usage(
+                    "Usage: redox-filemanager trash restore NAME...".to_string(),
+                ));
+            }
+            let mut failed = 0;
+            for name in &args.positional {
+                match actions::trash_restore_noninteractive(name) {
+                    Ok(path) => println!("Restored '{}' to {}", name, path.display()),
+                    Err(e) => {
+                        eprintln!("Error restoring '{}': {}", name, e);
+                        failed += 1;
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(CliError::Failed(format!(
+                    "{} of {} restores failed",
+                    failed,
+                    args.positional.len()
+                )));
+            }
+            Ok(())
+        }
+        "empty" => {
+            Args::parse(rest, &[], &[])?.expect(0, "trash empty")?;
+            let n = actions::trash_empty_noninteractive()
+                .map_err(|e| CliError::Failed(format!("Error emptying trash: {}", e)))?;
+            println!("Emptied trash ({} items removed).", n);
+            Ok(())
+        }
+        other => Err(CliError::Usage(format!(
+            "Unknown trash command '{}'",
+            other
+        ))),
+    }
+}
+
+fn rename(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &[], &[])?;
+    args.expect(2, "rename OLD NEW")?;
+    let (old, new) = (&args.positional[0], &args.positional[1]);
+    actions::rename_file_noninteractive(old, new)
+        .map_err(|e| CliError::Failed(format!("Error renaming file: {}", e)))?;
+    println!("Renamed '{}' -> '{}'", old, new);
+    Ok(())
+}
+
+fn move_file(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &[], &[])?;
+    args.expect(2, "move SRC DST")?;
+    let (src, dst) = (&args.positional[0], &args.positional[1]);
+    actions::move_file_noninteractive(src, dst)
+        .map_err(|e| CliError::Failed(format!("Error moving file: {}", e)))?;
+    println!("Moved '{}' -> '{}'", src, dst);
+    Ok(())
+}
+
+fn touch(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &[], &[])?;
+    args.expect(1, "touch PATH")?;
+    let path = &args.positional[0];
+    actions::create_file_noninteractive(path)
+        .map_err(|e| CliError::Failed(format!("Error creating file: {}", e)))?;
+    println!("File '{}' created successfully.", path);
+    Ok(())
+}
+
+fn mkdir(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &[], &[])?;
+    args.expect(1, "mkdir PATH")?;
+    let path = &args.positional[0];
+    actions::create_directory_noninteractive(path)
+        .map_err(|e| CliError::Failed(format!("Error creating directory: {}", e)))?;
+    println!("Directory '{}' created successfully.", path);
+    Ok(())
+}
+
+fn hash(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &["algo"], &["check"])?;
+    args.expect(1, "hash [--check] [--algo ALGO] FILE")?;
+    if let Some(algo) = args.option("algo")
+        && HashAlgo::parse(algo).is_none()
+    {
+        return Err(CliError::Usage(format!(
+            "Unsupported hash algorithm '{}'",
+            algo
+        )));
+    }
+    if args.flag("check") {
+        let report =
+            actions::verify_checksums_noninteractive(&args.positional[0], args.option("algo"))
+                .map_err(CliError::Failed)?;
+        for result in &report.results {
+            println!("{}", result);
+        }
+        println!("{}", report.summary());
+        if !report.all_ok() {
+            return Err(CliError::Failed("checksum verification failed".to_string()));
+        }
+        return Ok(());
+    }
+    let algo = args.option("algo").unwrap_or("sha256");
+    let out = actions::calculate_hash_noninteractive(&args.positional[0], algo)
+        .map_err(CliError::Failed)?;
+    println!("{}", out);
+    Ok(())
+}
+
+fn duplicates(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &["resolve"], &[])?;
+    args.expect(1, "duplicates [--resolve delete|hardlink|symlink] DIR")?;
+    let resolution = match args.option("resolve") {
+        Some(r) => Some(
+            Resolution::parse(r)
+                .ok_or_else(|| CliError::Usage(format!("Unknown resolution '{}'", r)))?,
+        ),
+        None => None,
+    };
+    let groups =
+        actions::find_duplicates_noninteractive(&args.positional[0]).map_err(CliError::Failed)?;
+    if groups.is_empty() {
+        println!("No duplicate files found.");
+    }
+    actions::print_duplicate_groups(&groups);
+    let Some(resolution) = resolution else {
+        return Ok(());
+    };
+    let mut failed = 0;
+    for group in &groups {
+        let report = actions::resolve_duplicates_noninteractive(group, 0, resolution)
+            .map_err(CliError::Failed)?;
+        for (path, e) in &report.errors {
+            eprintln!("{}: {}", path.display(), e);
+        }
+        failed += report.errors.len();
+    }
+    if failed > 0 {
+        return Err(CliError::Failed(format!(
+            "{} duplicates could not be resolved",
+            failed
+        )));
+    }
+    Ok(())
+}
+
+fn shred(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &["scheme"], &["verify"])?;
+    args.expect(1, "shred [--scheme zero|random|dod|N] [--verify] PATH")?;
+    let scheme = match args.option("scheme") {
+        Some(s) => {
+            Scheme::parse(s).ok_or_else(|| CliError::Usage(format!("Unknown scheme '{}'", s)))?
+        }
+        None => Scheme::Random,
+    };
+    let options = ShredOptions {
+        scheme,
+        verify: args.flag("verify"),
+    };
+    let path = &args.positional[0];
+    actions::secure_delete_noninteractive(path, &options).map_err(CliError::Failed)?;
+    println!("'{}' securely deleted.", path);
+    Ok(())
+}
+
+fn split(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &["size", "parts"], &[])?;
+    args.expect(1, "split [--size MB | --parts N] FILE")?;
+    let positive = |name: &str, s: &str| {
+        s.parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| CliError::Usage(format!("Invalid {} '{}'", name, s)))
+    };
+    let mode = match (args.option("size"), args.option("parts")) {
+        (Some(_), Some(_)) => {
+            return Err(CliError::Usage(
+                "--size and --parts cannot be combined".to_string(),
+            ));
+        }
+        (Some(s), None) => SplitMode::Size(
+            positive("chunk size", s)?
+                .checked_mul(1024 * 1024)
+                .ok_or_else(|| CliError::Usage(format!("Chunk size '{}' MB is too large", s)))?,
+        ),
+        (None, Some(n)) => SplitMode::Parts(positive("part count", n)?),
+        (None, None) => SplitMode::Size(100 * 1024 * 1024),
+    };
+    let n =
+        actions::split_file_noninteractive(&args.positional[0], mode).map_err(CliError::Failed)?;
+    println!("Split complete: {} chunks", n);
+    Ok(())
+}
+
+fn join(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &[], &[])?;
+    args.expect(2, "join FILE OUTPUT")?;
+    let (base, output) = (&args.positional[0], &args.positional[1]);
+    let n = actions::join_files_noninteractive(base, output).map_err(CliError::Failed)?;
+    println!("Joined {} parts into: {}", n, output);
+    Ok(())
+}
+
+fn compress(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &["format", "level"], &["keep"])?;
+    args.expect(
+        1,
+        "compress [--format gz|bz2|xz|zst] [--level N] [--keep] FILE",
+    )?;
+    let codec = match args.option("format") {
+        Some(f) => Codec::parse(f)
+            .ok_or_else(|| CliError::Usage(format!("Unknown compression format '{}'", f)))?,
+        None => Codec::default(),
+    };
+    let level = match args.option("level") {
+        Some(n) => Some(
+            n.parse()
+                .map_err(|_| CliError::Usage(format!("Invalid --level value '{}'", n)))?,
+        ),
+        None => None,
+    };
+    let options = CompressOptions {
+        codec,
+        level,
+        keep_original: args.flag("keep"),
+    };
+    let msg = actions::compress_file_noninteractive(&args.positional[0], &options)
+        .map_err(CliError::Failed)?;
+    println!("{}", msg);
+    Ok(())
+}
+
+fn decompress(raw: &[String]) -> Result<(), CliError> {
+    let args = Args::parse(raw, &[], &["keep"])?;
+    args.expect(1, "decompress [--keep] FILE")?;
+    let msg = actions::decompress_file_noninteractive(&args.positional[0], args.flag("keep"))
+        .map_err(CliError::Failed)?;
+    println!("{}", msg);
+    Ok(())
+}
+
+fn backend_option(args: &Args) -> Result<BackendChoice, CliError> {
+    match args.option("backend") {
+        Some(b) => BackendChoice::parse(b)
+            .ok_or_else(|| CliError::Usage(format!("Unknown archive backend '{}'", b))),
+        None => Ok(BackendChoice::Auto),
+    }
+}
+
+/// The archive password, read from the first line of `--password-file`
+/// (`-` for stdin). There is deliberately no option taking the password
+/// itself, which would show it to everyone in `ps` output.
+fn password_option(args: &Args) -> Result<Option<String>, CliError> {
+    let Some(file) = args.option("password-file") else {
+        return Ok(None);
+    };
+    let text = if file == "-" {
+        let mut line = String::new();
+        std::io::stdin()
+            .read_line(&mut line)
+            .map_err(|e| CliError::Failed(format!("Error reading password: {}", e)))?;
+        line
+    } else {
+        std::fs::read_to_string(file)
+            .map_err(|e| CliError::Failed(format!("Error reading password file: {}", e)))?
+    };
+    let password = text.lines().next().unwrap_or("");
+    Ok((!password.is_empty()).then(|| password.to_string()))
+}
+
+/// How new entries are written and which sources they come from, from
+/// `--method`, `--level`, `--encryption`, `--base-dir` and `--exclude`.
+fn entry_options(args: &Args) -> Result<CreateOptions, CliError> {
+    let method = match args.option("method") {
+        Some(m) => Some(
+            CompressionMethod::parse(m)
+                .ok_or_else(|| CliError::Usage(format!("Unknown compression method '{}'", m)))?,
+        ),
+        None => None,
+    };
+    let encryption = match args.option("encryption") {
+        Some(e) => ZipEncryption::parse(e)
+            .ok_or_else(|| CliError::Usage(format!("Unknown zip encryption '{}'", e)))?,
+        None => ZipEncryption::default(),
+    };
+    let level = match args.option("level") {
+        Some(n) => Some(
+            n.parse()
+                .map_err(|_| CliError::Usage(format!("Invalid --level value '{}'", n)))?,
+        ),
+        None => None,
+    };
+    Ok(CreateOptions {
+        format: None,
+        method,
+        level,
+        encryption,
+        base_dir: args.option("base-dir").map(PathBuf::from),
+        exclude: args
+            .option("exclude")
+            .unwrap_or("")
+            .split(',')
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect(),
+    })
+}
+
+fn archive(raw: &[String]) -> Result<(), CliError> {
+    let (sub, rest) = match raw.split_first() {
+        Some((s, r)) => (s.as_str(), r),
+        None => {
+            return Err(CliError::Usage(
+                "Usage: redox-filemanager archive list|test|extract|create|update ...".to_string(),
+            ));
+        }
+    };
+    match sub {
+        "list" => {
+            let args = Args::parse(rest, &["backend", "password-file"], &[])?;
+            args.expect(1, "archive list [--backend B] [--password-file F] ARCHIVE")?;
+            let entries = actions::archive_list_noninteractive(
+                &args.positional[0],
+                password_option(&args)?.as_deref(),
+                backend_option(&args)?,
+            )
+            .map_err(CliError::Failed)?;
+            print!("{}", crate::archive::format_listing(&entries));
+            Ok(())
+        }
+        "test" => {
+            let args = Args::parse(rest, &["backend", "password-file"], &[])?;
+            args.expect(1, "archive test [--backend B] [--password-file F] ARCHIVE")?;
+            let msg = actions::archive_test_noninteractive(
+                &args.positional[0],
+                password_option(&args)?.as_deref(),
+                backend_option(&args)?,
+            )
+            .map_err(CliError::Failed)?;
+            println!("{}", msg);
+            Ok(())
+        }
+        "extract" => {
+            let args = Args::parse(
+                rest,
+                &[
+                    "backend",
+                    "password-file",
+                    "strip-components",
+                    "on-conflict",
+                ],
+                &["rewrite-unsafe"],
+            )?;
+            if args.positional.len() < 2 {
+                return Err(CliError::Usage(
+                    "Usage: redox-filemanager archive extract [--backend B] [--password-file F] [--strip-components N] [--on-conflict POLICY] [--rewrite-unsafe] ARCHIVE DEST [ENTRY...]"
+                        .to_string(),
+                ));
+            }
+            let strip_components = match args.option("strip-components") {
+                Some(n) => n.parse().map_err(|_| {
+                    CliError::Usage(format!("Invalid --strip-components value '{}'", n))
+                })?,
+                None => 0,
+            };
+            let policy = match args.option("on-conflict") {
+                Some(p) => ConflictPolicy::parse(p)
+                    .ok_or_else(|| CliError::Usage(format!("Unknown conflict policy '{}'", p)))?,
+                None => ConflictPolicy::Overwrite,
+            };
+            let options = ExtractOptions {
+                patterns: args.positional[2..].to_vec(),
+                strip_components,
+                policy,
+                safety: if args.flag("rewrite-unsafe") {
+                    PathSafety::Rewrite
+                } else {
+                    PathSafety::Reject
+                },
+            };
+            let msg = actions::archive_extract_noninteractive(
+                &args.positional[0],
+                &args.positional[1],
+                password_option(&args)?.as_deref(),
+                &options,
+                backend_option(&args)?,
+            )
+            .map_err(CliError::Failed)?;
+            println!("Archive extracted successfully:\n{}", msg);
+            Ok(())
+        }
+        "create" => {
+            let args = Args::parse(
+                rest,
+                &[
+                    "backend",
+                    "format",
+                    "password-file",
+                    "encryption",
+                    "method",
+                    "level",
+                    "base-dir",
+                    "exclude",
+                ],
+                &[],
+            )?;
+            if args.positional.len() < 2 {
+                return Err(CliError::Usage(
+                    "Usage: redox-filemanager archive create [--backend B] [--format F] [--password-file F] [--encryption E] [--method M] [--level N] [--base-dir DIR] [--exclude PATTERNS] OUTPUT SRC..."
+                        .to_string(),
+                ));
+            }
+            let format =
+                match args.option("format") {
+                    Some(f) => Some(ArchiveFormat::parse(f).ok_or_else(|| {
+                        CliError::Usage(format!("Unknown archive format '{}'", f))
+                    })?),
+                    None => None,
+                };
+            let msg = actions::archive_create_noninteractive(
+                &args.positional[1..],
+                &args.positional[0],
+                password_option(&args)?.as_deref(),
+                &CreateOptions {
+                    format,
+                    ..entry_options(&args)?
+                },
+                backend_option(&args)?,
+            )
+            .map_err(CliError::Failed)?;
+            println!("Archive created successfully:\n{}", msg);
+            Ok(())
+        }
+        "update" => {
+            let args = Args::parse(
+                rest,
+                &[
+                    "backend",
+                    "password-file",
+                    "encryption",
+                    "method",
+                    "level",
+                    "base-dir",
+                    "exclude",
+                    "compare",
+                    "delete",
+                ],
+                &[],
+            )?;
+            if args.positional.is_empty() {
+                return Err(CliError::Usage(
+                    "Usage: redox-filemanager archive update [--backend B] [--password-file F] [--encryption E] [--method M] [--level N] [--base-dir DIR] [--exclude PATTERNS] [--compare mtime|hash] [--delete PATTERNS] ARCHIVE [SRC...]"
+                        .to_string(),
+                ));
+            }
+            let compare = match args.option("compare") {
+                Some(c) => ChangeCheck::parse(c)
+                    .ok_or_else(|| CliError::Usage(format!("Unknown --compare value '{}'", c)))?,
+                None => ChangeCheck::default(),
+            };
+            let delete = args
+                .option("delete")
+                .unwrap_or("")
+                .split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect();
+            let msg = actions::archive_update_noninteractive(
+                &args.positional[0],
+                &args.positional[1..],
+                password_option(&args)?.as_deref(),
+                &UpdateOptions {
+                    compare,
+                    delete,
+                    create: entry_options(&args)?,
+                },
+                backend_option(&args)?,
+            )
+            .map_err(CliError::Failed)?;
+            println!("Archive updated successfully:\n{}", msg);
+            Ok(())
+        }
+        other => Err(CliError::Usage(format!(
+            "Unknown archive command '{}'",
+            other
+        ))),
+    }
+}
+
+fn gui(raw: &[String]) -> Result<(), CliError> {
+    Args::parse(raw, &[], &[])?.expect(0, "gui")?;
+    crate::gui::run_gui().map_err(|e| CliError::Failed(format!("Failed to launch GUI: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(&strings(args), &["algo", "level"], &["check", "keep"])
+    }
+
+    #[test]
+    fn options_flags_and_positionals_are_told_apart() {
+        let args = parse(&["a", "--algo", "md5", "--check", "b", "--level=9"]).unwrap();
+        assert_eq!(args.positional, ["a", "b"]);
+        assert_eq!(args.option("algo"), Some("md5"));
+        assert_eq!(args.option("level"), Some("9"));
+        assert_eq!(args.option("keep"), None);
+        assert!(args.flag("check"));
+        assert!(!args.flag("keep"));
+        // A later value wins; an empty inline value is still a value.
+        let args = parse(&["--level", "1", "--level="]).unwrap();
+        assert_eq!(args.option("level"), Some(""));
+        assert!(args.positional.is_empty());
+    }
+
+    #[test]
+    fn everything_after_a_double_dash_is_positional() {
+        let args = parse(&["--keep", "--", "--algo", "--", "-x"]).unwrap();
+        assert_eq!(args.positional, ["--algo", "--", "-x"]);
+        assert!(args.flag("keep"));
+        assert_eq!(args.option("algo"), None);
+        // Single dashes are never options.
+        assert_eq!(parse(&["-", "-k"]).unwrap().positional, ["-", "-k"]);
+    }
+
+    #[test]
+    fn bad_options_are_rejected() {
+        assert_eq!(
+            parse(&["--bogus"]).err().unwrap(),
+            "Unknown option '--bogus'"
+        );
+        assert_eq!(
+            parse(&["--bogus=1"]).err().unwrap(),
+            "Unknown option '--bogus'"
+        );
+        assert_eq!(
+            parse(&["file", "--algo"]).err().unwrap(),
+            "Option '--algo' requires a value"
+        );
+        // Flags take no value, inline or not.
+        assert!(parse(&["--check=yes"]).is_err());
+        let args = parse(&["x"]).unwrap();
+        assert!(args.expect(1, "hash FILE").is_ok());
+        assert_eq!(
+            args.expect(2, "rename OLD NEW").unwrap_err(),
+            "Usage: redox-filemanager rename OLD NEW"
+        );
+    }
+
+    #[test]
+    fn exit_codes_tell_failures_from_usage_errors() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("made");
+        let dir = dir.to_str().unwrap();
+        let run = |args: &[&str]| run(&strings(args));
+
+        assert_eq!(run(&["mkdir", dir]), 0);
+        assert!(std::path::Path::new(dir).is_dir());
+        assert_eq!(run(&["help"]), 0);
+        // The directory exists now, so the command runs and fails.
+        assert_eq!(run(&["mkdir", dir]), EXIT_FAILURE);
+        let missing = tmp.path().join("missing");
+        let missing = missing.to_str().unwrap();
+        assert_eq!(run(&["rename", missing, dir]), EXIT_FAILURE);
+
+        assert_eq!(run(&[]), EXIT_USAGE);
+        assert_eq!(run(&["frobnicate"]), EXIT_USAGE);
+        assert_eq!(run(&["mkdir"]), EXIT_USAGE);
+        assert_eq!(run(&["mkdir", "a", "b"]), EXIT_USAGE);
+        assert_eq!(run(&["mkdir", "--parents", dir]), EXIT_USAGE);
+        assert_eq!(run(&["hash", "--algo"]), EXIT_USAGE);
+        assert_eq!(run(&["hash", "--algo", "rot13", dir]), EXIT_USAGE);
+        assert_eq!(run(&["archive"]), EXIT_USAGE);
+        assert_eq!(
+            run(&["archive", "list", "--password", "pw", dir]),
+            EXIT_USAGE
+        );
+    }
+
+    #[test]
+    fn passwords_come_from_the_first_line_of_a_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("pw");
+        let password = |text: &str| {
+            std::fs::write(&file, text).unwrap();
+            let raw = strings(&["--password-file", file.to_str().unwrap()]);
+            password_option(&Args::parse(&raw, &["password-file"], &[]).unwrap())
+                .ok()
+                .unwrap()
+        };
+        assert_eq!(password("s3cret\nignored\n").as_deref(), Some("s3cret"));
+        assert_eq!(
+            password("with spaces \r\n").as_deref(),
+            Some("with spaces ")
+        );
+        assert_eq!(password("\nsecond"), None);
+        assert_eq!(password(""), None);
+        let none = Args::parse(&[], &["password-file"], &[]).unwrap();
+        assert!(matches!(password_option(&none), Ok(None)));
+        let raw = strings(&["--password-file", tmp.path().join("no").to_str().unwrap()]);
+        let args = Args::parse(&raw, &["password-file"], &[]).unwrap();
+        assert!(matches!(password_option(&args), Err(CliError::Failed(_))));
+    }
+}
@@ -0,0 +1,477 @@
+use std::cell::Cell;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use filetime::FileTime;
+
+/// Read size used when compressing and decompressing.
+const CHUNK: usize = 1024 * 1024;
+
+/// A single-file compression format, as written by gzip, bzip2, xz and zstd.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Codec {
+    #[default]
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Codec {
+    pub const ALL: [Codec; 4] = [Codec::Gzip, Codec::Bzip2, Codec::Xz, Codec::Zstd];
+
+    pub fn parse(s: &str) -> Option<Codec> {
+        match s
+            .trim()
+            .trim_start_matches('.')
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "gz" | "gzip" => Some(Codec::Gzip),
+            "bz2" | "bzip2" => Some(Codec::Bzip2),
+            "xz" | "lzma" => Some(Codec::Xz),
+            "zst" | "zstd" => Some(Codec::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Codec::Gzip => "gzip",
+            Codec::Bzip2 => "bzip2",
+            Codec::Xz => "xz",
+            Codec::Zstd => "zstd",
+        }
+    }
+
+    /// File extension, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Codec::Gzip => "gz",
+            Codec::Bzip2 => "bz2",
+            Codec::Xz => "xz",
+            Codec::Zstd => "zst",
+        }
+    }
+
+    /// Levels the format accepts, lowest (fastest) first.
+    pub fn levels(self) -> (u32, u32) {
+        match self {
+            Codec::Gzip | Codec::Bzip2 => (1, 9),
+            Codec::Xz => (0, 9),
+            Codec::Zstd => (1, 22),
+        }
+    }
+
+    /// The level the command-line tool uses when none is given.
+    pub fn default_level(self) -> u32 {
+        match self {
+            Codec::Gzip => 6,
+            Codec::Bzip2 => 9,
+            Codec::Xz => 6,
+            Codec::Zstd => 3,
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// How [`compress_file`] writes its output.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CompressOptions {
+    pub codec: Codec,
+    /// `None` uses the codec's default level.
+    pub level: Option<u32>,
+    /// Keep the input file instead of removing it once the output is written.
+    pub keep_original: bool,
+}
+
+/// The result of compressing or decompressing one file.
+#[derive(Clone, Debug)]
+pub struct CompressReport {
+    pub output: PathBuf,
+    pub codec: Codec,
+    /// Bytes read from the input file.
+    pub input_size: u64,
+    /// Bytes written to the output file.
+    pub output_size: u64,
+}
+
+impl CompressReport {
+    /// Compressed size as a percentage of the uncompressed size.
+    pub fn ratio(&self) -> u64 {
+        let (packed, plain) = if self.output_size <= self.input_size {
+            (self.output_size, self.input_size)
+        } else {
+            (self.input_size, self.output_size)
+        };
+        (packed * 100).checked_div(plain).unwrap_or(100)
+    }
+}
+
+/// Path of the compressed copy of `path`: `<path>.gz`, `<path>.zst`, ...
+pub fn compressed_path(path: &Path, codec: Codec) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", codec.extension()));
+    PathBuf::from(name)
+}
+
+/// The codec named by `path`'s extension and the path without it. The
+/// tarball shorthands (`.tgz`, `.tbz2`, `.txz`, `.tzst`) become `.tar`.
+pub fn decompressed_path(path: &Path) -> Option<(Codec, PathBuf)> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let (codec, tarball) = match ext.as_str() {
+        "tgz" => (Codec::Gzip, true),
+        "tbz" | "tbz2" => (Codec::Bzip2, true),
+        "txz" => (Codec::Xz, true),
+        "tzst" => (Codec::Zstd, true),
+        other => (Codec::parse(other)?, false),
+    };
+    let stem = path.with_extension("");
+    let output = if tarball {
+        stem.with_extension("tar")
+    } else {
+        stem
+    };
+    Some((codec, output))
+}
+
+fn canceled() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "canceled")
+}
+
+/// Compress `path` into `<path>.<ext>`, streaming it so memory use does not
+/// depend on the file size. The output takes the input's permissions and
+/// modification time, as gzip does; the input is removed afterwards unless
+/// `options.keep_original` is set. An existing output is never overwritten,
+/// and on error or cancel the partial output is removed.
+pub fn compress_file(
+    path: &Path,
+    options: &CompressOptions,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<CompressReport> {
+    let codec = options.codec;
+    let level = options.level.unwrap_or(codec.default_level());
+    let (low, high) = codec.levels();
+    if !(low..=high).contains(&level) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} levels run from {} to {}, not {}",
+                codec, low, high, level
+            ),
+        ));
+    }
+    let output = compressed_path(path, codec);
+    convert(
+        path,
+        &output,
+        codec,
+        Some(level),
+        options.keep_original,
+        progress,
+        cancel,
+    )
+}
+
+/// Decompress `path`, whose extension names the format, into the same path
+/// without that extension. Otherwise as [`compress_file`]; the checksum
+/// each format carries is checked before the input is removed.
+pub fn decompress_file(
+    path: &Path,
+    keep_original: bool,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<CompressReport> {
+    let (codec, output) = decompressed_path(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "cannot tell the compression of '{}'; expected .gz, .bz2, .xz or .zst",
+                path.display()
+            ),
+        )
+    })?;
+    convert(path, &output, codec, None, keep_original, progress, cancel)
+}
+
+/// Compress `input` into `output` at `level`, or decompress it if `level`
+/// is `None`, then carry the metadata over and remove `input` unless kept.
+fn convert(
+    input: &Path,
+    output: &Path,
+    codec: Codec,
+    level: Option<u32>,
+    keep_original: bool,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<CompressReport> {
+    let meta = fs::metadata(input)?;
+    if !meta.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("'{}' is not a regular file", input.display()),
+        ));
+    }
+    let reader = File::open(input)?;
+    // create_new, so a file that appeared since is not clobbered either
+    let file = File::create_new(output).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => {
+            io::Error::new(e.kind(), format!("'{}' already exists", output.display()))
+        }
+        _ => e,
+    })?;
+    let read = Cell::new(0);
+    let mut source = Counted {
+        inner: reader,
+        read: &read,
+    };
+    let mut copy = |from: &mut dyn Read, to: &mut dyn Write| {
+        pump(from, to, &read, meta.len(), progress, cancel)
+    };
+    let result = match level {
+        Some(level) => Encoder::new(codec, file, level).and_then(|mut encoder| {
+            copy(&mut source, &mut encoder)?;
+            encoder.finish()
+        }),
+        None => decoder(codec, source).and_then(|mut decoder| {
+            let mut file = file;
+            copy(&mut decoder, &mut file)?;
+            Ok(file)
+        }),
+    }
+    .and_then(|file| {
+        file.sync_all()?;
+        file.metadata()
+    });
+    let output_size = match result {
+        Ok(m) => m.len(),
+        Err(e) => {
+            let _ = fs::remove_file(output);
+            return Err(e);
+        }
+    };
+
+    fs::set_permissions(output, meta.permissions())?;
+    filetime::set_file_mtime(output, FileTime::from_last_modification_time(&meta))?;
+    if !keep_original {
+        progress(format!("Removing {}", input.display()));
+        fs::remove_file(input)?;
+    }
+    Ok(CompressReport {
+        output: output.to_path_buf(),
+        codec,
+        input_size: meta.len(),
+        output_size,
+    })
+}
+
+/// Copy `from` into `to`, with a message each time another percent of the
+/// `total` input bytes (counted in `read`) is through.
+fn pump(
+    from: &mut dyn Read,
+    to: &mut dyn Write,
+    read: &Cell<u64>,
+    total: u64,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<()> {
+    let mut buf = vec![0u8; CHUNK];
+    let mut last_pct = None;
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(canceled());
+        }
+        let n = match from.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        to.write_all(&buf[..n])?;
+        let done = read.get();
+        let pct = (done.min(total) * 100).checked_div(total).unwrap_or(100);
+        if last_pct != Some(pct) {
+            last_pct = Some(pct);
+            progress(format!("Read {}/{} bytes ({}%)", done, total, pct));
+        }
+    }
+}
+
+/// A reader that adds the bytes it reads to `read`, so progress can follow
+/// the input while a decoder owns it.
+struct Counted<'a, R> {
+    inner: R,
+    read: &'a Cell<u64>,
+}
+
+impl<R: Read> Read for Counted<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read.set(self.read.get() + n as u64);
+        Ok(n)
+    }
+}
+
+/// A decoder for every stream in `input`, as `gzip -d` decodes concatenated
+/// members into one file.
+fn decoder<'a, R: Read + 'a>(codec: Codec, input: R) -> io::Result<Box<dyn Read + 'a>> {
+    Ok(match codec {
+        Codec::Gzip => Box::new(flate2::read::MultiGzDecoder::new(input)),
+        Codec::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(input)),
+        Codec::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(input)),
+        Codec::Zstd => Box::new(zstd::stream::read::Decoder::new(input)?),
+    })
+}
+
+enum Encoder {
+    Gz(flate2::write::GzEncoder<File>),
+    Bz2(bzip2::write::BzEncoder<File>),
+    Xz(xz2::write::XzEncoder<File>),
+    Zst(zstd::stream::write::Encoder<'static, File>),
+}
+
+impl Encoder {
+    fn new(codec: Codec, file: File, level: u32) -> io::Result<Encoder> {
+        Ok(match codec {
+            Codec::Gzip => Encoder::Gz(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::new(level),
+            )),
+            Codec::Bzip2 => Encoder::Bz2(bzip2::write::BzEncoder::new(
+                file,
+                bzip2::Compression::new(level),
+            )),
+            Codec::Xz => Encoder::Xz(xz2::write::XzEncoder::new(file, level)),
+            Codec::Zstd => {
+                let mut encoder = zstd::stream::write::Encoder::new(file, level as i32)?;
+                // zstd leaves the checksum out by default; without it a
+                // damaged frame can decode to wrong data without an error
+                encoder.include_checksum(true)?;
+                Encoder::Zst(encoder)
+            }
+        })
+    }
+
+    fn finish(self) -> io::Result<File> {
+        match self {
+            Encoder::Gz(e) => e.finish(),
+            Encoder::Bz2(e) => e.finish(),
+            Encoder::Xz(e) => e.finish(),
+            Encoder::Zst(e) => e.finish(),
+        }
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Gz(e) => e.write(buf),
+            Encoder::Bz2(e) => e.write(buf),
+            Encoder::Xz(e) => e.write(buf),
+            Encoder::Zst(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Gz(e) => e.flush(),
+            Encoder::Bz2(e) => e.flush(),
+            Encoder::Xz(e) => e.flush(),
+            Encoder::Zst(e) => e.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_compress(path: &Path, options: &CompressOptions) -> io::Result<CompressReport> {
+        compress_file(path, options, &mut |_| {}, &AtomicBool::new(false))
+    }
+
+    fn run_decompress(path: &Path, keep: bool) -> io::Result<CompressReport> {
+        decompress_file(path, keep, &mut |_| {}, &AtomicBool::new(false))
+    }
+
+    #[test]
+    fn every_codec_round_trips() {
+        let data = "a line of a log file\n".repeat(5000);
+        for codec in Codec::ALL {
+            let tmp = tempfile::tempdir().unwrap();
+            let path = tmp.path().join("app.log");
+            fs::write(&path, &data).unwrap();
+            let options = CompressOptions {
+                codec,
+                ..CompressOptions::default()
+            };
+            let packed = run_compress(&path, &options).unwrap();
+            assert!(!path.exists(), "{}", codec);
+            assert!(packed.output_size < packed.input_size, "{}", codec);
+
+            let unpacked = run_decompress(&packed.output, false).unwrap();
+            assert_eq!(unpacked.output, path);
+            assert_eq!(unpacked.codec, codec);
+            assert!(!packed.output.exists(), "{}", codec);
+            assert_eq!(fs::read_to_string(&path).unwrap(), data, "{}", codec);
+        }
+    }
+
+    #[test]
+    fn existing_output_is_left_alone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("app.log");
+        fs::write(&path, "new").unwrap();
+        fs::write(tmp.path().join("app.log.gz"), "old").unwrap();
+        let options = CompressOptions {
+            keep_original: true,
+            ..CompressOptions::default()
+        };
+        let e = run_compress(&path, &options).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(tmp.path().join("app.log.gz")).unwrap(), b"old");
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn damaged_input_is_kept_and_nothing_is_written() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("app.log");
+        fs::write(&path, "a line of a log file\n".repeat(5000)).unwrap();
+        let options = CompressOptions {
+            codec: Codec::Zstd,
+            ..CompressOptions::default()
+        };
+        let packed = run_compress(&path, &options).unwrap().output;
+        let mut bytes = fs::read(&packed).unwrap();
+        let end = bytes.len() - 1;
+        bytes[end] ^= 0xff;
+        fs::write(&packed, bytes).unwrap();
+
+        assert!(run_decompress(&packed, false).is_err());
+        assert!(packed.exists());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn tarball_shorthands_decompress_to_tar() {
+        assert_eq!(
+            decompressed_path(Path::new("dir/site.tgz")),
+            Some((Codec::Gzip, PathBuf::from("dir/site.tar")))
+        );
+        assert_eq!(
+            decompressed_path(Path::new("notes.txt.ZST")),
+            Some((Codec::Zstd, PathBuf::from("notes.txt")))
+        );
+        assert_eq!(decompressed_path(Path::new("notes.txt")), None);
+    }
+}
@@ -0,0 +1,774 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use filetime::FileTime;
+use walkdir::WalkDir;
+
+/// Buffer size for streamed copies.
+const COPY_CHUNK: usize = 1024 * 1024;
+
+/// What to do when a file being copied already exists at the destination.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Leave the existing file alone.
+    Skip,
+    /// Replace the existing file.
+    Overwrite,
+    /// Replace the existing file only if the source has a newer mtime.
+    OverwriteIfNewer,
+    /// Keep both by copying to "name (1).ext", "name (2).ext", ...
+    Rename,
+}
+
+impl ConflictPolicy {
+    pub const ALL: [ConflictPolicy; 4] = [
+        ConflictPolicy::Skip,
+        ConflictPolicy::Overwrite,
+        ConflictPolicy::OverwriteIfNewer,
+        ConflictPolicy::Rename,
+    ];
+
+    /// Parse a policy name as typed on the command line or in the menu.
+    pub fn parse(s: &str) -> Option<ConflictPolicy> {
+        match s.trim().to_lowercase().as_str() {
+            "skip" => Some(ConflictPolicy::Skip),
+            "overwrite" => Some(ConflictPolicy::Overwrite),
+            "newer" | "overwrite-if-newer" => Some(ConflictPolicy::OverwriteIfNewer),
+            "rename" => Some(ConflictPolicy::Rename),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Skip => "skip",
+            ConflictPolicy::Overwrite => "overwrite",
+            ConflictPolicy::OverwriteIfNewer => "newer",
+            ConflictPolicy::Rename => "rename",
+        }
+    }
+}
+
+/// Options for [`copy_recursive`].
+#[derive(Clone, Copy, Debug)]
+pub struct CopyOptions {
+    pub policy: ConflictPolicy,
+    /// Copy Unix permission bits / the read-only flag.
+    pub preserve_permissions: bool,
+    /// Copy access and modification times.
+    pub preserve_times: bool,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self {
+            policy: ConflictPolicy::Overwrite,
+            preserve_permissions: true,
+            preserve_times: true,
+        }
+    }
+}
+
+/// How a single collision was resolved.
+#[derive(Clone, Debug)]
+pub enum ConflictOutcome {
+    Skipped,
+    Overwritten,
+    Renamed(PathBuf),
+}
+
+/// A destination path that already existed when the copy reached it.
+#[derive(Clone, Debug)]
+pub struct Conflict {
+    pub path: PathBuf,
+    pub outcome: ConflictOutcome,
+}
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.outcome {
+            ConflictOutcome::Skipped => write!(f, "{} exists, skipped", self.path.display()),
+            ConflictOutcome::Overwritten => {
+                write!(f, "{} exists, overwritten", self.path.display())
+            }
+            ConflictOutcome::Renamed(to) => write!(
+                f,
+                "{} exists, copied as {}",
+                self.path.display(),
+                to.display()
+            ),
+        }
+    }
+}
+
+/// Summary of a recursive copy. Conflicts and per-entry errors are collected
+/// rather than aborting the whole operation.
+#[derive(Debug, Default)]
+pub struct CopyReport {
+    pub files_copied: usize,
+    pub dirs_created: usize,
+    pub bytes_copied: u64,
+    pub conflicts: Vec<Conflict>,
+    pub errors: Vec<(PathBuf, io::Error)>,
+    /// Where the top-level source ended up, after any rename.
+    pub target: PathBuf,
+    /// Nothing existed at `target` before the copy, so removing it undoes the copy.
+    pub target_is_new: bool,
+}
+
+impl CopyReport {
+    /// One-line summary suitable for a status bar or the CLI.
+    pub fn summary(&self) -> String {
+        let mut s = format!(
+            "{} files, {} bytes copied",
+            self.files_copied, self.bytes_copied
+        );
+        if !self.conflicts.is_empty() {
+            s.push_str(&format!(", {} conflicts", self.conflicts.len()));
+        }
+        if !self.errors.is_empty() {
+            s.push_str(&format!(", {} errors", self.errors.len()));
+        }
+        s
+    }
+}
+
+/// Copy a file or a whole directory tree from `src` to `dst`.
+///
+/// `dst` is the path the copy will have, not the directory it is copied into.
+/// Existing directories are merged; colliding files are resolved with
+/// `options.policy` and recorded in the report. With [`ConflictPolicy::Rename`]
+/// an existing top-level destination is renamed as a whole.
+pub fn copy_recursive(src: &Path, dst: &Path, options: &CopyOptions) -> io::Result<CopyReport> {
+    let src_meta = fs::symlink_metadata(src)?;
+    let mut report = CopyReport::default();
+    let existed = fs::symlink_metadata(dst).is_ok();
+
+    if src_meta.is_dir() {
+        if let (Ok(s), Ok(d)) = (src.canonicalize(), absolute_parent(dst))
+            && d.starts_with(&s)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "cannot copy directory '{}' into itself",
+                    src.to_string_lossy()
+                ),
+            ));
+        }
+        let root = if options.policy == ConflictPolicy::Rename && dst.exists() {
+            let renamed = unique_name(dst);
+            report.conflicts.push(Conflict {
+                path: dst.to_path_buf(),
+                outcome: ConflictOutcome::Renamed(renamed.clone()),
+            });
+            renamed
+        } else {
+            dst.to_path_buf()
+        };
+        report.target_is_new = !existed || root != dst;
+        copy_tree(src, &root, options, &mut report);
+        report.target = root;
+    } else {
+        if same_file(src, dst) {
+            return Err(same_file_error());
+        }
+        copy_entry(src, dst, &src_meta, options, &mut report);
+        let renamed = report.conflicts.iter().find_map(|c| match &c.outcome {
+            ConflictOutcome::Renamed(to) => Some(to.clone()),
+            _ => None,
+        });
+        report.target_is_new = !existed || renamed.is_some();
+        report.target = renamed.unwrap_or_else(|| dst.to_path_buf());
+    }
+    Ok(report)
+}
+
+fn copy_tree(src: &Path, dst: &Path, options: &CopyOptions, report: &mut CopyReport) {
+    // Directory times are applied last, since filling a directory bumps its mtime.
+    let mut dir_times: Vec<(PathBuf, fs::Metadata)> = Vec::new();
+
+    for entry in WalkDir::new(src).follow_links(false) {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                let path = e.path().map(Path::to_path_buf).unwrap_or_default();
+                report.errors.push((path, e.into()));
+                continue;
+            }
+        };
+        let rel = match entry.path().strip_prefix(src) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+        let target = dst.join(rel);
+        let meta = match entry.path().symlink_metadata() {
+            Ok(m) => m,
+            Err(e) => {
+                report.errors.push((entry.path().to_path_buf(), e));
+                continue;
+            }
+        };
+
+        if meta.is_dir() {
+            match fs::symlink_metadata(&target) {
+                Ok(existing) if existing.is_dir() => {}
+                Ok(_) => {
+                    // A file is in the way of a directory; no policy can merge these.
+                    report.errors.push((
+                        target.clone(),
+                        io::Error::new(
+                            io::ErrorKind::AlreadyExists,
+                            "a file exists where a directory is needed",
+                        ),
+                    ));
+                    continue;
+                }
+                Err(_) => match fs::create_dir_all(&target) {
+                    Ok(_) => report.dirs_created += 1,
+                    Err(e) => {
+                        report.errors.push((target.clone(), e));
+                        continue;
+                    }
+                },
+            }
+            dir_times.push((target, meta));
+        } else {
+            copy_entry(entry.path(), &target, &meta, options, report);
+        }
+    }
+
+    for (dir, meta) in dir_times.into_iter().rev() {
+        if let Err(e) = apply_metadata(&dir, &meta, options) {
+            report.errors.push((dir, e));
+        }
+    }
+}
+
+/// Copy a single non-directory entry, resolving a collision at `target` first.
+fn copy_entry(
+    src: &Path,
+    target: &Path,
+    meta: &fs::Metadata,
+    options: &CopyOptions,
+    report: &mut CopyReport,
+) {
+    let mut target = target.to_path_buf();
+    // No policy applies: overwriting would truncate the source itself.
+    if same_file(src, &target) {
+        report.errors.push((target, same_file_error()));
+        return;
+    }
+    if let Ok(existing) = fs::symlink_metadata(&target) {
+        let outcome = match options.policy {
+            ConflictPolicy::Skip => ConflictOutcome::Skipped,
+            ConflictPolicy::Overwrite => ConflictOutcome::Overwritten,
+            ConflictPolicy::OverwriteIfNewer => {
+                let src_time = FileTime::from_last_modification_time(meta);
+                let dst_time = FileTime::from_last_modification_time(&existing);
+                if src_time > dst_time {
+                    ConflictOutcome::Overwritten
+                } else {
+                    ConflictOutcome::Skipped
+                }
+            }
+            ConflictPolicy::Rename => ConflictOutcome::Renamed(unique_name(&target)),
+        };
+        report.conflicts.push(Conflict {
+            path: target.clone(),
+            outcome: outcome.clone(),
+        });
+        match outcome {
+            ConflictOutcome::Skipped => return,
+            ConflictOutcome::Overwritten => {
+                if existing.is_dir() {
+                    report.errors.push((
+                        target,
+                        io::Error::new(
+                            io::ErrorKind::AlreadyExists,
+                            "a directory exists where a file is needed",
+                        ),
+                    ));
+                    return;
+                }
+                // Replace symlinks rather than writing through them.
+                if existing.file_type().is_symlink()
+                    && let Err(e) = fs::remove_file(&target)
+                {
+                    report.errors.push((target, e));
+                    return;
+                }
+            }
+            ConflictOutcome::Renamed(to) => target = to,
+        }
+    }
+
+    if let Some(parent) = target.parent()
+        && !parent.as_os_str().is_empty()
+        && let Err(e) = fs::create_dir_all(parent)
+    {
+        report.errors.push((target, e));
+        return;
+    }
+
+    let result = if meta.file_type().is_symlink() {
+        copy_symlink(src, &target).map(|_| 0)
+    } else {
+        fs::copy(src, &target).and_then(|n| apply_metadata(&target, meta, options).map(|_| n))
+    };
+    match result {
+        Ok(n) => {
+            report.files_copied += 1;
+            report.bytes_copied += n;
+        }
+        Err(e) => report.errors.push((src.to_path_buf(), e)),
+    }
+}
+
+/// Whether `a` and `b` are the same file, or hard links to one. Symlinks
+/// are not followed.
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::symlink_metadata(a), fs::symlink_metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn same_file_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "source and destination are the same file",
+    )
+}
+
+#[cfg(unix)]
+fn copy_symlink(src: &Path, target: &Path) -> io::Result<()> {
+    let link = fs::read_link(src)?;
+    std::os::unix::fs::symlink(link, target)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(src: &Path, target: &Path) -> io::Result<()> {
+    // No portable way to recreate the link; copy what it points at instead.
+    fs::copy(src, target).map(|_| ())
+}
+
+fn apply_metadata(target: &Path, meta: &fs::Metadata, options: &CopyOptions) -> io::Result<()> {
+    if options.preserve_permissions {
+        fs::set_permissions(target, meta.permissions())?;
+    }
+    if options.preserve_times {
+        let atime = FileTime::from_last_access_time(meta);
+        let mtime = FileTime::from_last_modification_time(meta);
+        filetime::set_file_times(target, atime, mtime)?;
+    }
+    Ok(())
+}
+
+/// First free "name (N).ext" next to `path`.
+pub fn unique_name(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut n = 1;
+    loop {
+        let candidate = parent.join(format!("{} ({}){}", stem, n, ext));
+        if fs::symlink_metadata(&candidate).is_err() {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+/// Canonical form of `path` even when `path` itself does not exist yet.
+fn absolute_parent(path: &Path) -> io::Result<PathBuf> {
+    match path.canonicalize() {
+        Ok(p) => Ok(p),
+        Err(_) => {
+            let parent = match path.parent() {
+                Some(p) if !p.as_os_str().is_empty() => p.canonicalize()?,
+                _ => std::env::current_dir()?,
+            };
+            Ok(parent.join(path.file_name().unwrap_or_default()))
+        }
+    }
+}
+
+/// How [`move_path`] ended up moving its source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveMethod {
+    /// A plain `rename` on the same filesystem.
+    Renamed,
+    /// Copied across filesystems, verified, then the source was removed.
+    CopiedAndDeleted,
+}
+
+/// Move a file or directory tree from `src` to `dst`.
+///
+/// Tries `rename` first. When that fails because the two paths are on different
+/// filesystems (EXDEV), the tree is copied, every file is compared against its
+/// source with BLAKE3, and only then is the source deleted. `progress` receives
+/// human-readable status lines; setting `cancel` aborts the copy and removes
+/// the partial destination.
+pub fn move_path(
+    src: &Path,
+    dst: &Path,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<MoveMethod> {
+    match fs::rename(src, dst) {
+        Ok(_) => return Ok(MoveMethod::Renamed),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {}
+        Err(e) => return Err(e),
+    }
+
+    let src_meta = fs::symlink_metadata(src)?;
+    if src_meta.is_dir() && fs::symlink_metadata(dst).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("destination '{}' already exists", dst.display()),
+        ));
+    }
+    progress(format!(
+        "{} and {} are on different filesystems, copying instead",
+        src.display(),
+        dst.display()
+    ));
+    move_by_copy(src, dst, &src_meta, progress, cancel)?;
+    Ok(MoveMethod::CopiedAndDeleted)
+}
+
+/// The fallback of [`move_path`]: copy, verify, then delete the source. A
+/// directory is copied to `dst` itself, which does not exist yet; a file
+/// goes to a temporary name beside `dst` and only replaces it once verified,
+/// so a failed move leaves an existing `dst` as it was.
+fn move_by_copy(
+    src: &Path,
+    dst: &Path,
+    src_meta: &fs::Metadata,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<()> {
+    let staged = if src_meta.is_dir() {
+        dst.to_path_buf()
+    } else {
+        temp_beside(dst)
+    };
+    let result = copy_for_move(src, &staged, src_meta, progress, cancel)
+        .and_then(|_| {
+            progress("Verifying copy".to_string());
+            verify_tree(src, &staged, cancel)
+        })
+        .and_then(|_| {
+            if staged == dst {
+                Ok(())
+            } else {
+                fs::rename(&staged, dst)
+            }
+        });
+    if let Err(e) = result {
+        // Leave the source untouched and drop only what this move wrote.
+        let _ = remove_path(&staged);
+        return Err(e);
+    }
+
+    progress(format!("Removing source {}", src.display()));
+    remove_path(src)
+}
+
+/// A hidden name next to `dst` to copy a file to before renaming it into place.
+fn temp_beside(dst: &Path) -> PathBuf {
+    let name = dst
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    dst.with_file_name(format!(".{}.move-{}", name, std::process::id()))
+}
+
+fn copy_for_move(
+    src: &Path,
+    dst: &Path,
+    src_meta: &fs::Metadata,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<()> {
+    let total: u64 = WalkDir::new(src)
+        .follow_links(false)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum();
+    let options = CopyOptions::default();
+    let mut copied = 0u64;
+    let mut last_pct = None;
+    let mut on_bytes = |n: u64| {
+        copied += n;
+        let pct = (copied * 100).checked_div(total).unwrap_or(100);
+        if last_pct != Some(pct) {
+            last_pct = Some(pct);
+            progress(format!("Copied {}/{} bytes ({}%)", copied, total, pct));
+        }
+    };
+
+    if !src_meta.is_dir() {
+        return copy_file_streaming(src, dst, src_meta, &options, &mut on_bytes, cancel);
+    }
+
+    let mut dir_times: Vec<(PathBuf, fs::Metadata)> = Vec::new();
+    for entry in WalkDir::new(src).follow_links(false) {
+        let entry = entry?;
+        let rel = entry.path().strip_prefix(src).unwrap_or(Path::new(""));
+        let target = dst.join(rel);
+        let meta = entry.path().symlink_metadata()?;
+        if meta.is_dir() {
+            fs::create_dir(&target)?;
+            dir_times.push((target, meta));
+        } else {
+            copy_file_streaming(
+                entry.path(),
+                &target,
+                &meta,
+                &options,
+                &mut on_bytes,
+                cancel,
+            )?;
+        }
+    }
+    for (dir, meta) in dir_times.into_iter().rev() {
+        apply_metadata(&dir, &meta, &options)?;
+    }
+    Ok(())
+}
+
+/// Copy one file in fixed-size chunks to `dst`, which must be a fresh path,
+/// reporting bytes written and honouring `cancel`.
+fn copy_file_streaming(
+    src: &Path,
+    dst: &Path,
+    meta: &fs::Metadata,
+    options: &CopyOptions,
+    on_bytes: &mut dyn FnMut(u64),
+    cancel: &AtomicBool,
+) -> io::Result<()> {
+    if meta.file_type().is_symlink() {
+        return copy_symlink(src, dst);
+    }
+    let mut reader = fs::File::open(src)?;
+    let mut writer = fs::File::create_new(dst)?;
+    let mut buf = vec![0u8; COPY_CHUNK];
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "move canceled"));
+        }
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        writer.write_all(&buf[..n])?;
+        on_bytes(n as u64);
+    }
+    writer.sync_all()?;
+    drop(writer);
+    apply_metadata(dst, meta, options)
+}
+
+/// Check that every regular file under `dst` matches its counterpart under `src`.
+fn verify_tree(src: &Path, dst: &Path, cancel: &AtomicBool) -> io::Result<()> {
+    for entry in WalkDir::new(src).follow_links(false) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        if cancel.load(Ordering::SeqCst) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "move canceled"));
+        }
+        // A single-file move walks just the file itself, with an empty relative path.
+        let target = match entry.path().strip_prefix(src) {
+            Ok(rel) if !rel.as_os_str().is_empty() => dst.join(rel),
+            _ => dst.to_path_buf(),
+        };
+        if hash_file(entry.path())? != hash_file(&target)? {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("verification failed for '{}'", target.display()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn hash_file(path: &Path) -> io::Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    let mut file = fs::File::open(path)?;
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize())
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(m) if m.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use filetime::FileTime;
+
+    use super::*;
+
+    fn options(policy: ConflictPolicy) -> CopyOptions {
+        CopyOptions {
+            policy,
+            ..CopyOptions::default()
+        }
+    }
+
+    fn set_mtime(path: &Path, secs: i64) {
+        filetime::set_file_mtime(path, FileTime::from_unix_time(secs, 0)).unwrap();
+    }
+
+    #[test]
+    fn copying_a_file_onto_itself_fails_under_every_policy() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("f");
+        fs::write(&file, "keep me").unwrap();
+        for policy in ConflictPolicy::ALL {
+            let err = copy_recursive(&file, &file, &options(policy)).unwrap_err();
+            assert!(err.to_string().contains("same file"), "{}", err);
+            assert_eq!(fs::read_to_string(&file).unwrap(), "keep me");
+        }
+        assert_eq!(fs::read_dir(tmp.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn copying_onto_a_hard_link_of_the_source_fails() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("src");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("a"), "data").unwrap();
+        let dst = tmp.path().join("dst");
+        fs::create_dir(&dst).unwrap();
+        fs::hard_link(src.join("a"), dst.join("a")).unwrap();
+
+        let report = copy_recursive(&src, &dst, &options(ConflictPolicy::Overwrite)).unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.files_copied, 0);
+        assert_eq!(fs::read_to_string(src.join("a")).unwrap(), "data");
+    }
+
+    #[test]
+    fn conflict_policies_resolve_existing_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("new.txt");
+        fs::write(&src, "new").unwrap();
+        set_mtime(&src, 2_000_000);
+        let dst = tmp.path().join("old.txt");
+        let reset = || {
+            fs::write(&dst, "old").unwrap();
+            set_mtime(&dst, 1_000_000);
+        };
+
+        reset();
+        let report = copy_recursive(&src, &dst, &options(ConflictPolicy::Skip)).unwrap();
+        assert!(matches!(
+            report.conflicts[0].outcome,
+            ConflictOutcome::Skipped
+        ));
+        assert_eq!(report.files_copied, 0);
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "old");
+
+        let report = copy_recursive(&src, &dst, &options(ConflictPolicy::Overwrite)).unwrap();
+        assert!(matches!(
+            report.conflicts[0].outcome,
+            ConflictOutcome::Overwritten
+        ));
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "new");
+
+        reset();
+        copy_recursive(&src, &dst, &options(ConflictPolicy::OverwriteIfNewer)).unwrap();
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "new");
+        fs::write(&dst, "newest").unwrap();
+        set_mtime(&dst, 3_000_000);
+        let report =
+            copy_recursive(&src, &dst, &options(ConflictPolicy::OverwriteIfNewer)).unwrap();
+        assert!(matches!(
+            report.conflicts[0].outcome,
+            ConflictOutcome::Skipped
+        ));
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "newest");
+
+        reset();
+        let report = copy_recursive(&src, &dst, &options(ConflictPolicy::Rename)).unwrap();
+        let renamed = tmp.path().join("old (1).txt");
+        assert_eq!(report.target, renamed);
+        assert!(report.target_is_new);
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "old");
+        assert_eq!(fs::read_to_string(&renamed).unwrap(), "new");
+    }
+
+    #[test]
+    fn failed_move_by_copy_keeps_the_existing_destination() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("src.txt");
+        fs::write(&src, "moved").unwrap();
+        let dst = tmp.path().join("dst.txt");
+        fs::write(&dst, "precious").unwrap();
+        let meta = fs::symlink_metadata(&src).unwrap();
+
+        let err = move_by_copy(&src, &dst, &meta, &mut |_| {}, &AtomicBool::new(true));
+        assert_eq!(err.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "precious");
+        assert_eq!(fs::read_to_string(&src).unwrap(), "moved");
+        assert_eq!(fs::read_dir(tmp.path()).unwrap().count(), 2);
+
+        move_by_copy(&src, &dst, &meta, &mut |_| {}, &AtomicBool::new(false)).unwrap();
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "moved");
+        assert!(!src.exists());
+        assert_eq!(fs::read_dir(tmp.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn directories_merge_and_refuse_to_copy_into_themselves() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("sub/a"), "a").unwrap();
+        let dst = tmp.path().join("dst");
+        fs::create_dir_all(dst.join("sub")).unwrap();
+        fs::write(dst.join("sub/b"), "b").unwrap();
+
+        let report = copy_recursive(&src, &dst, &CopyOptions::default()).unwrap();
+        assert_eq!(report.files_copied, 1);
+        assert!(!report.target_is_new);
+        assert_eq!(fs::read_to_string(dst.join("sub/a")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dst.join("sub/b")).unwrap(), "b");
+
+        let err = copy_recursive(&src, &src.join("sub/inner"), &CopyOptions::default());
+        assert!(err.unwrap_err().to_string().contains("into itself"));
+    }
+}
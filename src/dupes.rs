@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use walkdir::WalkDir;
+
+use crate::hash::{self, HashAlgo};
+use crate::trash;
+
+/// Bytes hashed from the start of each same-size candidate before committing
+/// to a full hash.
+const PARTIAL_BLOCK: usize = 64 * 1024;
+
+/// Files with identical contents.
+#[derive(Clone, Debug)]
+pub struct DuplicateGroup {
+    /// Size of each file in bytes.
+    pub size: u64,
+    /// Full BLAKE3 hash shared by every file.
+    pub hash: String,
+    /// Sorted paths; hard links to the same inode appear only once.
+    pub files: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// Bytes that would be freed by keeping a single copy.
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.files.len() as u64 - 1)
+    }
+}
+
+fn canceled() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "duplicate search canceled")
+}
+
+/// Find duplicate files anywhere under `dir`.
+///
+/// Files are grouped by size first, then by a BLAKE3 hash of their first
+/// 64 KiB, and only the survivors are hashed in full. Empty files and symlinks
+/// are ignored. Groups are returned largest waste first.
+pub fn find_duplicates(
+    dir: &Path,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<Vec<DuplicateGroup>> {
+    if !dir.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Unable to read directory: {}", dir.display()),
+        ));
+    }
+
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    #[cfg(unix)]
+    let mut seen_inodes = std::collections::HashSet::new();
+    let mut scanned = 0usize;
+    for entry in WalkDir::new(dir)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .flatten()
+    {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(canceled());
+        }
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.len() == 0 {
+            continue;
+        }
+        // Hard links share their data already; count each inode once.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if !seen_inodes.insert((meta.dev(), meta.ino())) {
+                continue;
+            }
+        }
+        by_size
+            .entry(meta.len())
+            .or_default()
+            .push(entry.into_path());
+        scanned += 1;
+    }
+    by_size.retain(|_, files| files.len() > 1);
+    let candidates: usize = by_size.values().map(Vec::len).sum();
+    progress(format!(
+        "Scanned {} files, {} share a size with another file",
+        scanned, candidates
+    ));
+
+    let mut by_partial: HashMap<(u64, String), Vec<PathBuf>> = HashMap::new();
+    let mut done = 0usize;
+    for (size, files) in by_size {
+        for path in files {
+            if cancel.load(Ordering::SeqCst) {
+                return Err(canceled());
+            }
+            done += 1;
+            if let Ok(partial) = partial_hash(&path) {
+                by_partial.entry((size, partial)).or_default().push(path);
+            }
+        }
+        progress(format!(
+            "Checked first block of {}/{} files",
+            done, candidates
+        ));
+    }
+    by_partial.retain(|_, files| files.len() > 1);
+
+    let mut groups = Vec::new();
+    for ((size, partial), files) in by_partial {
+        if size <= PARTIAL_BLOCK as u64 {
+            // The partial hash already covered the whole file.
+            groups.push(DuplicateGroup {
+                size,
+                hash: partial,
+                files,
+            });
+            continue;
+        }
+        let mut by_full: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for path in files {
+            progress(format!("Hashing {}", path.display()));
+            match hash::hash_file(&path, HashAlgo::Blake3, &mut |_| {}, cancel) {
+                Ok(full) => by_full.entry(full).or_default().push(path),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => return Err(canceled()),
+                Err(_) => {}
+            }
+        }
+        for (hash, files) in by_full {
+            if files.len() > 1 {
+                groups.push(DuplicateGroup { size, hash, files });
+            }
+        }
+    }
+
+    for group in &mut groups {
+        group.files.sort();
+    }
+    groups.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then(a.files.cmp(&b.files)));
+    Ok(groups)
+}
+
+/// BLAKE3 of the first `PARTIAL_BLOCK` bytes of a file.
+fn partial_hash(path: &Path) -> io::Result<String> {
+    let mut buf = Vec::with_capacity(PARTIAL_BLOCK);
+    fs::File::open(path)?
+        .take(PARTIAL_BLOCK as u64)
+        .read_to_end(&mut buf)?;
+    Ok(blake3::hash(&buf).to_hex().to_string())
+}
+
+/// What to do with the copies that are not kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /// Move them to the trash.
+    Delete,
+    /// Replace them with hard links to the kept file.
+    Hardlink,
+    /// Replace them with symbolic links to the kept file.
+    Symlink,
+}
+
+impl Resolution {
+    pub const ALL: [Resolution; 3] = [
+        Resolution::Delete,
+        Resolution::Hardlink,
+        Resolution::Symlink,
+    ];
+
+    pub fn parse(s: &str) -> Option<Resolution> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "delete" | "trash" => Some(Resolution::Delete),
+            "hardlink" | "link" => Some(Resolution::Hardlink),
+            "symlink" => Some(Resolution::Symlink),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Resolution::Delete => "Delete",
+            Resolution::Hardlink => "Hardlink",
+            Resolution::Symlink => "Symlink",
+        }
+    }
+}
+
+/// A duplicate that was dealt with.
+#[derive(Debug)]
+pub struct Resolved {
+    pub path: PathBuf,
+    /// Trash entry name, for [`Resolution::Delete`].
+    pub trash_name: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ResolveReport {
+    pub resolved: Vec<Resolved>,
+    pub errors: Vec<(PathBuf, io::Error)>,
+}
+
+/// Keep `group.files[keep]` and apply `resolution` to every other file.
+///
+/// Every file is re-hashed first, since it may have changed after the scan.
+/// If the kept file no longer matches the group nothing is touched; any
+/// other file that no longer matches is left alone.
+pub fn resolve_group(
+    group: &DuplicateGroup,
+    keep: usize,
+    resolution: Resolution,
+) -> io::Result<ResolveReport> {
+    let kept = group.files.get(keep).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "kept file is not in the group")
+    })?;
+    let cancel = AtomicBool::new(false);
+    let unchanged = |path: &Path| {
+        let h = hash::hash_file(path, HashAlgo::Blake3, &mut |_| {}, &cancel)?;
+        if h == group.hash {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "contents changed since the scan",
+            ))
+        }
+    };
+    unchanged(kept).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("{} (the file to keep): {}", kept.display(), e),
+        )
+    })?;
+    let kept = std::path::absolute(kept)?;
+    let mut report = ResolveReport::default();
+    for (i, path) in group.files.iter().enumerate() {
+        if i == keep {
+            continue;
+        }
+        let result = unchanged(path).and_then(|_| match resolution {
+            Resolution::Delete => trash::move_to_trash(path).map(|item| Some(item.name)),
+            Resolution::Hardlink => {
+                replace_with(path, |tmp| fs::hard_link(&kept, tmp)).map(|_| None)
+            }
+            Resolution::Symlink => replace_with(path, |tmp| symlink(&kept, tmp)).map(|_| None),
+        });
+        match result {
+            Ok(trash_name) => report.resolved.push(Resolved {
+                path: path.clone(),
+                trash_name,
+            }),
+            Err(e) => report.errors.push((path.clone(), e)),
+        }
+    }
+    Ok(report)
+}
+
+/// Create a link next to `path` with `make`, then rename it over `path` so
+/// the original is never missing.
+fn replace_with(path: &Path, make: impl Fn(&Path) -> io::Result<()>) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{}.dedup-tmp", name));
+    let _ = fs::remove_file(&tmp);
+    make(&tmp)?;
+    if let Err(e) = fs::rename(&tmp, path) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trash::tests::with_temp_trash;
+
+    fn find(dir: &Path) -> Vec<DuplicateGroup> {
+        find_duplicates(dir, &mut |_| {}, &AtomicBool::new(false)).unwrap()
+    }
+
+    /// Names of the files in `group`, relative to `dir`.
+    fn names(dir: &Path, group: &DuplicateGroup) -> Vec<String> {
+        group
+            .files
+            .iter()
+            .map(|f| {
+                f.strip_prefix(dir)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn groups_by_size_then_first_block_then_full_hash() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("a1"), "same bytes").unwrap();
+        fs::write(dir.join("sub/a2"), "same bytes").unwrap();
+        // Same size, different first block.
+        fs::write(dir.join("b"), "SAME BYTES").unwrap();
+        // Same first block, different ending.
+        let mut big = vec![1u8; PARTIAL_BLOCK + 1000];
+        fs::write(dir.join("big1"), &big).unwrap();
+        fs::write(dir.join("big3"), &big).unwrap();
+        *big.last_mut().unwrap() = 2;
+        fs::write(dir.join("big2"), &big).unwrap();
+        fs::write(dir.join("empty1"), "").unwrap();
+        fs::write(dir.join("empty2"), "").unwrap();
+
+        let groups = find(dir);
+        assert_eq!(groups.len(), 2, "{:?}", groups);
+        assert_eq!(names(dir, &groups[0]), ["big1", "big3"]);
+        assert_eq!(groups[0].wasted(), PARTIAL_BLOCK as u64 + 1000);
+        assert_eq!(names(dir, &groups[1]), ["a1", "sub/a2"]);
+        // Small files are hashed whole by the first-block pass; the hash
+        // still has to match a full hash for resolving to work.
+        let full = hash::hash_file(
+            &dir.join("a1"),
+            HashAlgo::Blake3,
+            &mut |_| {},
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        assert_eq!(groups[1].hash, full);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_links_to_one_file_are_not_duplicates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("a"), "data").unwrap();
+        fs::hard_link(dir.join("a"), dir.join("b")).unwrap();
+        assert!(find(dir).is_empty());
+        fs::write(dir.join("c"), "data").unwrap();
+        let groups = find(dir);
+        assert_eq!(names(dir, &groups[0]), ["a", "c"]);
+    }
+
+    /// Three copies of the same text under a fresh directory, as one group.
+    fn three_copies() -> (tempfile::TempDir, DuplicateGroup) {
+        let tmp = tempfile::tempdir().unwrap();
+        for name in ["a", "b", "c"] {
+            fs::write(tmp.path().join(name), "copy").unwrap();
+        }
+        let group = find(tmp.path()).remove(0);
+        (tmp, group)
+    }
+
+    #[test]
+    fn delete_trashes_the_other_copies() {
+        with_temp_trash(|_| {
+            let (tmp, group) = three_copies();
+            let report = resolve_group(&group, 1, Resolution::Delete).unwrap();
+            assert!(report.errors.is_empty(), "{:?}", report.errors);
+            assert_eq!(report.resolved.len(), 2);
+            assert!(report.resolved.iter().all(|r| r.trash_name.is_some()));
+            assert!(tmp.path().join("b").exists());
+            assert!(!tmp.path().join("a").exists() && !tmp.path().join("c").exists());
+            assert_eq!(trash::list_trash().unwrap().len(), 2);
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hardlink_makes_every_copy_the_kept_file() {
+        use std::os::unix::fs::MetadataExt;
+        let (tmp, group) = three_copies();
+        let report = resolve_group(&group, 0, Resolution::Hardlink).unwrap();
+        assert_eq!(report.resolved.len(), 2);
+        let inode = |name| fs::metadata(tmp.path().join(name)).unwrap().ino();
+        assert_eq!(inode("a"), inode("b"));
+        assert_eq!(inode("a"), inode("c"));
+        assert_eq!(fs::read_to_string(tmp.path().join("c")).unwrap(), "copy");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_points_every_copy_at_the_kept_file() {
+        let (tmp, group) = three_copies();
+        let report = resolve_group(&group, 2, Resolution::Symlink).unwrap();
+        assert_eq!(report.resolved.len(), 2);
+        let kept = std::path::absolute(tmp.path().join("c")).unwrap();
+        for name in ["a", "b"] {
+            assert_eq!(fs::read_link(tmp.path().join(name)).unwrap(), kept);
+        }
+        assert!(!tmp.path().join("c").is_symlink());
+    }
+
+    #[test]
+    fn changed_files_are_left_alone() {
+        let (tmp, group) = three_copies();
+        // The kept file changed: nothing may be replaced by it.
+        fs::write(tmp.path().join("a"), "edit").unwrap();
+        let e = resolve_group(&group, 0, Resolution::Hardlink).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        for (name, text) in [("a", "edit"), ("b", "copy"), ("c", "copy")] {
+            assert_eq!(fs::read_to_string(tmp.path().join(name)).unwrap(), text);
+        }
+
+        // Another copy changed: only that one is skipped.
+        let report = resolve_group(&group, 1, Resolution::Hardlink).unwrap();
+        assert_eq!(report.resolved.len(), 1);
+        assert_eq!(report.resolved[0].path, tmp.path().join("c"));
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, tmp.path().join("a"));
+        assert_eq!(fs::read_to_string(tmp.path().join("a")).unwrap(), "edit");
+    }
+}
@@ -11,29 +11,73 @@ pub fn list_files() {
 
     match fs::read_dir(".") {
         Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let metadata = match entry.metadata() {
-                        Ok(m) => m,
-                        Err(e) => {
-                            println!("Could not read metadata: {}", e);
-                            continue;
-                        }
-                    };
+            for entry in entries.flatten() {
+                let metadata = match entry.metadata() {
+                    Ok(m) => m,
+                    Err(e) => {
+                        println!("Could not read metadata: {}", e);
+                        continue;
+                    }
+                };
 
-                    let file_name = entry.file_name();
-                    let file_name_str = file_name.to_string_lossy();
+                let file_name = entry.file_name();
+                let file_name_str = file_name.to_string_lossy();
 
-                    // Hidden detection
-                    let mut is_hidden = file_name_str.starts_with('.'); // dotfiles
-                    #[cfg(target_os = "windows")]
-                    {
-                        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
-                        if metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0 {
-                            is_hidden = true;
-                        }
+                // Hidden detection
+                #[cfg_attr(not(target_os = "windows"), allow(unused_mut))]
+                let mut is_hidden = file_name_str.starts_with('.'); // dotfiles
+                #[cfg(target_os = "windows")]
+                {
+                    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+                    if metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0 {
+                        is_hidden = true;
                     }
+                }
+
+                let file_type = if metadata.is_dir() {
+                    "Directory"
+                } else {
+                    "File"
+                };
+                let size = metadata.len();
+                let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+                let modified_str = DateTime::<Local>::from(modified)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string();
+                let readonly = metadata.permissions().readonly();
+
+                if is_hidden {
+                    println!(
+                        "{} (hidden) | {} | {} bytes | modified {} | readonly: {}",
+                        file_name_str, file_type, size, modified_str, readonly
+                    );
+                } else {
+                    println!(
+                        "{} | {} | {} bytes | modified {} | readonly: {}",
+                        file_name_str, file_type, size, modified_str, readonly
+                    );
+                }
+            }
+        }
+        Err(e) => println!("Error reading directory: {}", e),
+    }
+}
 
+pub fn search_files() {
+    print!("Enter search term: ");
+    io::stdout().flush().unwrap();
+    let mut term = String::new();
+    io::stdin().read_line(&mut term).unwrap();
+    let term = term.trim().to_lowercase();
+
+    match fs::read_dir(".") {
+        Ok(entries) => {
+            for entry in entries.flatten() {
+                let file_name = entry.file_name();
+                let file_name_str = file_name.to_string_lossy();
+
+                if file_name_str.to_lowercase().contains(&term) {
+                    let metadata = entry.metadata().unwrap();
                     let file_type = if metadata.is_dir() {
                         "Directory"
                     } else {
@@ -46,6 +90,17 @@ pub fn list_files() {
                         .to_string();
                     let readonly = metadata.permissions().readonly();
 
+                    // Hidden detection
+                    #[cfg_attr(not(target_os = "windows"), allow(unused_mut))]
+                    let mut is_hidden = file_name_str.starts_with('.');
+                    #[cfg(target_os = "windows")]
+                    {
+                        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+                        if metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0 {
+                            is_hidden = true;
+                        }
+                    }
+
                     if is_hidden {
                         println!(
                             "{} (hidden) | {} | {} bytes | modified {} | readonly: {}",
@@ -60,63 +115,6 @@ pub fn list_files() {
                 }
             }
         }
-        Err(e) => println!("Error reading directory: {}", e),
-    }
-}
-
-pub fn search_files() {
-    print!("Enter search term: ");
-    io::stdout().flush().unwrap();
-    let mut term = String::new();
-    io::stdin().read_line(&mut term).unwrap();
-    let term = term.trim().to_lowercase();
-
-    match fs::read_dir(".") {
-        Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let file_name = entry.file_name();
-                    let file_name_str = file_name.to_string_lossy();
-
-                    if file_name_str.to_lowercase().contains(&term) {
-                        let metadata = entry.metadata().unwrap();
-                        let file_type = if metadata.is_dir() {
-                            "Directory"
-                        } else {
-                            "File"
-                        };
-                        let size = metadata.len();
-                        let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
-                        let modified_str = DateTime::<Local>::from(modified)
-                            .format("%Y-%m-%d %H:%M:%S")
-                            .to_string();
-                        let readonly = metadata.permissions().readonly();
-
-                        // Hidden detection
-                        let mut is_hidden = file_name_str.starts_with('.');
-                        #[cfg(target_os = "windows")]
-                        {
-                            const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
-                            if metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0 {
-                                is_hidden = true;
-                            }
-                        }
-
-                        if is_hidden {
-                            println!(
-                                "{} (hidden) | {} | {} bytes | modified {} | readonly: {}",
-                                file_name_str, file_type, size, modified_str, readonly
-                            );
-                        } else {
-                            println!(
-                                "{} | {} | {} bytes | modified {} | readonly: {}",
-                                file_name_str, file_type, size, modified_str, readonly
-                            );
-                        }
-                    }
-                }
-            }
-        }
         Err(e) => println!("Error searching directory: {}", e),
     }
 }
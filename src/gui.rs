@@ -1,6 +1,20 @@
+use crate::archive::{
+    ArchiveEntry, ArchiveFormat, BackendChoice, ChangeCheck, CompressionMethod, CreateOptions,
+    ExtractOptions, PathSafety, UpdateOptions, ZipEncryption,
+};
+use crate::compress::{self, Codec, CompressOptions};
+use crate::copy::{ConflictPolicy, CopyOptions, CopyReport};
+use crate::dupes::{DuplicateGroup, Resolution};
+use crate::hash::HashAlgo;
+use crate::journal::{Journal, Operation};
+use crate::navigation;
+use crate::shred::{Scheme, ShredOptions};
+use crate::split::SplitMode;
+use crate::trash::TrashItem;
 use eframe::egui;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{
     Arc,
     atomic::{AtomicBool, Ordering},
@@ -30,130 +44,1949 @@ struct FileEntry {
     hidden: bool,
 }
 
-struct FileManagerApp {
-    files: Vec<FileEntry>,
-    current_dir: String,
-    selected: Option<FileEntry>,
-    rename_input: String,
-    new_name_input: String,
-    move_input: String,
-    copy_input: String,
-    search_input: String,
-    batch_input: String,
-    // top-level actions inputs
-    change_dir_input: String,
-    batch_copy_input: String,
-    batch_copy_dest_input: String,
-    batch_rename_input: String,
-    // advanced action inputs
-    archive_input: String,
-    archive_dest_input: String,
-    archive_sources_input: String,
-    archive_output_input: String,
-    archive_format_input: String,
-    archive_password_input: String,
-    hash_input: String,
-    hash_algo_input: String,
-    duplicates_dir_input: String,
-    secure_delete_input: String,
-    split_input: String,
-    split_chunk_input: String,
-    join_base_input: String,
-    join_output_input: String,
-    // confirmation dialogs state
-    confirm_delete_open: bool,
-    confirm_delete_target: String,
-    confirm_delete_is_dir: bool,
-    confirm_batch_open: bool,
-    confirm_batch_targets: Vec<String>,
-    confirm_secure_open: bool,
-    confirm_secure_target: String,
-    // background worker state
-    worker_rx: Option<mpsc::Receiver<String>>,
-    worker_cancel: Option<Arc<AtomicBool>>,
-    progress_messages: Vec<String>,
-    is_busy: bool,
-    status: String,
-    status_is_error: bool,
-    sort_mode: SortMode,
-}
+struct FileManagerApp {
+    // left and right pane; with dual-pane off only the active one is shown
+    panes: [Pane; 2],
+    active_pane: usize,
+    dual_pane: bool,
+    rename_input: String,
+    new_name_input: String,
+    move_input: String,
+    copy_input: String,
+    search_input: String,
+    batch_input: String,
+    // top-level actions inputs
+    change_dir_input: String,
+    batch_copy_input: String,
+    batch_copy_dest_input: String,
+    batch_rename_input: String,
+    // glob for Select Matching
+    select_glob_input: String,
+    // advanced action inputs
+    archive_input: String,
+    archive_dest_input: String,
+    archive_sources_input: String,
+    archive_output_input: String,
+    archive_format_input: String,
+    archive_method: Option<CompressionMethod>,
+    archive_level_input: String,
+    archive_encryption: ZipEncryption,
+    // directory sources are taken from, and patterns to leave out
+    archive_base_input: String,
+    archive_exclude_input: String,
+    archive_password_input: String,
+    archive_backend: BackendChoice,
+    // selective extraction: entries/patterns, components to strip, conflicts
+    extract_patterns_input: String,
+    extract_strip: usize,
+    extract_policy: ConflictPolicy,
+    extract_rewrite_unsafe: bool,
+    // updating an existing archive: entries to delete, how changes are found
+    archive_delete_input: String,
+    archive_compare: ChangeCheck,
+    hash_input: String,
+    hash_algo_input: String,
+    duplicates_dir_input: String,
+    secure_delete_input: String,
+    split_input: String,
+    split_chunk_input: String,
+    split_by_count: bool,
+    join_base_input: String,
+    join_output_input: String,
+    // single-file compression from the file list; an empty level is the default
+    compress_codec: Codec,
+    compress_level_input: String,
+    compress_keep: bool,
+    // confirmation dialogs state
+    confirm_delete_open: bool,
+    confirm_delete_target: String,
+    confirm_delete_is_dir: bool,
+    confirm_batch_open: bool,
+    confirm_batch_targets: Vec<String>,
+    confirm_secure_open: bool,
+    confirm_secure_target: String,
+    shred_scheme: Scheme,
+    shred_verify: bool,
+    // background worker state
+    worker_rx: Option<mpsc::Receiver<String>>,
+    worker_cancel: Option<Arc<AtomicBool>>,
+    progress_messages: Vec<String>,
+    is_busy: bool,
+    status: String,
+    status_is_error: bool,
+    copy_policy: ConflictPolicy,
+    // trash panel state
+    trash_items: Vec<TrashItem>,
+    confirm_empty_trash_open: bool,
+    // duplicate finder results; `duplicate_keep[i]` indexes the file kept in group i
+    duplicate_groups: Vec<DuplicateGroup>,
+    duplicate_keep: Vec<usize>,
+    duplicates_rx: Option<mpsc::Receiver<Vec<DuplicateGroup>>>,
+    // last archive listing and the archive it came from
+    archive_entries: Vec<ArchiveEntry>,
+    archive_listed: String,
+    // entries yanked with Ctrl+C / Ctrl+X; the system clipboard, when there is
+    // one, also carries them as a text/uri-list for other file managers
+    clipboard: Option<ClipboardFiles>,
+    system_clipboard: Option<arboard::Clipboard>,
+    // undo/redo history
+    journal: Journal,
+    pending_op: Option<Operation>,
+}
+
+/// An archive opened from the file list and browsed like a directory.
+struct ArchiveView {
+    archive: String,
+    entries: Vec<ArchiveEntry>,
+    /// Directory inside the archive; empty at the root.
+    dir: String,
+    selected: Option<ArchiveEntry>,
+    preview: String,
+}
+
+/// Entries waiting to be pasted, and whether pasting moves them.
+#[derive(Clone)]
+struct ClipboardFiles {
+    paths: Vec<String>,
+    cut: bool,
+}
+
+/// Drag-and-drop payload: the path of an archive entry.
+struct ArchiveDrag(String);
+
+/// Bytes of an entry shown by Preview.
+const PREVIEW_BYTES: usize = 4096;
+
+#[derive(Clone, Copy)]
+enum SortMode {
+    Name,
+    Size,
+    Date,
+}
+
+/// One directory view with its own listing, selection, sort and history.
+struct Tab {
+    dir: String,
+    files: Vec<FileEntry>,
+    // paths of the selected entries; Shift-click ranges start at `anchor`
+    selected: HashSet<String>,
+    anchor: Option<String>,
+    sort_mode: SortMode,
+    // archive entered from the file list, browsed in place of a directory
+    archive_view: Option<ArchiveView>,
+    // directories left by navigating, most recent last
+    back: Vec<String>,
+    forward: Vec<String>,
+    // result of the last Compare, by file name
+    compare: HashMap<String, Difference>,
+}
+
+impl Tab {
+    fn new(dir: &str) -> Self {
+        let mut tab = Self {
+            dir: absolute_dir(dir),
+            files: Vec::new(),
+            selected: HashSet::new(),
+            anchor: None,
+            sort_mode: SortMode::Name,
+            archive_view: None,
+            back: Vec::new(),
+            forward: Vec::new(),
+            compare: HashMap::new(),
+        };
+        tab.refresh();
+        tab
+    }
+
+    /// Re-read the directory, keeping the tab's sort order and the selected
+    /// entries that are still there.
+    fn refresh(&mut self) {
+        self.files = read_files(&self.dir);
+        sort_files(&mut self.files, self.sort_mode);
+        self.compare.clear();
+        self.keep_listed();
+    }
+
+    fn keep_listed(&mut self) {
+        let listed: HashSet<&str> = self.files.iter().map(|f| f.path.as_str()).collect();
+        self.selected.retain(|p| listed.contains(p.as_str()));
+    }
+
+    /// Enter `dir`, remembering the current directory for Back.
+    fn navigate(&mut self, dir: &str) {
+        let dir = absolute_dir(dir);
+        if dir != self.dir {
+            self.back.push(std::mem::replace(&mut self.dir, dir));
+            self.forward.clear();
+        }
+        self.enter();
+    }
+
+    fn go_back(&mut self) {
+        if let Some(dir) = self.back.pop() {
+            self.forward.push(std::mem::replace(&mut self.dir, dir));
+            self.enter();
+        }
+    }
+
+    fn go_forward(&mut self) {
+        if let Some(dir) = self.forward.pop() {
+            self.back.push(std::mem::replace(&mut self.dir, dir));
+            self.enter();
+        }
+    }
+
+    /// Go to the parent directory. Inside an archive, go to the parent folder
+    /// in it, and leave the archive from its root.
+    fn go_up(&mut self) {
+        match &mut self.archive_view {
+            Some(view) if !view.dir.is_empty() => {
+                view.dir = view
+                    .dir
+                    .rsplit_once('/')
+                    .map(|(parent, _)| parent.to_string())
+                    .unwrap_or_default();
+                view.selected = None;
+            }
+            Some(_) => {
+                self.archive_view = None;
+                self.refresh();
+            }
+            None => {
+                if let Some(parent) = Path::new(&self.dir).parent() {
+                    let parent = parent.to_string_lossy().to_string();
+                    self.navigate(&parent);
+                }
+            }
+        }
+    }
+
+    fn enter(&mut self) {
+        self.clear_selection();
+        self.archive_view = None;
+        self.refresh();
+    }
+
+    /// Keep only the entries whose name contains `query` (lowercase).
+    fn filter(&mut self, query: &str) {
+        self.refresh();
+        self.files
+            .retain(|f| f.display.to_lowercase().contains(query));
+        self.keep_listed();
+    }
+
+    /// Selected entries in listing order.
+    fn selection(&self) -> Vec<FileEntry> {
+        self.files
+            .iter()
+            .filter(|f| self.selected.contains(&f.path))
+            .cloned()
+            .collect()
+    }
+
+    /// A plain click selects only entry `index`, Ctrl toggles it and Shift
+    /// selects the range from the anchor (adding to the selection with Ctrl).
+    fn click(&mut self, index: usize, modifiers: egui::Modifiers) {
+        let path = self.files[index].path.clone();
+        if modifiers.shift {
+            let anchor = self
+                .anchor
+                .as_ref()
+                .and_then(|a| self.files.iter().position(|f| &f.path == a))
+                .unwrap_or(index);
+            if !modifiers.command {
+                self.selected.clear();
+            }
+            let range = anchor.min(index)..=anchor.max(index);
+            self.selected
+                .extend(self.files[range].iter().map(|f| f.path.clone()));
+            return;
+        }
+        if !modifiers.command {
+            self.selected.clear();
+            self.selected.insert(path.clone());
+        } else if !self.selected.remove(&path) {
+            self.selected.insert(path.clone());
+        }
+        self.anchor = Some(path);
+    }
+
+    fn select_all(&mut self) {
+        self.selected = self.files.iter().map(|f| f.path.clone()).collect();
+    }
+
+    fn invert_selection(&mut self) {
+        self.selected = self
+            .files
+            .iter()
+            .filter(|f| !self.selected.contains(&f.path))
+            .map(|f| f.path.clone())
+            .collect();
+    }
+
+    /// Add the entries whose name matches `pattern`; returns how many match.
+    fn select_glob(&mut self, pattern: &str) -> usize {
+        let matching: Vec<String> = self
+            .files
+            .iter()
+            .filter(|f| crate::archive::glob_match(pattern, &f.display))
+            .map(|f| f.path.clone())
+            .collect();
+        let count = matching.len();
+        self.selected.extend(matching);
+        count
+    }
+
+    fn clear_selection(&mut self) {
+        self.selected.clear();
+        self.anchor = None;
+    }
+
+    fn set_sort(&mut self, mode: SortMode) {
+        self.sort_mode = mode;
+        sort_files(&mut self.files, mode);
+    }
+
+    /// Short name for the tab strip: the last path component.
+    fn title(&self) -> String {
+        match &self.archive_view {
+            Some(view) => Path::new(&view.archive)
+                .file_name()
+                .map_or(view.archive.clone(), |n| n.to_string_lossy().to_string()),
+            None => Path::new(&self.dir)
+                .file_name()
+                .map_or(self.dir.clone(), |n| n.to_string_lossy().to_string()),
+        }
+    }
+}
+
+/// One side of the dual-pane layout: browser-style tabs, one of them shown.
+struct Pane {
+    tabs: Vec<Tab>,
+    active: usize,
+}
+
+impl Pane {
+    fn new(dir: &str) -> Self {
+        Self {
+            tabs: vec![Tab::new(dir)],
+            active: 0,
+        }
+    }
+
+    fn tab(&self) -> &Tab {
+        &self.tabs[self.active]
+    }
+
+    fn tab_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.active]
+    }
+}
+
+/// How an entry differs from the one of the same name in the other pane.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Difference {
+    Missing,
+    Newer,
+    Older,
+    Size,
+}
+
+impl Difference {
+    fn label(self) -> &'static str {
+        match self {
+            Difference::Missing => "only here",
+            Difference::Newer => "newer",
+            Difference::Older => "older",
+            Difference::Size => "size differs",
+        }
+    }
+}
+
+impl Default for FileManagerApp {
+    fn default() -> Self {
+        Self {
+            panes: [Pane::new("."), Pane::new(".")],
+            active_pane: 0,
+            dual_pane: true,
+            rename_input: String::new(),
+            new_name_input: String::new(),
+            move_input: String::new(),
+            copy_input: String::new(),
+            search_input: String::new(),
+            batch_input: String::new(),
+            change_dir_input: String::new(),
+            batch_copy_input: String::new(),
+            batch_copy_dest_input: String::new(),
+            batch_rename_input: String::new(),
+            select_glob_input: String::new(),
+            archive_input: String::new(),
+            archive_dest_input: String::new(),
+            archive_sources_input: String::new(),
+            archive_output_input: String::new(),
+            archive_format_input: String::new(),
+            archive_method: None,
+            archive_level_input: String::new(),
+            archive_encryption: ZipEncryption::default(),
+            archive_base_input: String::new(),
+            archive_exclude_input: String::new(),
+            archive_password_input: String::new(),
+            archive_backend: BackendChoice::Auto,
+            extract_patterns_input: String::new(),
+            extract_strip: 0,
+            extract_policy: ConflictPolicy::Overwrite,
+            extract_rewrite_unsafe: false,
+            archive_delete_input: String::new(),
+            archive_compare: ChangeCheck::default(),
+            hash_input: String::new(),
+            hash_algo_input: String::from("sha256"),
+            duplicates_dir_input: String::new(),
+            secure_delete_input: String::new(),
+            split_input: String::new(),
+            split_chunk_input: String::from("100"),
+            split_by_count: false,
+            join_base_input: String::new(),
+            join_output_input: String::new(),
+            compress_codec: Codec::default(),
+            compress_level_input: String::new(),
+            compress_keep: true,
+            confirm_delete_open: false,
+            confirm_delete_target: String::new(),
+            confirm_delete_is_dir: false,
+            confirm_batch_open: false,
+            confirm_batch_targets: Vec::new(),
+            confirm_secure_open: false,
+            confirm_secure_target: String::new(),
+            shred_scheme: Scheme::Random,
+            shred_verify: false,
+            worker_rx: None,
+            worker_cancel: None,
+            progress_messages: Vec::new(),
+            is_busy: false,
+            status: String::new(),
+            status_is_error: false,
+            copy_policy: ConflictPolicy::Overwrite,
+            trash_items: Vec::new(),
+            confirm_empty_trash_open: false,
+            duplicate_groups: Vec::new(),
+            duplicate_keep: Vec::new(),
+            duplicates_rx: None,
+            archive_entries: Vec::new(),
+            archive_listed: String::new(),
+            clipboard: None,
+            system_clipboard: None,
+            journal: Journal::default(),
+            pending_op: None,
+        }
+    }
+}
+
+impl FileManagerApp {
+    fn tab(&self) -> &Tab {
+        self.panes[self.active_pane].tab()
+    }
+
+    fn tab_mut(&mut self) -> &mut Tab {
+        self.panes[self.active_pane].tab_mut()
+    }
+
+    /// Directory of the other pane, the default target of copy, move and compare.
+    fn other_dir(&self) -> Option<String> {
+        let other = &self.panes[1 - self.active_pane];
+        (self.dual_pane && other.tab().archive_view.is_none()).then(|| other.tab().dir.clone())
+    }
+
+    /// `input`, or the other pane's directory when it is left empty.
+    fn target_or_other(&mut self, input: &str) -> Option<String> {
+        let input = input.trim();
+        if !input.is_empty() {
+            return Some(input.to_string());
+        }
+        let target = self.other_dir();
+        if target.is_none() {
+            self.status = "Enter a destination".to_string();
+            self.status_is_error = true;
+        }
+        target
+    }
+
+    /// Re-read the listings on show; an operation may touch either pane.
+    fn refresh(&mut self) {
+        for pane in &mut self.panes {
+            pane.tab_mut().refresh();
+        }
+    }
+
+    /// Go to `input` the way the CLI's `cd` does: `-` is the previous
+    /// directory, `..` the parent and `~` home.
+    fn change_directory(&mut self, input: &str) {
+        let tab = self.tab();
+        let previous = tab.back.last().map(PathBuf::from);
+        match navigation::resolve(input, Path::new(&tab.dir), previous.as_deref()) {
+            Ok(dir) if dir.is_dir() => {
+                self.tab_mut().navigate(&dir.to_string_lossy());
+                self.status = format!("Changed dir to {}", self.tab().dir);
+                self.status_is_error = false;
+            }
+            Ok(dir) => {
+                self.status = format!("{} is not a directory", dir.display());
+                self.status_is_error = true;
+            }
+            Err(e) => {
+                self.status = format!("Cannot change directory: {}", e);
+                self.status_is_error = true;
+            }
+        }
+    }
+
+    /// Mark and select the entries that are missing or differ from the other
+    /// pane, comparing size and modification time.
+    fn compare_panes(&mut self) {
+        self.refresh();
+        let [left, right] = &mut self.panes;
+        let (left, right) = (left.tab_mut(), right.tab_mut());
+        left.compare = compare_listings(&left.files, &right.files);
+        right.compare = compare_listings(&right.files, &left.files);
+        // select what differs, ready to be copied across
+        for tab in [&mut *left, &mut *right] {
+            tab.selected = tab
+                .files
+                .iter()
+                .filter(|f| tab.compare.contains_key(&f.display))
+                .map(|f| f.path.clone())
+                .collect();
+        }
+        if left.compare.is_empty() && right.compare.is_empty() {
+            self.status = format!("{} and {} have the same files", left.dir, right.dir);
+        } else {
+            self.status = format!(
+                "{} entries differ in {}, {} in {}",
+                left.compare.len(),
+                left.dir,
+                right.compare.len(),
+                right.dir
+            );
+        }
+        self.status_is_error = false;
+    }
+
+    /// Copy `sources` to `dst` with the selected conflict policy and report the
+    /// result. Several sources need an existing directory to copy into.
+    fn run_copy(&mut self, sources: &[String], dst: &str) {
+        if sources.len() > 1 && !Path::new(dst).is_dir() {
+            self.status = format!("{} is not a directory", dst);
+            self.status_is_error = true;
+            return;
+        }
+        let options = CopyOptions {
+            policy: self.copy_policy,
+            ..CopyOptions::default()
+        };
+        self.progress_messages.clear();
+        self.status_is_error = false;
+        let mut ops = Vec::new();
+        for src in sources {
+            match crate::actions::copy_file_noninteractive(src, dst, &options) {
+                Ok(report) => {
+                    self.record_copy_report(&report);
+                    ops.push(copy_operation(src, &report));
+                    self.status = format!("Copied {} -> {} ({})", src, dst, report.summary());
+                    self.status_is_error |= !report.errors.is_empty();
+                }
+                Err(e) => {
+                    self.status = format!("Error copying {}: {}", src, e);
+                    self.status_is_error = true;
+                }
+            }
+        }
+        if sources.len() > 1 && !self.status_is_error {
+            self.status = format!("Copied {} items -> {}", sources.len(), dst);
+        }
+        match ops.len() {
+            0 => {}
+            1 => self.journal.record(ops.remove(0)),
+            _ => self.journal.record(Operation::Batch {
+                label: "copy".to_string(),
+                ops,
+            }),
+        }
+        self.refresh();
+    }
+
+    /// Run `job` on a background thread, wiring its messages and cancel flag
+    /// into the progress panel.
+    fn spawn_worker<F>(&mut self, job: F)
+    where
+        F: FnOnce(mpsc::Sender<String>, Arc<AtomicBool>) + Send + 'static,
+    {
+        if self.is_busy {
+            self.status = "Already running an operation".to_string();
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancel_clone = cancel.clone();
+        std::thread::spawn(move || job(tx, cancel_clone));
+        self.worker_rx = Some(rx);
+        self.worker_cancel = Some(cancel);
+        self.progress_messages.clear();
+        self.is_busy = true;
+    }
+
+    /// Move in the background, since cross-filesystem moves copy the whole tree.
+    /// Several sources are moved into the directory `dst`.
+    fn start_move(&mut self, sources: &[String], dst: &str) {
+        if self.is_busy {
+            self.status = "Already running an operation".to_string();
+            return;
+        }
+        let dst = dst.to_string();
+        if let [src] = sources {
+            self.pending_op = Some(Operation::Move {
+                from: PathBuf::from(src),
+                to: crate::actions::move_target(src, &dst),
+            });
+            let src = src.clone();
+            self.spawn_worker(move |tx, cancel| {
+                crate::actions::move_file_progress(&src, &dst, tx, cancel)
+            });
+        } else {
+            self.pending_op = Some(Operation::Batch {
+                label: "move".to_string(),
+                ops: sources
+                    .iter()
+                    .map(|src| Operation::Move {
+                        from: PathBuf::from(src),
+                        to: crate::actions::move_target(src, &dst),
+                    })
+                    .collect(),
+            });
+            let sources = sources.to_vec();
+            self.spawn_worker(move |tx, cancel| {
+                crate::actions::batch_move_progress(&sources, &dst, tx, cancel)
+            });
+        }
+        self.tab_mut().clear_selection();
+    }
+
+    /// Hash each of `paths` in turn with the algorithm from the hash row.
+    fn start_hash_files(&mut self, paths: Vec<String>) {
+        let algo = self.hash_algo_input.clone();
+        self.spawn_worker(move |tx, cancel| {
+            for path in &paths {
+                if cancel.load(Ordering::SeqCst) {
+                    let _ = tx.send("Canceled".to_string());
+                    return;
+                }
+                crate::actions::calculate_hash_progress(path, &algo, tx.clone(), cancel.clone());
+            }
+        });
+    }
+
+    /// Archive `sources` into `output` in the background.
+    fn start_archive_create(
+        &mut self,
+        sources: Vec<String>,
+        output: String,
+        options: CreateOptions,
+    ) {
+        let password = self.archive_password();
+        let backend = self.archive_backend;
+        self.spawn_worker(move |tx, cancel| {
+            crate::actions::archive_create_progress(
+                &sources,
+                &output,
+                password.as_deref(),
+                options,
+                backend,
+                tx,
+                cancel,
+            )
+        });
+    }
+
+    /// Pack the selection into the archive named in the create row, or into
+    /// `<name>.zip` next to it, storing names relative to the directory.
+    fn archive_selection(&mut self) {
+        if self.is_busy {
+            self.status = "Already running an operation".to_string();
+            return;
+        }
+        let Some(mut options) = self.create_options() else {
+            return;
+        };
+        let tab = self.tab();
+        let selection = tab.selection();
+        let dir = tab.dir.clone();
+        let output = match self.archive_output_input.trim() {
+            "" => {
+                let stem = match selection.as_slice() {
+                    [one] => one.display.clone(),
+                    _ => tab.title(),
+                };
+                Path::new(&dir)
+                    .join(format!("{}.zip", stem))
+                    .to_string_lossy()
+                    .to_string()
+            }
+            output => output.to_string(),
+        };
+        let sources = selection.into_iter().map(|f| f.display).collect();
+        options.base_dir.get_or_insert_with(|| PathBuf::from(&dir));
+        self.start_archive_create(sources, output, options);
+    }
+
+    /// Copy the selection to `copy_input`, or to the other pane.
+    fn copy_selection(&mut self) {
+        let sources: Vec<String> = self.tab().selection().into_iter().map(|f| f.path).collect();
+        if sources.is_empty() {
+            self.status = "No file selected".to_string();
+            return;
+        }
+        let input = self.copy_input.clone();
+        if let Some(target) = self.target_or_other(&input) {
+            self.run_copy(&sources, &target);
+        }
+    }
+
+    /// Move the selection to `move_input`, or to the other pane.
+    fn move_selection(&mut self) {
+        let sources: Vec<String> = self.tab().selection().into_iter().map(|f| f.path).collect();
+        if sources.is_empty() {
+            self.status = "No file selected".to_string();
+            return;
+        }
+        let input = self.move_input.clone();
+        if let Some(target) = self.target_or_other(&input) {
+            self.start_move(&sources, &target);
+        }
+    }
+
+    /// The system clipboard, opened on first use; `None` outside a desktop session.
+    fn system_clipboard(&mut self) -> Option<&mut arboard::Clipboard> {
+        if self.system_clipboard.is_none() {
+            self.system_clipboard = arboard::Clipboard::new().ok();
+        }
+        self.system_clipboard.as_mut()
+    }
+
+    /// Put the selection on the clipboard for Paste to copy, or with `cut` to move.
+    fn yank_selection(&mut self, cut: bool) {
+        let paths: Vec<String> = self.tab().selection().into_iter().map(|f| f.path).collect();
+        if paths.is_empty() {
+            self.status = "No file selected".to_string();
+            return;
+        }
+        let shared = self
+            .system_clipboard()
+            .is_some_and(|c| c.set().file_list(&paths).is_ok());
+        self.status = format!(
+            "{} {} items{}",
+            if cut { "Cut" } else { "Copied" },
+            paths.len(),
+            if shared {
+                ""
+            } else {
+                " (system clipboard unavailable)"
+            }
+        );
+        self.status_is_error = false;
+        self.clipboard = Some(ClipboardFiles { paths, cut });
+    }
+
+    /// Entries to paste: our own, unless another application has put a
+    /// different file list on the system clipboard since.
+    fn clipboard_files(&mut self) -> Option<ClipboardFiles> {
+        let system = self
+            .system_clipboard()
+            .and_then(|c| c.get().file_list().ok())
+            .filter(|list| !list.is_empty());
+        match (system, &self.clipboard) {
+            (Some(list), Some(own))
+                if own
+                    .paths
+                    .iter()
+                    .filter_map(|p| Path::new(p).canonicalize().ok())
+                    .eq(list.iter().cloned()) =>
+            {
+                Some(own.clone())
+            }
+            (Some(list), _) => Some(ClipboardFiles {
+                paths: list
+                    .iter()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .collect(),
+                cut: false,
+            }),
+            (None, own) => own.clone(),
+        }
+    }
+
+    /// Paste the clipboard into the active directory in the background.
+    fn paste(&mut self) {
+        if self.is_busy {
+            self.status = "Already running an operation".to_string();
+            return;
+        }
+        if self.tab().archive_view.is_some() {
+            self.status = "Leave the archive to paste into a directory".to_string();
+            self.status_is_error = true;
+            return;
+        }
+        let Some(files) = self.clipboard_files() else {
+            self.status = "Nothing to paste".to_string();
+            self.status_is_error = true;
+            return;
+        };
+        let dir = self.tab().dir.clone();
+        let ops = files
+            .paths
+            .iter()
+            .map(|src| {
+                let target = crate::actions::paste_target(src, &dir);
+                if files.cut {
+                    Operation::Move {
+                        from: PathBuf::from(src),
+                        to: crate::actions::move_target(src, &dir),
+                    }
+                } else if target.exists() {
+                    Operation::Irreversible {
+                        description: format!("paste over {}", target.display()),
+                    }
+                } else {
+                    Operation::Copy {
+                        src: PathBuf::from(src),
+                        created: target,
+                    }
+                }
+            })
+            .collect();
+        self.pending_op = Some(Operation::Batch {
+            label: "paste".to_string(),
+            ops,
+        });
+        // cut entries are gone once moved
+        if files.cut {
+            self.clipboard = None;
+            if let Some(clipboard) = self.system_clipboard() {
+                let _ = clipboard.clear();
+            }
+        }
+        let options = CopyOptions {
+            policy: self.copy_policy,
+            ..CopyOptions::default()
+        };
+        self.spawn_worker(move |tx, cancel| {
+            crate::actions::paste_progress(&files.paths, &dir, files.cut, options, tx, cancel)
+        });
+    }
+
+    /// Open the delete confirmation for the selection.
+    fn delete_selection(&mut self) {
+        match self.tab().selection().as_slice() {
+            [] => self.status = "No file selected".to_string(),
+            [one] => {
+                self.confirm_delete_open = true;
+                self.confirm_delete_target = one.path.clone();
+                self.confirm_delete_is_dir = one.is_dir;
+            }
+            many => {
+                self.confirm_batch_targets = many.iter().map(|f| f.path.clone()).collect();
+                self.confirm_batch_open = true;
+            }
+        }
+    }
+
+    /// Rename the selection: one entry to `rename_input`, several by using it as
+    /// a pattern where `{name}`, `{ext}` and `{n}` are the old stem, the old
+    /// extension with its dot and a counter from 1.
+    fn rename_selection(&mut self) {
+        let selection = self.tab().selection();
+        if selection.is_empty() {
+            self.status = "No file selected".to_string();
+            return;
+        }
+        let dir = self.tab().dir.clone();
+        let pairs: Vec<(String, String)> = selection
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                let name = if selection.len() == 1 {
+                    self.rename_input.clone()
+                } else {
+                    rename_pattern(&self.rename_input, &f.display, i + 1)
+                };
+                let to = Path::new(&dir).join(name).to_string_lossy().to_string();
+                (f.path.clone(), to)
+            })
+            .collect();
+        let results = crate::actions::batch_rename_noninteractive(&pairs);
+        let mut ops = Vec::new();
+        self.status_is_error = false;
+        for ((from, to), res) in pairs.iter().zip(results) {
+            match res {
+                Ok(_) => {
+                    ops.push(Operation::Rename {
+                        from: PathBuf::from(from),
+                        to: PathBuf::from(to),
+                    });
+                    self.status = format!("Renamed {} -> {}", from, to);
+                }
+                Err(e) => {
+                    self.status = format!("Error renaming {}: {}", from, e);
+                    self.status_is_error = true;
+                }
+            }
+        }
+        if ops.len() > 1 && !self.status_is_error {
+            self.status = format!("Renamed {} items", ops.len());
+        }
+        match ops.len() {
+            0 => {}
+            1 => self.journal.record(ops.remove(0)),
+            _ => self.journal.record(Operation::Batch {
+                label: "rename".to_string(),
+                ops,
+            }),
+        }
+        self.refresh();
+        self.tab_mut().clear_selection();
+    }
+
+    fn start_hash(&mut self) {
+        let (path, algo) = (self.hash_input.clone(), self.hash_algo_input.clone());
+        self.spawn_worker(move |tx, cancel| {
+            crate::actions::calculate_hash_progress(&path, &algo, tx, cancel)
+        });
+    }
+
+    fn start_verify(&mut self) {
+        let sum_file = self.hash_input.clone();
+        // an empty algorithm field means "detect from the checksum file"
+        let algo = Some(self.hash_algo_input.trim().to_string()).filter(|a| !a.is_empty());
+        self.spawn_worker(move |tx, cancel| {
+            crate::actions::verify_checksums_progress(&sum_file, algo.as_deref(), tx, cancel)
+        });
+    }
+
+    fn start_archive_test(&mut self) {
+        let path = self.archive_input.clone();
+        let password = self.archive_password();
+        let backend = self.archive_backend;
+        self.spawn_worker(move |tx, cancel| {
+            crate::actions::archive_test_progress(&path, password.as_deref(), backend, tx, cancel)
+        });
+    }
+
+    fn start_archive_update(&mut self) {
+        if self.is_busy {
+            self.status = "Already running an operation".to_string();
+            return;
+        }
+        let Some(create) = self.create_options() else {
+            return;
+        };
+        let split = |input: &str| -> Vec<String> {
+            input
+                .split(',')
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .collect()
+        };
+        let sources = split(&self.archive_sources_input);
+        let options = UpdateOptions {
+            compare: self.archive_compare,
+            delete: split(&self.archive_delete_input),
+            create,
+        };
+        let archive = self.archive_output_input.clone();
+        let password = self.archive_password();
+        let backend = self.archive_backend;
+        self.pending_op = Some(Operation::Irreversible {
+            description: format!("update archive {}", archive),
+        });
+        self.spawn_worker(move |tx, cancel| {
+            crate::actions::archive_update_progress(
+                &archive,
+                &sources,
+                password.as_deref(),
+                options,
+                backend,
+                tx,
+                cancel,
+            )
+        });
+    }
+
+    fn start_split(&mut self) {
+        let n = match self.split_chunk_input.trim().parse::<u64>() {
+            Ok(n) if n > 0 => n,
+            _ => {
+                self.status = format!("Invalid number '{}'", self.split_chunk_input);
+                self.status_is_error = true;
+                return;
+            }
+        };
+        let mode = if self.split_by_count {
+            SplitMode::Parts(n)
+        } else {
+            SplitMode::Size(n * 1024 * 1024)
+        };
+        let path = self.split_input.clone();
+        self.spawn_worker(move |tx, cancel| {
+            crate::actions::split_file_progress(&path, mode, tx, cancel)
+        });
+    }
+
+    /// Compress `path` with the chosen level, keeping or removing the original.
+    fn start_compress(&mut self, path: &str, codec: Codec) {
+        if self.is_busy {
+            self.status = "Already running an operation".to_string();
+            return;
+        }
+        let level = match self.compress_level_input.trim() {
+            "" => None,
+            n => match n.parse() {
+                Ok(level) => Some(level),
+                Err(_) => {
+                    self.status = format!("Invalid compression level '{}'", n);
+                    self.status_is_error = true;
+                    return;
+                }
+            },
+        };
+        let options = CompressOptions {
+            codec,
+            level,
+            keep_original: self.compress_keep,
+        };
+        // with the original kept, removing the output undoes it
+        self.pending_op = Some(if options.keep_original {
+            Operation::CreateFile {
+                path: compress::compressed_path(Path::new(path), codec),
+            }
+        } else {
+            Operation::Irreversible {
+                description: format!("compress {}", path),
+            }
+        });
+        let path = path.to_string();
+        self.spawn_worker(move |tx, cancel| {
+            crate::actions::compress_file_progress(&path, options, tx, cancel)
+        });
+        self.tab_mut().clear_selection();
+    }
+
+    fn start_decompress(&mut self, path: &str) {
+        if self.is_busy {
+            self.status = "Already running an operation".to_string();
+            return;
+        }
+        let Some((_, output)) = compress::decompressed_path(Path::new(path)) else {
+            self.status = format!("{} is not a .gz, .bz2, .xz or .zst file", path);
+            self.status_is_error = true;
+            return;
+        };
+        let keep = self.compress_keep;
+        self.pending_op = Some(if keep {
+            Operation::CreateFile { path: output }
+        } else {
+            Operation::Irreversible {
+                description: format!("decompress {}", path),
+            }
+        });
+        let path = path.to_string();
+        self.spawn_worker(move |tx, cancel| {
+            crate::actions::decompress_file_progress(&path, keep, tx, cancel)
+        });
+        self.tab_mut().clear_selection();
+    }
+
+    fn start_join(&mut self) {
+        let (base, output) = (self.join_base_input.clone(), self.join_output_input.clone());
+        self.spawn_worker(move |tx, cancel| {
+            crate::actions::join_files_progress(&base, &output, tx, cancel)
+        });
+    }
+
+    /// Search in the background; the groups arrive on `duplicates_rx`.
+    fn start_find_duplicates(&mut self) {
+        if self.is_busy {
+            self.status = "Already running an operation".to_string();
+            return;
+        }
+        let (groups_tx, groups_rx) = mpsc::channel();
+        let dir = self.duplicates_dir_input.clone();
+        self.spawn_worker(move |tx, cancel| {
+            if let Some(groups) = crate::actions::find_duplicates_progress(&dir, tx, cancel) {
+                let _ = groups_tx.send(groups);
+            }
+        });
+        self.duplicate_groups.clear();
+        self.duplicate_keep.clear();
+        self.duplicates_rx = Some(groups_rx);
+    }
+
+    /// Keep the selected file of group `index` and apply `resolution` to the rest.
+    fn resolve_duplicates(&mut self, index: usize, resolution: Resolution) {
+        let group = &self.duplicate_groups[index];
+        let keep = self.duplicate_keep[index];
+        match crate::actions::resolve_duplicates_noninteractive(group, keep, resolution) {
+            Ok(report) => {
+                if resolution == Resolution::Delete {
+                    let ops = report
+                        .resolved
+                        .iter()
+                        .filter_map(|r| {
+                            Some(Operation::Trash {
+                                original: r.path.clone(),
+                                trash_name: r.trash_name.clone()?,
+                            })
+                        })
+                        .collect();
+                    self.journal.record(Operation::Batch {
+                        label: "delete duplicates".to_string(),
+                        ops,
+                    });
+                } else if !report.resolved.is_empty() {
+                    self.journal.record(Operation::Irreversible {
+                        description: format!(
+                            "{} {} duplicates",
+                            resolution.label().to_lowercase(),
+                            report.resolved.len()
+                        ),
+                    });
+                }
+                if let Some((path, e)) = report.errors.first() {
+                    self.status = format!(
+                        "{} of {} duplicates failed; {}: {}",
+                        report.errors.len(),
+                        report.errors.len() + report.resolved.len(),
+                        path.display(),
+                        e
+                    );
+                    self.status_is_error = true;
+                } else {
+                    self.status = format!(
+                        "{}: resolved {} duplicates, kept {}",
+                        resolution.label(),
+                        report.resolved.len(),
+                        group.files[keep].display()
+                    );
+                    self.status_is_error = false;
+                    self.duplicate_groups.remove(index);
+                    self.duplicate_keep.remove(index);
+                }
+            }
+            Err(e) => {
+                self.status = format!("Error resolving duplicates: {}", e);
+                self.status_is_error = true;
+            }
+        }
+        self.refresh();
+    }
+
+    fn undo(&mut self) {
+        if self.is_busy {
+            self.status = "Wait for the running operation to finish".to_string();
+            return;
+        }
+        match self.journal.undo() {
+            Ok(msg) => {
+                self.status = msg;
+                self.status_is_error = false;
+            }
+            Err(e) => {
+                self.status = e;
+                self.status_is_error = true;
+            }
+        }
+        self.refresh();
+        self.tab_mut().clear_selection();
+    }
+
+    fn redo(&mut self) {
+        if self.is_busy {
+            self.status = "Wait for the running operation to finish".to_string();
+            return;
+        }
+        match self.journal.redo() {
+            Ok(msg) => {
+                self.status = msg;
+                self.status_is_error = false;
+            }
+            Err(e) => {
+                self.status = e;
+                self.status_is_error = true;
+            }
+        }
+        self.refresh();
+        self.tab_mut().clear_selection();
+    }
+
+    fn list_archive(&mut self) {
+        match crate::actions::archive_list_noninteractive(
+            &self.archive_input,
+            self.archive_password().as_deref(),
+            self.archive_backend,
+        ) {
+            Ok(entries) => {
+                self.status = format!("{} entries in {}", entries.len(), self.archive_input);
+                self.status_is_error = false;
+                self.archive_entries = entries;
+                self.archive_listed = self.archive_input.clone();
+            }
+            Err(e) => {
+                self.status = e;
+                self.status_is_error = true;
+            }
+        }
+    }
+
+    fn open_archive_view(&mut self, path: &str) {
+        match crate::actions::archive_list_noninteractive(
+            path,
+            self.archive_password().as_deref(),
+            self.archive_backend,
+        ) {
+            Ok(entries) => {
+                self.status = format!("Opened archive {} ({} entries)", path, entries.len());
+                self.status_is_error = false;
+                self.tab_mut().clear_selection();
+                self.tab_mut().archive_view = Some(ArchiveView {
+                    archive: path.to_string(),
+                    entries,
+                    dir: String::new(),
+                    selected: None,
+                    preview: String::new(),
+                });
+            }
+            Err(e) => {
+                self.status = format!("Cannot open archive {}: {}", path, e);
+                self.status_is_error = true;
+            }
+        }
+    }
+
+    fn extract_options(&self) -> ExtractOptions {
+        ExtractOptions {
+            patterns: self
+                .extract_patterns_input
+                .split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect(),
+            strip_components: self.extract_strip,
+            policy: self.extract_policy,
+            safety: self.path_safety(),
+        }
+    }
+
+    /// Format and compression settings from the create row, or `None` after
+    /// reporting one that cannot be read.
+    fn create_options(&mut self) -> Option<CreateOptions> {
+        let format = self.archive_format_input.trim();
+        let format = if format.is_empty() {
+            None
+        } else {
+            match ArchiveFormat::parse(format) {
+                Some(f) => Some(f),
+                None => {
+                    self.status = format!("Unknown archive format '{}'", format);
+                    self.status_is_error = true;
+                    return None;
+                }
+            }
+        };
+        let level = self.archive_level_input.trim();
+        let level = if level.is_empty() {
+            None
+        } else {
+            match level.parse() {
+                Ok(n) => Some(n),
+                Err(_) => {
+                    self.status = format!("Invalid compression level '{}'", level);
+                    self.status_is_error = true;
+                    return None;
+                }
+            }
+        };
+        let base = self.archive_base_input.trim();
+        Some(CreateOptions {
+            format,
+            method: self.archive_method,
+            level,
+            encryption: self.archive_encryption,
+            base_dir: (!base.is_empty()).then(|| PathBuf::from(base)),
+            exclude: self
+                .archive_exclude_input
+                .split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect(),
+        })
+    }
+
+    fn path_safety(&self) -> PathSafety {
+        if self.extract_rewrite_unsafe {
+            PathSafety::Rewrite
+        } else {
+            PathSafety::Reject
+        }
+    }
+
+    /// Extract a directory of the open archive, and everything under it, into
+    /// the directory that holds the archive.
+    fn extract_view_dir(&mut self, dir: &str) {
+        let Some(view) = &self.tab().archive_view else {
+            return;
+        };
+        let options = ExtractOptions {
+            patterns: vec![dir.to_string()],
+            // keep the folder itself but drop the folders above it
+            strip_components: dir.matches('/').count(),
+            policy: self.extract_policy,
+            safety: self.path_safety(),
+        };
+        let (archive, dest) = (view.archive.clone(), self.tab().dir.clone());
+        let password = self.archive_password();
+        let backend = self.archive_backend;
+        self.spawn_worker(move |tx, cancel| {
+            crate::actions::archive_extract_progress(
+                &archive,
+                &dest,
+                password.as_deref(),
+                options,
+                backend,
+                tx,
+                cancel,
+            )
+        });
+    }
+
+    fn archive_password(&self) -> Option<String> {
+        Some(self.archive_password_input.trim().to_string()).filter(|p| !p.is_empty())
+    }
+
+    /// Extract one entry of the open archive into the directory that holds the archive.
+    fn extract_view_entry(&mut self, name: &str) {
+        let Some(view) = &self.tab().archive_view else {
+            return;
+        };
+        match crate::archive::extract_entry(
+            &view.archive,
+            name,
+            Path::new(&self.tab().dir),
+            self.archive_password().as_deref(),
+            self.archive_backend,
+        ) {
+            Ok(path) => {
+                self.status = format!("Extracted {} to {}", name, path.display());
+                self.status_is_error = false;
+                self.journal.record(Operation::CreateFile { path });
+                self.refresh();
+            }
+            Err(e) => {
+                self.status = format!("Error extracting {}: {}", name, e);
+                self.status_is_error = true;
+            }
+        }
+    }
+
+    fn preview_view_entry(&mut self, name: &str) {
+        let Some(view) = &self.tab().archive_view else {
+            return;
+        };
+        let result = crate::archive::preview_entry(
+            &view.archive,
+            name,
+            PREVIEW_BYTES,
+            self.archive_password().as_deref(),
+            self.archive_backend,
+        );
+        match result {
+            Ok(bytes) => {
+                let text = if bytes.contains(&0) {
+                    // Binary data: show a short hex dump instead.
+                    bytes
+                        .chunks(16)
+                        .take(16)
+                        .enumerate()
+                        .map(|(i, row)| {
+                            let hex: Vec<String> =
+                                row.iter().map(|b| format!("{:02x}", b)).collect();
+                            format!("{:08x}  {}", i * 16, hex.join(" "))
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                } else {
+                    String::from_utf8_lossy(&bytes).to_string()
+                };
+                if let Some(view) = &mut self.tab_mut().archive_view {
+                    view.preview = text;
+                }
+            }
+            Err(e) => {
+                self.status = format!("Error previewing {}: {}", name, e);
+                self.status_is_error = true;
+            }
+        }
+    }
+
+    fn hash_view_entry(&mut self, name: &str) {
+        let Some(view) = &self.tab().archive_view else {
+            return;
+        };
+        let Some(algo) = HashAlgo::parse(&self.hash_algo_input) else {
+            self.status = format!("Unsupported hash algorithm '{}'", self.hash_algo_input);
+            self.status_is_error = true;
+            return;
+        };
+        match crate::archive::hash_entry(
+            &view.archive,
+            name,
+            algo,
+            self.archive_password().as_deref(),
+            self.archive_backend,
+        ) {
+            Ok(digest) => {
+                self.status = format!("{}: {}\n File: {}", algo, digest, name);
+                self.status_is_error = false;
+            }
+            Err(e) => {
+                self.status = format!("Error hashing {}: {}", name, e);
+                self.status_is_error = true;
+            }
+        }
+    }
+
+    /// File list for the archive open in pane `index`: its entries at `view.dir`, a ".." row
+    /// to go up (or leave the archive), and a drop zone for extracting.
+    fn archive_view_ui(&mut self, ui: &mut egui::Ui, index: usize) {
+        enum Action {
+            Up,
+            Enter(String),
+            Select(ArchiveEntry),
+            Extract(String),
+            ExtractDir(String),
+            Preview(String),
+            Hash(String),
+        }
+        let tab = self.panes[index].tab();
+        let Some(view) = &tab.archive_view else {
+            return;
+        };
+        let listing = crate::archive::children(&view.entries, &view.dir);
+        let selected_path = view.selected.as_ref().map(|e| e.path.clone());
+        let mut action = None;
+
+        egui::Grid::new(("archive_view_grid", index))
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Name");
+                ui.label("Size");
+                ui.label("Modified");
+                ui.label("Type");
+                ui.end_row();
+
+                if ui.selectable_label(false, "..").double_clicked() {
+                    action = Some(Action::Up);
+                }
+                ui.label("");
+                ui.label("");
+                ui.label("Directory");
+                ui.end_row();
+
+                for entry in listing {
+                    let selected = selected_path.as_deref() == Some(entry.path.as_str());
+                    let label = if entry.encrypted {
+                        format!("{} (encrypted)", entry.name())
+                    } else {
+                        entry.name().to_string()
+                    };
+                    let id = egui::Id::new(("archive_entry", index, &entry.path));
+                    let response = if entry.is_dir {
+                        ui.selectable_label(selected, label)
+                    } else {
+                        ui.dnd_drag_source(id, ArchiveDrag(entry.path.clone()), |ui| {
+                            ui.selectable_label(selected, label)
+                        })
+                        .inner
+                    };
+                    ui.label(format!("{} bytes", entry.size));
+                    ui.label(entry.modified_string());
+                    ui.label(if entry.is_dir { "Directory" } else { "File" });
+                    ui.end_row();
+
+                    if response.double_clicked() && entry.is_dir {
+                        action = Some(Action::Enter(entry.path.clone()));
+                    } else if response.clicked() {
+                        action = Some(Action::Select(entry));
+                    }
+                }
+            });
+
+        ui.separator();
+        let (_, dropped) =
+            ui.dnd_drop_zone::<ArchiveDrag, _>(egui::Frame::default().inner_margin(8.0), |ui| {
+                ui.label(format!("Drag a file here to extract it into {}", tab.dir));
+            });
+        if let Some(drag) = dropped {
+            action = Some(Action::Extract(drag.0.clone()));
+        }
+
+        let selected = tab.archive_view.as_ref().and_then(|v| v.selected.clone());
+        if let Some(entry) = &selected
+            && entry.is_dir
+        {
+            ui.label(format!("Selected: {}/", entry.path));
+            if ui.button("Extract folder here").clicked() {
+                action = Some(Action::ExtractDir(entry.path.clone()));
+            }
+        }
+        if let Some(entry) = selected
+            && !entry.is_dir
+        {
+            ui.label(format!("Selected: {}", entry.path));
+            ui.horizontal(|ui| {
+                if ui.button("Preview").clicked() {
+                    action = Some(Action::Preview(entry.path.clone()));
+                }
+                if ui.button("Hash").clicked() {
+                    action = Some(Action::Hash(entry.path.clone()));
+                }
+                if ui.button("Extract here").clicked() {
+                    action = Some(Action::Extract(entry.path.clone()));
+                }
+            });
+        }
+        if let Some(view) = &tab.archive_view
+            && !view.preview.is_empty()
+        {
+            egui::ScrollArea::vertical()
+                .id_source(("archive_preview_scroll", index))
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    ui.monospace(&view.preview);
+                });
+        }
+
+        if action.is_some() {
+            self.active_pane = index;
+        }
+        match action {
+            Some(Action::Up) => {
+                self.tab_mut().go_up();
+                if self.tab().archive_view.is_none() {
+                    self.status = format!("Left archive, back in {}", self.tab().dir);
+                    self.status_is_error = false;
+                }
+            }
+            Some(Action::Enter(dir)) => {
+                if let Some(view) = &mut self.tab_mut().archive_view {
+                    view.dir = dir;
+                    view.selected = None;
+                    view.preview.clear();
+                }
+            }
+            Some(Action::Select(entry)) => {
+                if let Some(view) = &mut self.tab_mut().archive_view {
+                    view.selected = Some(entry);
+                    view.preview.clear();
+                }
+                self.status.clear();
+            }
+            Some(Action::Extract(name)) => self.extract_view_entry(&name),
+            Some(Action::ExtractDir(dir)) => self.extract_view_dir(&dir),
+            Some(Action::Preview(name)) => self.preview_view_entry(&name),
+            Some(Action::Hash(name)) => self.hash_view_entry(&name),
+            None => {}
+        }
+    }
+
+    /// Tab strip, history buttons and listing of pane `index`. Using a pane
+    /// makes it the active one, which the actions below the panes work on.
+    fn pane_ui(&mut self, ui: &mut egui::Ui, index: usize) {
+        enum Action {
+            SwitchTab(usize),
+            NewTab,
+            CloseTab(usize),
+            Back,
+            Forward,
+            Up,
+            ArchiveRoot,
+            Enter(String),
+            OpenArchive(String),
+            Click(usize, egui::Modifiers),
+            Compress(String, Codec),
+            Decompress(String),
+        }
+        let mut action = None;
+        let mut frame = egui::Frame::group(ui.style());
+        if self.dual_pane && index == self.active_pane {
+            frame = frame.stroke(ui.visuals().selection.stroke);
+        }
+        let response = frame.show(ui, |ui| {
+            let pane = &self.panes[index];
+            ui.horizontal_wrapped(|ui| {
+                for (i, tab) in pane.tabs.iter().enumerate() {
+                    if ui
+                        .selectable_label(i == pane.active, tab.title())
+                        .on_hover_text(&tab.dir)
+                        .clicked()
+                    {
+                        action = Some(Action::SwitchTab(i));
+                    }
+                    if pane.tabs.len() > 1
+                        && ui.small_button("x").on_hover_text("Close tab").clicked()
+                    {
+                        action = Some(Action::CloseTab(i));
+                    }
+                }
+                if ui.small_button("+").on_hover_text("New tab").clicked() {
+                    action = Some(Action::NewTab);
+                }
+            });
+            let tab = pane.tab();
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!tab.back.is_empty(), egui::Button::new("<"))
+                    .on_hover_text("Back")
+                    .clicked()
+                {
+                    action = Some(Action::Back);
+                }
+                if ui
+                    .add_enabled(!tab.forward.is_empty(), egui::Button::new(">"))
+                    .on_hover_text("Forward")
+                    .clicked()
+                {
+                    action = Some(Action::Forward);
+                }
+                let at_root = tab.archive_view.is_none() && Path::new(&tab.dir).parent().is_none();
+                if ui
+                    .add_enabled(!at_root, egui::Button::new("^"))
+                    .on_hover_text("Parent directory (Alt+Up)")
+                    .clicked()
+                {
+                    action = Some(Action::Up);
+                }
+                let home = navigation::home_dir();
+                if ui
+                    .add_enabled(home.is_some(), egui::Button::new("~"))
+                    .on_hover_text("Home directory")
+                    .clicked()
+                    && let Some(home) = home
+                {
+                    action = Some(Action::Enter(home.to_string_lossy().to_string()));
+                }
+                if ui.button("/").on_hover_text("Root directory").clicked()
+                    && let Some(root) = Path::new(&tab.dir).ancestors().last()
+                {
+                    action = Some(Action::Enter(root.to_string_lossy().to_string()));
+                }
+            });
+            // Breadcrumbs: every ancestor of the directory, then the archive
+            ui.horizontal_wrapped(|ui| {
+                ui.spacing_mut().item_spacing.x = 2.0;
+                let crumbs: Vec<&Path> = Path::new(&tab.dir).ancestors().collect();
+                for (i, crumb) in crumbs.iter().rev().enumerate() {
+                    let name = crumb
+                        .file_name()
+                        .map_or(crumb.to_string_lossy(), |n| n.to_string_lossy());
+                    if i > 1 {
+                        ui.label("/");
+                    }
+                    let current = i + 1 == crumbs.len() && tab.archive_view.is_none();
+                    if ui.selectable_label(current, name).clicked() {
+                        action = Some(Action::Enter(crumb.to_string_lossy().to_string()));
+                    }
+                }
+                if let Some(view) = &tab.archive_view {
+                    ui.label("/");
+                    let name = Path::new(&view.archive)
+                        .file_name()
+                        .map_or(view.archive.clone(), |n| n.to_string_lossy().to_string());
+                    if ui.selectable_label(view.dir.is_empty(), name).clicked() {
+                        action = Some(Action::ArchiveRoot);
+                    }
+                    if !view.dir.is_empty() {
+                        ui.label(format!("/ {}", view.dir));
+                    }
+                }
+            });
+            ui.separator();
+
+            if tab.archive_view.is_some() {
+                self.archive_view_ui(ui, index);
+                return;
+            }
+            // File list with metadata
+            let tab = self.panes[index].tab();
+            egui::Grid::new(("file_grid", index))
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Name");
+                    ui.label("Size");
+                    ui.label("Modified");
+                    ui.label("Type");
+                    ui.end_row();
+
+                    for (i, entry) in tab.files.iter().enumerate() {
+                        let selected = tab.selected.contains(&entry.path);
+                        let mut label = if entry.hidden {
+                            format!("{} (hidden)", entry.display)
+                        } else {
+                            entry.display.clone()
+                        };
+                        let difference = tab.compare.get(&entry.display);
+                        if let Some(difference) = difference {
+                            label = format!("{} ({})", label, difference.label());
+                        }
+                        let mut text = egui::RichText::new(label);
+                        if difference.is_some() {
+                            text = text.color(egui::Color32::YELLOW);
+                        }
+
+                        let response = ui.selectable_label(selected, text);
+                        ui.label(format!("{} bytes", entry.size));
+                        ui.label(&entry.modified);
+                        ui.label(if entry.is_dir { "Directory" } else { "File" });
+                        ui.end_row();
+
+                        // Right-click: compress or decompress the file in place
+                        if !entry.is_dir {
+                            let compressed =
+                                compress::decompressed_path(Path::new(&entry.path)).is_some();
+                            let keep = &mut self.compress_keep;
+                            response.context_menu(|ui| {
+                                if compressed && ui.button("Decompress here").clicked() {
+                                    action = Some(Action::Decompress(entry.path.clone()));
+                                    ui.close_menu();
+                                }
+                                for codec in Codec::ALL {
+                                    let label = format!("Compress to .{}", codec.extension());
+                                    if ui.button(label).clicked() {
+                                        action = Some(Action::Compress(entry.path.clone(), codec));
+                                        ui.close_menu();
+                                    }
+                                }
+                                ui.checkbox(keep, "Keep original");
+                            });
+                        }
+
+                        // Double-click navigation into directories and archives
+                        if response.double_clicked() && entry.is_dir {
+                            action = Some(Action::Enter(entry.path.clone()));
+                        } else if response.double_clicked()
+                            && crate::archive::is_archive(Path::new(&entry.path))
+                        {
+                            action = Some(Action::OpenArchive(entry.path.clone()));
+                        } else if response.clicked() {
+                            let modifiers = ui.input(|i| i.modifiers);
+                            action = Some(Action::Click(i, modifiers));
+                        }
+                    }
+                });
+        });
+        // a click anywhere in the pane makes it the active one
+        if ui.rect_contains_pointer(response.response.rect) && ui.input(|i| i.pointer.any_pressed())
+        {
+            self.active_pane = index;
+        }
+
+        let Some(action) = action else {
+            return;
+        };
+        self.active_pane = index;
+        let pane = &mut self.panes[index];
+        match action {
+            Action::SwitchTab(i) => pane.active = i,
+            Action::NewTab => {
+                let dir = pane.tab().dir.clone();
+                pane.tabs.push(Tab::new(&dir));
+                pane.active = pane.tabs.len() - 1;
+            }
+            Action::CloseTab(i) => {
+                pane.tabs.remove(i);
+                if pane.active > i || pane.active == pane.tabs.len() {
+                    pane.active -= 1;
+                }
+            }
+            Action::Back => pane.tab_mut().go_back(),
+            Action::Forward => pane.tab_mut().go_forward(),
+            Action::Up => pane.tab_mut().go_up(),
+            Action::ArchiveRoot => {
+                if let Some(view) = &mut pane.tab_mut().archive_view {
+                    view.dir.clear();
+                    view.selected = None;
+                }
+            }
+            Action::Enter(dir) => {
+                pane.tab_mut().navigate(&dir);
+                self.status = format!("Entered directory {}", self.tab().dir);
+                self.status_is_error = false;
+            }
+            Action::OpenArchive(path) => self.open_archive_view(&path),
+            Action::Click(i, modifiers) => {
+                let tab = pane.tab_mut();
+                tab.click(i, modifiers);
+                // a single entry is renamed to the name typed in; start from its own
+                if let [one] = tab.selection().as_slice() {
+                    self.rename_input = one.display.clone();
+                }
+                self.status.clear();
+            }
+            Action::Compress(path, codec) => self.start_compress(&path, codec),
+            Action::Decompress(path) => self.start_decompress(&path),
+        }
+    }
 
-#[derive(Clone, Copy)]
-enum SortMode {
-    Name,
-    Size,
-    Date,
-}
+    fn refresh_trash(&mut self) {
+        match crate::actions::trash_list_noninteractive() {
+            Ok(items) => self.trash_items = items,
+            Err(e) => {
+                self.status = format!("Error reading trash: {}", e);
+                self.status_is_error = true;
+            }
+        }
+    }
 
-impl Default for FileManagerApp {
-    fn default() -> Self {
-        let dir = ".".to_string();
-        let files = read_files(&dir);
-        Self {
-            files,
-            current_dir: dir,
-            selected: None,
-            rename_input: String::new(),
-            new_name_input: String::new(),
-            move_input: String::new(),
-            copy_input: String::new(),
-            search_input: String::new(),
-            batch_input: String::new(),
-            change_dir_input: String::new(),
-            batch_copy_input: String::new(),
-            batch_copy_dest_input: String::new(),
-            batch_rename_input: String::new(),
-            archive_input: String::new(),
-            archive_dest_input: String::new(),
-            archive_sources_input: String::new(),
-            archive_output_input: String::new(),
-            archive_format_input: String::new(),
-            archive_password_input: String::new(),
-            hash_input: String::new(),
-            hash_algo_input: String::from("sha256"),
-            duplicates_dir_input: String::new(),
-            secure_delete_input: String::new(),
-            split_input: String::new(),
-            split_chunk_input: String::from("100"),
-            join_base_input: String::new(),
-            join_output_input: String::new(),
-            confirm_delete_open: false,
-            confirm_delete_target: String::new(),
-            confirm_delete_is_dir: false,
-            confirm_batch_open: false,
-            confirm_batch_targets: Vec::new(),
-            confirm_secure_open: false,
-            confirm_secure_target: String::new(),
-            worker_rx: None,
-            worker_cancel: None,
-            progress_messages: Vec::new(),
-            is_busy: false,
-            status: String::new(),
-            status_is_error: false,
-            sort_mode: SortMode::Name,
+    /// List every conflict and error from a copy in the progress panel.
+    fn record_copy_report(&mut self, report: &CopyReport) {
+        for conflict in &report.conflicts {
+            self.progress_messages
+                .push(format!("Conflict: {}", conflict));
+        }
+        for (path, e) in &report.errors {
+            self.progress_messages
+                .push(format!("Error copying {}: {}", path.display(), e));
         }
     }
 }
 
 impl eframe::App for FileManagerApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Ctrl+Z / Ctrl+Shift+Z walk the journal, Tab switches panes, Ctrl+A
+        // selects everything and Ctrl+C / Ctrl+X / Ctrl+V cut, copy and paste
+        // files, unless a text field has focus
+        if !ctx.wants_keyboard_input() {
+            let (undo, redo) = ctx.input(|i| {
+                let z = i.modifiers.command && i.key_pressed(egui::Key::Z);
+                (z && !i.modifiers.shift, z && i.modifiers.shift)
+            });
+            if undo {
+                self.undo();
+            } else if redo {
+                self.redo();
+            }
+            if self.dual_pane && ctx.input(|i| i.key_pressed(egui::Key::Tab)) {
+                self.active_pane = 1 - self.active_pane;
+            }
+            if ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::A)) {
+                self.tab_mut().select_all();
+            }
+            // Alt+Left / Alt+Right walk the history, Alt+Up or Backspace go up
+            let (back, forward, up) = ctx.input(|i| {
+                let alt = |key| i.modifiers.alt && i.key_pressed(key);
+                (
+                    alt(egui::Key::ArrowLeft),
+                    alt(egui::Key::ArrowRight),
+                    alt(egui::Key::ArrowUp) || i.key_pressed(egui::Key::Backspace),
+                )
+            });
+            if back {
+                self.tab_mut().go_back();
+            } else if forward {
+                self.tab_mut().go_forward();
+            } else if up {
+                self.tab_mut().go_up();
+            }
+            let (copy, cut, paste) = ctx.input(|i| {
+                let copy = i.events.iter().any(|e| matches!(e, egui::Event::Copy));
+                let cut = i.events.iter().any(|e| matches!(e, egui::Event::Cut));
+                // Ctrl+V only becomes a Paste event when the clipboard holds text,
+                // so watch the key itself
+                (
+                    copy,
+                    cut,
+                    i.modifiers.command && i.key_released(egui::Key::V),
+                )
+            });
+            if copy || cut {
+                self.yank_selection(cut);
+            } else if paste {
+                self.paste();
+            }
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading(format!("Directory: {}", self.current_dir));
+            match &self.tab().archive_view {
+                Some(view) if view.dir.is_empty() => {
+                    ui.heading(format!("Archive: {}", view.archive));
+                }
+                Some(view) => {
+                    ui.heading(format!("Archive: {}/{}", view.archive, view.dir));
+                }
+                None => {
+                    ui.heading(format!("Directory: {}", self.tab().dir));
+                }
+            }
+
+            // Undo / redo
+            ui.horizontal(|ui| {
+                let undo_label = match self.journal.next_undo() {
+                    Some(op) => format!("Undo {}", op.describe()),
+                    None => "Undo".to_string(),
+                };
+                if ui
+                    .add_enabled(
+                        self.journal.next_undo().is_some(),
+                        egui::Button::new(undo_label),
+                    )
+                    .on_hover_text("Ctrl+Z")
+                    .clicked()
+                {
+                    self.undo();
+                }
+                let redo_label = match self.journal.next_redo() {
+                    Some(op) => format!("Redo {}", op.describe()),
+                    None => "Redo".to_string(),
+                };
+                if ui
+                    .add_enabled(
+                        self.journal.next_redo().is_some(),
+                        egui::Button::new(redo_label),
+                    )
+                    .on_hover_text("Ctrl+Shift+Z")
+                    .clicked()
+                {
+                    self.redo();
+                }
+            });
 
             // Sorting options
             ui.horizontal(|ui| {
                 ui.label("Sort by:");
                 if ui.button("Name").clicked() {
-                    self.sort_mode = SortMode::Name;
-                    sort_files(&mut self.files, self.sort_mode);
+                    self.tab_mut().set_sort(SortMode::Name);
                 }
                 if ui.button("Size").clicked() {
-                    self.sort_mode = SortMode::Size;
-                    sort_files(&mut self.files, self.sort_mode);
+                    self.tab_mut().set_sort(SortMode::Size);
                 }
                 if ui.button("Date").clicked() {
-                    self.sort_mode = SortMode::Date;
-                    sort_files(&mut self.files, self.sort_mode);
+                    self.tab_mut().set_sort(SortMode::Date);
+                }
+                ui.separator();
+                ui.checkbox(&mut self.dual_pane, "Dual pane")
+                    .on_hover_text("Tab switches between the panes");
+                if ui
+                    .add_enabled(self.dual_pane, egui::Button::new("Compare"))
+                    .on_hover_text("Mark files that are missing from or differ in the other pane")
+                    .clicked()
+                {
+                    self.compare_panes();
                 }
             });
 
@@ -165,40 +1998,15 @@ impl eframe::App for FileManagerApp {
                 .show(ui, |ui| {
                     ui.horizontal(|ui| {
                         if ui.button("1. List files").clicked() {
-                            self.files = read_files(&self.current_dir);
+                            self.refresh();
                             self.status = "Listed files".to_string();
                         }
                         if ui.button("2. Copy file").clicked() {
-                            /* copy uses selected + copy_input */
-                            if let Some(sel) = &self.selected {
-                                match crate::actions::copy_file_noninteractive(
-                                    &sel.path,
-                                    &self.copy_input,
-                                ) {
-                                    Ok(_) => {
-                                        self.status = format!(
-                                            "Copied {} -> {}",
-                                            sel.display, self.copy_input
-                                        );
-                                        self.files = read_files(&self.current_dir);
-                                    }
-                                    Err(e) => {
-                                        self.status = format!("Error copying: {}", e);
-                                        self.status_is_error = true
-                                    }
-                                }
-                            } else {
-                                self.status = "No file selected".to_string();
-                            }
+                            /* copy uses the selection + copy_input */
+                            self.copy_selection();
                         }
                         if ui.button("3. Delete file").clicked() {
-                            if let Some(sel) = &self.selected {
-                                self.confirm_delete_open = true;
-                                self.confirm_delete_target = sel.path.clone();
-                                self.confirm_delete_is_dir = false;
-                            } else {
-                                self.status = "No file selected".to_string();
-                            }
+                            self.delete_selection();
                         }
                         if ui.button("4. Handle error").clicked() {
                             self.status = "Centralized error handling is active.".to_string();
@@ -209,27 +2017,27 @@ impl eframe::App for FileManagerApp {
                         ui.label("Change dir:");
                         ui.text_edit_singleline(&mut self.change_dir_input);
                         if ui.button("5. Change directory").clicked() {
-                            if !self.change_dir_input.trim().is_empty() {
-                                self.current_dir = self.change_dir_input.clone();
-                                self.files = read_files(&self.current_dir);
-                                self.status = format!("Changed dir to {}", self.current_dir);
-                            } else {
+                            let input = self.change_dir_input.trim().to_string();
+                            if input.is_empty() {
                                 self.status = "Enter directory".to_string();
+                            } else {
+                                self.change_directory(&input);
                             }
                         }
                         if ui.button("6. Search files").clicked() {
                             let query = self.search_input.to_lowercase();
-                            self.files = read_files(&self.current_dir)
-                                .into_iter()
-                                .filter(|f| f.display.to_lowercase().contains(&query))
-                                .collect();
+                            self.tab_mut().filter(&query);
                             self.status = format!("Search results for '{}'", self.search_input);
                         }
                         if ui.button("7. Batch delete files").clicked() {
+                            if self.batch_input.trim().is_empty() {
+                                self.delete_selection();
+                                return;
+                            }
                             self.confirm_batch_targets = self
                                 .batch_input
                                 .split(',')
-                                .map(|s| self.current_dir.clone() + "/" + s.trim())
+                                .map(|s| self.tab().dir.clone() + "/" + s.trim())
                                 .filter(|s| !s.is_empty())
                                 .collect();
                             self.confirm_batch_open = true;
@@ -238,79 +2046,63 @@ impl eframe::App for FileManagerApp {
 
                     ui.horizontal(|ui| {
                         if ui.button("8. Rename file").clicked() {
-                            if let Some(sel) = &self.selected {
-                                let new_path = Path::new(&self.current_dir)
-                                    .join(&self.rename_input)
-                                    .to_string_lossy()
-                                    .to_string();
-                                match crate::actions::rename_file_noninteractive(
-                                    &sel.path, &new_path,
-                                ) {
-                                    Ok(_) => {
-                                        self.status = format!(
-                                            "Renamed {} -> {}",
-                                            sel.display, self.rename_input
-                                        );
-                                        self.files = read_files(&self.current_dir);
-                                        self.selected = None;
-                                    }
-                                    Err(e) => {
-                                        self.status = format!("Error renaming: {}", e);
-                                        self.status_is_error = true
-                                    }
-                                }
-                            } else {
-                                self.status = "No file selected".to_string();
-                            }
+                            self.rename_selection();
                         }
                         if ui.button("9. Move file").clicked() {
-                            if let Some(sel) = &self.selected {
-                                match crate::actions::move_file_noninteractive(
-                                    &sel.path,
-                                    &self.move_input,
-                                ) {
-                                    Ok(_) => {
-                                        self.status =
-                                            format!("Moved {} -> {}", sel.display, self.move_input);
-                                        self.files = read_files(&self.current_dir);
-                                        self.selected = None;
-                                    }
-                                    Err(e) => {
-                                        self.status = format!("Error moving: {}", e);
-                                        self.status_is_error = true
-                                    }
-                                }
-                            } else {
-                                self.status = "No file selected".to_string();
-                            }
+                            self.move_selection();
                         }
                         if ui.button("10. Batch copy files").clicked() {
+                            let input = self.batch_copy_dest_input.clone();
+                            let Some(dest) = self.target_or_other(&input) else {
+                                return;
+                            };
+                            // no names typed in: copy the selection
+                            if self.batch_copy_input.trim().is_empty() {
+                                let sources: Vec<String> =
+                                    self.tab().selection().into_iter().map(|f| f.path).collect();
+                                self.run_copy(&sources, &dest);
+                                return;
+                            }
                             let names: Vec<String> = self
                                 .batch_copy_input
                                 .split(',')
                                 .map(|s| s.trim())
                                 .filter(|s| !s.is_empty())
                                 .map(|n| {
-                                    Path::new(&self.current_dir)
+                                    Path::new(&self.tab().dir)
                                         .join(n)
                                         .to_string_lossy()
                                         .to_string()
                                 })
                                 .collect();
-                            let results = crate::actions::batch_copy_noninteractive(
-                                &names,
-                                &self.batch_copy_dest_input,
-                            );
-                            for (i, res) in results.iter().enumerate() {
+                            let options = CopyOptions {
+                                policy: self.copy_policy,
+                                ..CopyOptions::default()
+                            };
+                            let results =
+                                crate::actions::batch_copy_noninteractive(&names, &dest, &options);
+                            self.progress_messages.clear();
+                            let mut ops = Vec::new();
+                            for (i, res) in results.into_iter().enumerate() {
                                 match res {
-                                    Ok(_) => self.status = format!("Copied {}", names[i]),
+                                    Ok(report) => {
+                                        self.record_copy_report(&report);
+                                        ops.push(copy_operation(&names[i], &report));
+                                        self.status =
+                                            format!("Copied {} ({})", names[i], report.summary());
+                                        self.status_is_error = !report.errors.is_empty();
+                                    }
                                     Err(e) => {
                                         self.status = format!("Error copying {}: {}", names[i], e);
                                         self.status_is_error = true
                                     }
                                 }
                             }
-                            self.files = read_files(&self.current_dir);
+                            self.journal.record(Operation::Batch {
+                                label: "batch copy".to_string(),
+                                ops,
+                            });
+                            self.refresh();
                         }
                     });
 
@@ -325,16 +2117,21 @@ impl eframe::App for FileManagerApp {
                                 .filter_map(|p| {
                                     p.split_once(':').map(|(a, b)| {
                                         (
-                                            self.current_dir.clone() + "/" + a.trim(),
-                                            self.current_dir.clone() + "/" + b.trim(),
+                                            self.tab().dir.clone() + "/" + a.trim(),
+                                            self.tab().dir.clone() + "/" + b.trim(),
                                         )
                                     })
                                 })
                                 .collect();
                             let results = crate::actions::batch_rename_noninteractive(&pairs);
+                            let mut ops = Vec::new();
                             for (i, res) in results.iter().enumerate() {
                                 match res {
                                     Ok(_) => {
+                                        ops.push(Operation::Rename {
+                                            from: PathBuf::from(&pairs[i].0),
+                                            to: PathBuf::from(&pairs[i].1),
+                                        });
                                         self.status =
                                             format!("Renamed {} -> {}", pairs[i].0, pairs[i].1)
                                     }
@@ -345,20 +2142,27 @@ impl eframe::App for FileManagerApp {
                                     }
                                 }
                             }
-                            self.files = read_files(&self.current_dir);
+                            self.journal.record(Operation::Batch {
+                                label: "batch rename".to_string(),
+                                ops,
+                            });
+                            self.refresh();
                         }
                     });
 
                     ui.horizontal(|ui| {
                         if ui.button("12. Create file").clicked() {
-                            let path = Path::new(&self.current_dir)
+                            let path = Path::new(&self.tab().dir)
                                 .join(&self.new_name_input)
                                 .to_string_lossy()
                                 .to_string();
                             match crate::actions::create_file_noninteractive(&path) {
                                 Ok(_) => {
+                                    self.journal.record(Operation::CreateFile {
+                                        path: PathBuf::from(&path),
+                                    });
                                     self.status = format!("Created file {}", self.new_name_input);
-                                    self.files = read_files(&self.current_dir);
+                                    self.refresh();
                                 }
                                 Err(e) => {
                                     self.status = format!(
@@ -370,15 +2174,18 @@ impl eframe::App for FileManagerApp {
                             }
                         }
                         if ui.button("13. Create directory").clicked() {
-                            let path = Path::new(&self.current_dir)
+                            let path = Path::new(&self.tab().dir)
                                 .join(&self.new_name_input)
                                 .to_string_lossy()
                                 .to_string();
                             match crate::actions::create_directory_noninteractive(&path) {
                                 Ok(_) => {
+                                    self.journal.record(Operation::CreateDir {
+                                        path: PathBuf::from(&path),
+                                    });
                                     self.status =
                                         format!("Created directory {}", self.new_name_input);
-                                    self.files = read_files(&self.current_dir);
+                                    self.refresh();
                                 }
                                 Err(e) => {
                                     self.status = format!(
@@ -390,7 +2197,7 @@ impl eframe::App for FileManagerApp {
                             }
                         }
                         if ui.button("14. Delete directory").clicked() {
-                            let path = Path::new(&self.current_dir)
+                            let path = Path::new(&self.tab().dir)
                                 .join(&self.new_name_input)
                                 .to_string_lossy()
                                 .to_string();
@@ -402,16 +2209,7 @@ impl eframe::App for FileManagerApp {
 
                     ui.horizontal(|ui| {
                         if ui.button("17. Archive: List contents").clicked() {
-                            match crate::actions::archive_list_noninteractive(&self.archive_input) {
-                                Ok(s) => {
-                                    self.status = s;
-                                    self.status_is_error = false
-                                }
-                                Err(e) => {
-                                    self.status = e;
-                                    self.status_is_error = true
-                                }
-                            }
+                            self.list_archive();
                         }
                         if ui.button("18. Archive: Extract").clicked() {
                             if self.is_busy {
@@ -427,11 +2225,15 @@ impl eframe::App for FileManagerApp {
                                     Some(self.archive_password_input.clone())
                                 };
                                 let cancel_clone = cancel.clone();
+                                let backend = self.archive_backend;
+                                let options = self.extract_options();
                                 std::thread::spawn(move || {
                                     crate::actions::archive_extract_progress(
                                         &inp,
                                         &dest,
                                         pwd.as_deref(),
+                                        options,
+                                        backend,
                                         tx,
                                         cancel_clone,
                                     );
@@ -448,7 +2250,7 @@ impl eframe::App for FileManagerApp {
                         if ui.button("19. Archive: Create").clicked() {
                             if self.is_busy {
                                 self.status = "Already running an operation".to_string();
-                            } else {
+                            } else if let Some(options) = self.create_options() {
                                 let sources: Vec<String> = self
                                     .archive_sources_input
                                     .split(',')
@@ -456,75 +2258,18 @@ impl eframe::App for FileManagerApp {
                                     .filter(|s| !s.is_empty())
                                     .map(|s| s.to_string())
                                     .collect();
-                                let fmt_owned = if self.archive_format_input.trim().is_empty() {
-                                    None
-                                } else {
-                                    Some(self.archive_format_input.clone())
-                                };
-                                let pwd_owned = if self.archive_password_input.trim().is_empty() {
-                                    None
-                                } else {
-                                    Some(self.archive_password_input.clone())
-                                };
-                                let (tx, rx) = mpsc::channel();
-                                let cancel = Arc::new(AtomicBool::new(false));
-                                let cancel_clone = cancel.clone();
-                                let sources_clone = sources.clone();
                                 let output = self.archive_output_input.clone();
-                                std::thread::spawn(move || {
-                                    crate::actions::archive_create_progress(
-                                        &sources_clone,
-                                        &output,
-                                        fmt_owned.as_deref(),
-                                        pwd_owned.as_deref(),
-                                        tx,
-                                        cancel_clone,
-                                    );
-                                });
-                                self.worker_rx = Some(rx);
-                                self.worker_cancel = Some(cancel);
-                                self.progress_messages.clear();
-                                self.is_busy = true;
+                                self.start_archive_create(sources, output, options);
                             }
                         }
                         if ui.button("20. Calculate file hash").clicked() {
-                            match crate::actions::calculate_hash_noninteractive(
-                                &self.hash_input,
-                                &self.hash_algo_input,
-                            ) {
-                                Ok(s) => {
-                                    self.status = s;
-                                    self.status_is_error = false
-                                }
-                                Err(e) => {
-                                    self.status = e;
-                                    self.status_is_error = true
-                                }
-                            }
+                            self.start_hash();
                         }
                     });
 
                     ui.horizontal(|ui| {
                         if ui.button("21. Find duplicate files").clicked() {
-                            if self.is_busy {
-                                self.status = "Already running an operation".to_string();
-                            } else {
-                                let (tx, rx) = mpsc::channel();
-                                let cancel = Arc::new(AtomicBool::new(false));
-                                let dir = self.duplicates_dir_input.clone();
-                                let cancel_clone = cancel.clone();
-                                std::thread::spawn(move || {
-                                    crate::actions::find_duplicates_progress(
-                                        &dir,
-                                        tx,
-                                        cancel_clone,
-                                    );
-                                });
-                                self.worker_rx = Some(rx);
-                                self.worker_cancel = Some(cancel);
-                                self.progress_messages.clear();
-                                self.is_busy = true;
-                            }
+                            self.start_find_duplicates();
                         }
                         if ui.button("22. Secure delete file").clicked() {
                             self.confirm_secure_open = true;
@@ -534,32 +2279,10 @@ impl eframe::App for FileManagerApp {
 
                     ui.horizontal(|ui| {
                         if ui.button("23. Split file").clicked() {
-                            let mb = self.split_chunk_input.parse::<u64>().unwrap_or(100);
-                            match crate::actions::split_file_noninteractive(&self.split_input, mb) {
-                                Ok(n) => {
-                                    self.status = format!("Split into {} parts", n);
-                                    self.files = read_files(&self.current_dir);
-                                }
-                                Err(e) => {
-                                    self.status = e;
-                                    self.status_is_error = true
-                                }
-                            }
+                            self.start_split();
                         }
                         if ui.button("24. Join file chunks").clicked() {
-                            match crate::actions::join_files_noninteractive(
-                                &self.join_base_input,
-                                &self.join_output_input,
-                            ) {
-                                Ok(n) => {
-                                    self.status = format!("Joined {} parts", n);
-                                    self.files = read_files(&self.current_dir);
-                                }
-                                Err(e) => {
-                                    self.status = e;
-                                    self.status_is_error = true
-                                }
-                            }
+                            self.start_join();
                         }
                     });
 
@@ -574,32 +2297,37 @@ impl eframe::App for FileManagerApp {
             egui::CollapsingHeader::new("Advanced Actions")
                 .default_open(false)
                 .show(ui, |ui| {
-                    // show availability of external 7z
-                    if crate::archive::is_7z_available() {
-                        ui.colored_label(
-                            egui::Color32::LIGHT_GREEN,
-                            "7z: available (will use 7z for many formats)",
-                        );
-                    } else {
-                        ui.colored_label(
-                            egui::Color32::YELLOW,
-                            "7z: not found — using built-in handlers for zip/tar",
-                        );
-                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Archive backend:");
+                        egui::ComboBox::from_id_source("archive_backend")
+                            .selected_text(self.archive_backend.label())
+                            .show_ui(ui, |ui| {
+                                for choice in BackendChoice::ALL {
+                                    ui.selectable_value(
+                                        &mut self.archive_backend,
+                                        choice,
+                                        choice.label(),
+                                    );
+                                }
+                            });
+                        // show availability of external 7z
+                        if crate::archive::is_7z_available() {
+                            ui.colored_label(
+                                egui::Color32::LIGHT_GREEN,
+                                "7z: available (used for .7z and when built-in support is missing)",
+                            );
+                        } else {
+                            ui.colored_label(
+                                egui::Color32::YELLOW,
+                                "7z: not found — built-in zip/tar backends only",
+                            );
+                        }
+                    });
                     ui.horizontal(|ui| {
                         ui.label("Archive path:");
                         ui.text_edit_singleline(&mut self.archive_input);
                         if ui.button("List Archive").clicked() {
-                            match crate::actions::archive_list_noninteractive(&self.archive_input) {
-                                Ok(s) => {
-                                    self.status = s;
-                                    self.status_is_error = false
-                                }
-                                Err(e) => {
-                                    self.status = e;
-                                    self.status_is_error = true
-                                }
-                            }
+                            self.list_archive();
                         }
                     });
 
@@ -625,11 +2353,15 @@ impl eframe::App for FileManagerApp {
                                 let dest = self.archive_dest_input.clone();
                                 let cancel_clone = cancel.clone();
                                 let pwd_move = pwd.clone();
+                                let backend = self.archive_backend;
+                                let options = self.extract_options();
                                 std::thread::spawn(move || {
                                     crate::actions::archive_extract_progress(
                                         &inp,
                                         &dest,
                                         pwd_move.as_deref(),
+                                        options,
+                                        backend,
                                         tx,
                                         cancel_clone,
                                     );
@@ -640,6 +2372,37 @@ impl eframe::App for FileManagerApp {
                                 self.is_busy = true;
                             }
                         }
+                        if ui
+                            .button("Test")
+                            .on_hover_text("Check every entry against the archive's checksums")
+                            .clicked()
+                        {
+                            self.start_archive_test();
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Only (comma):");
+                        ui.text_edit_singleline(&mut self.extract_patterns_input);
+                        ui.label("Strip:");
+                        ui.add(egui::DragValue::new(&mut self.extract_strip).clamp_range(0..=32));
+                        ui.label("If exists:");
+                        egui::ComboBox::from_id_source("extract_policy")
+                            .selected_text(self.extract_policy.label())
+                            .show_ui(ui, |ui| {
+                                for policy in ConflictPolicy::ALL {
+                                    ui.selectable_value(
+                                        &mut self.extract_policy,
+                                        policy,
+                                        policy.label(),
+                                    );
+                                }
+                            });
+                        ui.checkbox(&mut self.extract_rewrite_unsafe, "Rewrite unsafe paths")
+                            .on_hover_text(
+                                "Keep entries with absolute or '..' paths inside the \
+                                 destination instead of rejecting them",
+                            );
                     });
 
                     ui.horizontal(|ui| {
@@ -649,10 +2412,46 @@ impl eframe::App for FileManagerApp {
                         ui.text_edit_singleline(&mut self.archive_output_input);
                         ui.label("Fmt:");
                         ui.text_edit_singleline(&mut self.archive_format_input);
+                        egui::ComboBox::from_id_source("archive_method")
+                            .selected_text(self.archive_method.map_or("default", |m| m.label()))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.archive_method, None, "default");
+                                for method in CompressionMethod::ALL {
+                                    ui.selectable_value(
+                                        &mut self.archive_method,
+                                        Some(method),
+                                        method.label(),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text("Entry compression for zip and 7z archives");
+                        ui.label("Level:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.archive_level_input)
+                                .desired_width(32.0)
+                                .hint_text("def"),
+                        );
+                        egui::ComboBox::from_id_source("archive_encryption")
+                            .selected_text(self.archive_encryption.label())
+                            .show_ui(ui, |ui| {
+                                for encryption in ZipEncryption::ALL {
+                                    ui.selectable_value(
+                                        &mut self.archive_encryption,
+                                        encryption,
+                                        encryption.label(),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text(
+                                "Cipher for zip archives with a password; ZipCrypto is weak \
+                                 but opens in every zip tool",
+                            );
                         if ui.button("Create Archive").clicked() {
                             if self.is_busy {
                                 self.status = "Already running an operation".to_string();
-                            } else {
+                            } else if let Some(options) = self.create_options() {
                                 let sources: Vec<String> = self
                                     .archive_sources_input
                                     .split(',')
@@ -660,39 +2459,52 @@ impl eframe::App for FileManagerApp {
                                     .filter(|s| !s.is_empty())
                                     .map(|s| s.to_string())
                                     .collect();
-                                let fmt_owned = if self.archive_format_input.trim().is_empty() {
-                                    None
-                                } else {
-                                    Some(self.archive_format_input.clone())
-                                };
-                                let pwd_owned = if self.archive_password_input.trim().is_empty() {
-                                    None
-                                } else {
-                                    Some(self.archive_password_input.clone())
-                                };
-                                let (tx, rx) = mpsc::channel();
-                                let cancel = Arc::new(AtomicBool::new(false));
-                                let cancel_clone = cancel.clone();
-                                let sources_clone = sources.clone();
                                 let output = self.archive_output_input.clone();
-                                std::thread::spawn(move || {
-                                    crate::actions::archive_create_progress(
-                                        &sources_clone,
-                                        &output,
-                                        fmt_owned.as_deref(),
-                                        pwd_owned.as_deref(),
-                                        tx,
-                                        cancel_clone,
-                                    );
-                                });
-                                self.worker_rx = Some(rx);
-                                self.worker_cancel = Some(cancel);
-                                self.progress_messages.clear();
-                                self.is_busy = true;
+                                self.start_archive_create(sources, output, options);
                             }
                         }
                     });
 
+                    ui.horizontal(|ui| {
+                        ui.label("Base dir:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.archive_base_input)
+                                .hint_text("sources are relative to"),
+                        );
+                        ui.label("Exclude (comma):");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.archive_exclude_input)
+                                .hint_text("target/, .git/, *.o"),
+                        );
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Delete (comma):");
+                        ui.text_edit_singleline(&mut self.archive_delete_input);
+                        ui.label("Changed by:");
+                        egui::ComboBox::from_id_source("archive_compare")
+                            .selected_text(self.archive_compare.label())
+                            .show_ui(ui, |ui| {
+                                for check in ChangeCheck::ALL {
+                                    ui.selectable_value(
+                                        &mut self.archive_compare,
+                                        check,
+                                        check.label(),
+                                    );
+                                }
+                            });
+                        if ui
+                            .button("Update Archive")
+                            .on_hover_text(
+                                "Add the sources to the archive in Out, replacing changed \
+                                 entries and removing the ones to delete",
+                            )
+                            .clicked()
+                        {
+                            self.start_archive_update();
+                        }
+                    });
+
                     ui.separator();
 
                     ui.horizontal(|ui| {
@@ -701,19 +2513,17 @@ impl eframe::App for FileManagerApp {
                         ui.label("Algo:");
                         ui.text_edit_singleline(&mut self.hash_algo_input);
                         if ui.button("Calculate").clicked() {
-                            match crate::actions::calculate_hash_noninteractive(
-                                &self.hash_input,
-                                &self.hash_algo_input,
-                            ) {
-                                Ok(s) => {
-                                    self.status = s;
-                                    self.status_is_error = false
-                                }
-                                Err(e) => {
-                                    self.status = e;
-                                    self.status_is_error = true
-                                }
-                            }
+                            self.start_hash();
+                        }
+                        if ui
+                            .button("Verify")
+                            .on_hover_text(
+                                "Treat the path as a sha256sum/b3sum-style checksum file \
+                                 and check every file it lists",
+                            )
+                            .clicked()
+                        {
+                            self.start_verify();
                         }
                     });
 
@@ -721,31 +2531,35 @@ impl eframe::App for FileManagerApp {
                         ui.label("Find duplicates in:");
                         ui.text_edit_singleline(&mut self.duplicates_dir_input);
                         if ui.button("Find Duplicates").clicked() {
-                            if self.is_busy {
-                                self.status = "Already running an operation".to_string();
-                            } else {
-                                let (tx, rx) = mpsc::channel();
-                                let cancel = Arc::new(AtomicBool::new(false));
-                                let dir = self.duplicates_dir_input.clone();
-                                let cancel_clone = cancel.clone();
-                                std::thread::spawn(move || {
-                                    crate::actions::find_duplicates_progress(
-                                        &dir,
-                                        tx,
-                                        cancel_clone,
-                                    );
-                                });
-                                self.worker_rx = Some(rx);
-                                self.worker_cancel = Some(cancel);
-                                self.progress_messages.clear();
-                                self.is_busy = true;
-                            }
+                            self.start_find_duplicates();
                         }
                     });
 
                     ui.horizontal(|ui| {
-                        ui.label("Secure delete file:");
+                        ui.label("Secure delete file or directory:");
                         ui.text_edit_singleline(&mut self.secure_delete_input);
+                        egui::ComboBox::from_id_source("shred_scheme")
+                            .selected_text(self.shred_scheme.label())
+                            .show_ui(ui, |ui| {
+                                for scheme in Scheme::ALL {
+                                    // Custom matches regardless of its pass count
+                                    let selected = std::mem::discriminant(&self.shred_scheme)
+                                        == std::mem::discriminant(&scheme);
+                                    if ui.selectable_label(selected, scheme.label()).clicked()
+                                        && !selected
+                                    {
+                                        self.shred_scheme = scheme;
+                                    }
+                                }
+                            });
+                        if let Scheme::Custom(passes) = &mut self.shred_scheme {
+                            ui.add(
+                                egui::DragValue::new(passes)
+                                    .clamp_range(1..=35)
+                                    .suffix(" passes"),
+                            );
+                        }
+                        ui.checkbox(&mut self.shred_verify, "Verify last pass");
                         if ui.button("Secure Delete").clicked() {
                             if self.is_busy {
                                 self.status = "Already running an operation".to_string();
@@ -760,21 +2574,15 @@ impl eframe::App for FileManagerApp {
                     ui.horizontal(|ui| {
                         ui.label("Split file:");
                         ui.text_edit_singleline(&mut self.split_input);
-                        ui.label("Chunk MB:");
+                        ui.label(if self.split_by_count {
+                            "Parts:"
+                        } else {
+                            "Chunk MB:"
+                        });
                         ui.text_edit_singleline(&mut self.split_chunk_input);
+                        ui.checkbox(&mut self.split_by_count, "By part count");
                         if ui.button("Split").clicked() {
-                            let mb = self.split_chunk_input.parse::<u64>().unwrap_or(100);
-                            match crate::actions::split_file_noninteractive(&self.split_input, mb) {
-                                Ok(n) => {
-                                    self.status = format!("Split into {} parts", n);
-                                    self.status_is_error = false
-                                }
-                                Err(e) => {
-                                    self.status = e;
-                                    self.status_is_error = true
-                                }
-                            }
-                            self.files = read_files(&self.current_dir);
+                            self.start_split();
                         }
                     });
 
@@ -784,22 +2592,137 @@ impl eframe::App for FileManagerApp {
                         ui.label("Out:");
                         ui.text_edit_singleline(&mut self.join_output_input);
                         if ui.button("Join").clicked() {
-                            match crate::actions::join_files_noninteractive(
-                                &self.join_base_input,
-                                &self.join_output_input,
-                            ) {
-                                Ok(n) => {
-                                    self.status = format!("Joined {} parts", n);
-                                    self.status_is_error = false
-                                }
-                                Err(e) => {
-                                    self.status = e;
-                                    self.status_is_error = true
-                                }
+                            self.start_join();
+                        }
+                    });
+                });
+
+            // Trash panel
+            egui::CollapsingHeader::new("Trash")
+                .default_open(false)
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("Refresh Trash").clicked() {
+                            self.refresh_trash();
+                        }
+                        if ui.button("Empty Trash").clicked() {
+                            self.refresh_trash();
+                            self.confirm_empty_trash_open = true;
+                        }
+                    });
+                    if self.trash_items.is_empty() {
+                        ui.label("Trash is empty (or not loaded yet).");
+                    }
+                    let mut restore: Option<String> = None;
+                    egui::Grid::new("trash_grid").striped(true).show(ui, |ui| {
+                        for item in &self.trash_items {
+                            ui.label(&item.name);
+                            ui.label(item.original_path.to_string_lossy());
+                            ui.label(&item.deletion_date);
+                            if ui.button("Restore").clicked() {
+                                restore = Some(item.name.clone());
                             }
-                            self.files = read_files(&self.current_dir);
+                            ui.end_row();
                         }
                     });
+                    if let Some(name) = restore {
+                        match crate::actions::trash_restore_noninteractive(&name) {
+                            Ok(path) => {
+                                self.journal.record(Operation::Restore {
+                                    original: path.clone(),
+                                    trash_name: name.clone(),
+                                });
+                                self.status = format!("Restored {} to {}", name, path.display());
+                                self.status_is_error = false;
+                            }
+                            Err(e) => {
+                                self.status = format!("Error restoring {}: {}", name, e);
+                                self.status_is_error = true;
+                            }
+                        }
+                        self.refresh_trash();
+                        self.refresh();
+                    }
+                });
+
+            egui::CollapsingHeader::new("Duplicates")
+                .default_open(false)
+                .show(ui, |ui| {
+                    if self.duplicate_groups.is_empty() {
+                        ui.label("No duplicate groups (run Find Duplicates first).");
+                    }
+                    let mut action: Option<(usize, Resolution)> = None;
+                    for (i, group) in self.duplicate_groups.iter().enumerate() {
+                        ui.label(format!(
+                            "Group {}: {} files, {} bytes each",
+                            i + 1,
+                            group.files.len(),
+                            group.size
+                        ));
+                        for (j, file) in group.files.iter().enumerate() {
+                            ui.radio_value(&mut self.duplicate_keep[i], j, file.to_string_lossy());
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label("Keep selected and");
+                            for resolution in Resolution::ALL {
+                                if ui.button(resolution.label()).clicked() {
+                                    action = Some((i, resolution));
+                                }
+                            }
+                            ui.label("the rest");
+                        });
+                        ui.separator();
+                    }
+                    if let Some((i, resolution)) = action {
+                        self.resolve_duplicates(i, resolution);
+                    }
+                });
+
+            egui::CollapsingHeader::new("Archive Contents")
+                .default_open(false)
+                .show(ui, |ui| {
+                    if self.archive_entries.is_empty() {
+                        ui.label("No archive listed (use List Archive first).");
+                        return;
+                    }
+                    ui.label(&self.archive_listed);
+                    egui::ScrollArea::vertical()
+                        .id_source("archive_entries_scroll")
+                        .max_height(300.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("archive_grid")
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    ui.label("Name");
+                                    ui.label("Size");
+                                    ui.label("Packed");
+                                    ui.label("Ratio");
+                                    ui.label("Modified");
+                                    ui.label("CRC32");
+                                    ui.label("Type");
+                                    ui.end_row();
+                                    for entry in &self.archive_entries {
+                                        let name = if entry.encrypted {
+                                            format!("{} (encrypted)", entry.path)
+                                        } else {
+                                            entry.path.clone()
+                                        };
+                                        ui.label(name);
+                                        ui.label(format!("{} bytes", entry.size));
+                                        ui.label(
+                                            entry
+                                                .compressed_size
+                                                .map(|s| format!("{} bytes", s))
+                                                .unwrap_or_default(),
+                                        );
+                                        ui.label(entry.ratio_string());
+                                        ui.label(entry.modified_string());
+                                        ui.label(entry.crc_string());
+                                        ui.label(if entry.is_dir { "Directory" } else { "File" });
+                                        ui.end_row();
+                                    }
+                                });
+                        });
                 });
 
             // Search bar
@@ -808,148 +2731,198 @@ impl eframe::App for FileManagerApp {
                 ui.text_edit_singleline(&mut self.search_input);
                 if ui.button("Go").clicked() {
                     let query = self.search_input.to_lowercase();
-                    self.files = read_files(&self.current_dir)
-                        .into_iter()
-                        .filter(|f| f.display.to_lowercase().contains(&query))
-                        .collect();
+                    self.tab_mut().filter(&query);
                     self.status = format!("Search results for '{}'", self.search_input);
                     self.status_is_error = false;
                 }
                 if ui.button("Clear").clicked() {
-                    self.files = read_files(&self.current_dir);
+                    self.refresh();
                     self.search_input.clear();
                     self.status = "Search cleared".to_string();
                     self.status_is_error = false;
                 }
             });
 
-            // File list with metadata
-            egui::Grid::new("file_grid").striped(true).show(ui, |ui| {
-                ui.label("Name");
-                ui.label("Size");
-                ui.label("Modified");
-                ui.label("Type");
-                ui.end_row();
-
-                // iterate over a clone so we can safely mutate self.files later
-                for entry in self.files.clone() {
-                    let selected =
-                        self.selected.as_ref().map(|s| s.path.clone()) == Some(entry.path.clone());
-                    let label = if entry.hidden {
-                        format!("{} (hidden)", entry.display)
-                    } else {
-                        entry.display.clone()
-                    };
+            if self.dual_pane {
+                ui.columns(2, |columns| {
+                    for (index, ui) in columns.iter_mut().enumerate() {
+                        self.pane_ui(ui, index);
+                    }
+                });
+            } else {
+                self.pane_ui(ui, self.active_pane);
+            }
 
-                    let response = ui.selectable_label(selected, label);
-                    ui.label(format!("{} bytes", entry.size));
-                    ui.label(&entry.modified);
-                    ui.label(if entry.is_dir { "Directory" } else { "File" });
-                    ui.end_row();
+            ui.separator();
 
-                    // Double-click navigation into directories
-                    if response.double_clicked() && entry.is_dir {
-                        self.current_dir = entry.path.clone();
-                        self.files = read_files(&self.current_dir);
-                        self.selected = None;
-                        self.status = format!("Entered directory {}", self.current_dir);
-                        self.status_is_error = false;
-                    } else if response.clicked() {
-                        self.selected = Some(entry.clone());
-                        self.rename_input = entry.display.clone();
-                        self.status.clear();
-                    }
+            // Selection tools
+            ui.horizontal(|ui| {
+                if ui.button("Select All").on_hover_text("Ctrl+A").clicked() {
+                    self.tab_mut().select_all();
+                }
+                if ui.button("Invert").clicked() {
+                    self.tab_mut().invert_selection();
+                }
+                if ui.button("Select None").clicked() {
+                    self.tab_mut().clear_selection();
+                }
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.select_glob_input)
+                        .desired_width(80.0)
+                        .hint_text("*.txt"),
+                );
+                if ui.button("Select Matching").clicked() {
+                    let pattern = self.select_glob_input.trim().to_string();
+                    let count = self.tab_mut().select_glob(&pattern);
+                    self.status = format!("{} entries match '{}'", count, pattern);
+                    self.status_is_error = false;
+                }
+                ui.separator();
+                let held = match &self.clipboard {
+                    Some(files) if files.cut => format!("{} items cut", files.paths.len()),
+                    Some(files) => format!("{} items copied", files.paths.len()),
+                    None => "Files copied in another application".to_string(),
+                };
+                if ui
+                    .button("Paste")
+                    .on_hover_text(format!("Ctrl+V: {}", held))
+                    .clicked()
+                {
+                    self.paste();
                 }
             });
 
-            ui.separator();
-
-            // Actions on selected file
-            if let Some(selected_file) = self.selected.clone() {
-                ui.label(format!("Selected: {}", selected_file.display));
+            // Actions on the selection
+            let selection = self.tab().selection();
+            if !selection.is_empty() {
+                match selection.as_slice() {
+                    [one] => ui.label(format!("Selected: {}", one.display)),
+                    many => ui.label(format!(
+                        "Selected: {} items, {} bytes in files",
+                        many.len(),
+                        many.iter()
+                            .filter(|f| !f.is_dir)
+                            .map(|f| f.size)
+                            .sum::<u64>()
+                    )),
+                };
 
                 ui.horizontal(|ui| {
                     if ui.button("Delete").clicked() {
                         // open confirmation dialog
-                        self.confirm_delete_open = true;
-                        self.confirm_delete_target = selected_file.path.clone();
-                        self.confirm_delete_is_dir = false;
+                        self.delete_selection();
                     }
 
-                    ui.text_edit_singleline(&mut self.rename_input);
-                    if ui.button("Rename").clicked() {
-                        let new_path = Path::new(&self.current_dir).join(&self.rename_input);
-                        let new_path_str = new_path.to_string_lossy().to_string();
-                        match crate::actions::rename_file_noninteractive(
-                            &selected_file.path,
-                            &new_path_str,
-                        ) {
-                            Ok(_) => {
-                                self.status = format!(
-                                    "Renamed {} -> {}",
-                                    selected_file.display, self.rename_input
-                                );
-                                self.status_is_error = false;
-                            }
-                            Err(e) => {
-                                self.status =
-                                    format!("Error renaming {}: {}", selected_file.display, e);
-                                self.status_is_error = true;
-                            }
-                        }
-                        self.files = read_files(&self.current_dir);
-                        self.selected = None;
+                    let hint = if selection.len() > 1 {
+                        "{name}_{n}{ext}"
+                    } else {
+                        ""
+                    };
+                    ui.add(egui::TextEdit::singleline(&mut self.rename_input).hint_text(hint));
+                    if ui
+                        .button("Rename")
+                        .on_hover_text(
+                            "With several entries selected, {name}, {ext} and {n} stand for \
+                             the old name, its extension and a counter",
+                        )
+                        .clicked()
+                    {
+                        self.rename_selection();
                     }
                 });
 
+                // an empty destination is the other pane's directory
+                let other = self.other_dir().unwrap_or_default();
                 ui.horizontal(|ui| {
-                    ui.text_edit_singleline(&mut self.move_input);
+                    ui.add(egui::TextEdit::singleline(&mut self.move_input).hint_text(&other));
                     if ui.button("Move").clicked() {
-                        match crate::actions::move_file_noninteractive(
-                            &selected_file.path,
-                            &self.move_input,
-                        ) {
-                            Ok(_) => {
-                                self.status = format!(
-                                    "Moved {} -> {}",
-                                    selected_file.display, self.move_input
-                                );
-                                self.status_is_error = false;
-                            }
-                            Err(e) => {
-                                self.status =
-                                    format!("Error moving {}: {}", selected_file.display, e);
-                                self.status_is_error = true;
-                            }
-                        }
-                        self.files = read_files(&self.current_dir);
-                        self.selected = None;
+                        self.move_selection();
                     }
                 });
 
                 ui.horizontal(|ui| {
-                    ui.text_edit_singleline(&mut self.copy_input);
+                    ui.add(egui::TextEdit::singleline(&mut self.copy_input).hint_text(&other));
                     if ui.button("Copy").clicked() {
-                        match crate::actions::copy_file_noninteractive(
-                            &selected_file.path,
-                            &self.copy_input,
-                        ) {
-                            Ok(_) => {
-                                self.status = format!(
-                                    "Copied {} -> {}",
-                                    selected_file.display, self.copy_input
-                                );
-                                self.status_is_error = false;
-                            }
-                            Err(e) => {
-                                self.status =
-                                    format!("Error copying {}: {}", selected_file.display, e);
-                                self.status_is_error = true;
+                        self.copy_selection();
+                    }
+                    ui.label("If exists:");
+                    egui::ComboBox::from_id_source("copy_policy")
+                        .selected_text(self.copy_policy.label())
+                        .show_ui(ui, |ui| {
+                            for policy in ConflictPolicy::ALL {
+                                ui.selectable_value(&mut self.copy_policy, policy, policy.label());
                             }
-                        }
-                        self.files = read_files(&self.current_dir);
+                        });
+                });
+
+                ui.horizontal(|ui| {
+                    if ui.button("Cut").on_hover_text("Ctrl+X").clicked() {
+                        self.yank_selection(true);
+                    }
+                    if ui
+                        .button("Copy to Clipboard")
+                        .on_hover_text("Ctrl+C")
+                        .clicked()
+                    {
+                        self.yank_selection(false);
+                    }
+                    if ui
+                        .button("Hash")
+                        .on_hover_text("Hash the selected files with the algorithm of the hash row")
+                        .clicked()
+                    {
+                        let files = selection
+                            .iter()
+                            .filter(|f| !f.is_dir)
+                            .map(|f| f.path.clone())
+                            .collect();
+                        self.start_hash_files(files);
+                    }
+                    if ui
+                        .button("Archive")
+                        .on_hover_text(
+                            "Pack the selection into the archive named in the create row, \
+                             or into <name>.zip here",
+                        )
+                        .clicked()
+                    {
+                        self.archive_selection();
                     }
                 });
+
+                if let [file] = selection.as_slice()
+                    && !file.is_dir
+                {
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_source("compress_codec")
+                            .selected_text(self.compress_codec.label())
+                            .show_ui(ui, |ui| {
+                                for codec in Codec::ALL {
+                                    ui.selectable_value(
+                                        &mut self.compress_codec,
+                                        codec,
+                                        codec.label(),
+                                    );
+                                }
+                            });
+                        let (low, high) = self.compress_codec.levels();
+                        ui.label(format!("Level ({}-{}):", low, high));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.compress_level_input)
+                                .desired_width(30.0),
+                        );
+                        ui.checkbox(&mut self.compress_keep, "Keep original");
+                        if ui.button("Compress").clicked() {
+                            let codec = self.compress_codec;
+                            self.start_compress(&file.path, codec);
+                        }
+                        if compress::decompressed_path(Path::new(&file.path)).is_some()
+                            && ui.button("Decompress").clicked()
+                        {
+                            self.start_decompress(&file.path);
+                        }
+                    });
+                }
             }
 
             ui.separator();
@@ -961,12 +2934,15 @@ impl eframe::App for FileManagerApp {
                 ui.text_edit_singleline(&mut self.new_name_input);
 
                 if ui.button("Create File").clicked() {
-                    let path = Path::new(&self.current_dir)
+                    let path = Path::new(&self.tab().dir)
                         .join(&self.new_name_input)
                         .to_string_lossy()
                         .to_string();
                     match crate::actions::create_file_noninteractive(&path) {
                         Ok(_) => {
+                            self.journal.record(Operation::CreateFile {
+                                path: PathBuf::from(&path),
+                            });
                             self.status = format!("Created file {}", self.new_name_input);
                             self.status_is_error = false;
                         }
@@ -976,17 +2952,20 @@ impl eframe::App for FileManagerApp {
                             self.status_is_error = true;
                         }
                     }
-                    self.files = read_files(&self.current_dir);
+                    self.refresh();
                     self.new_name_input.clear();
                 }
 
                 if ui.button("Create Directory").clicked() {
-                    let path = Path::new(&self.current_dir)
+                    let path = Path::new(&self.tab().dir)
                         .join(&self.new_name_input)
                         .to_string_lossy()
                         .to_string();
                     match crate::actions::create_directory_noninteractive(&path) {
                         Ok(_) => {
+                            self.journal.record(Operation::CreateDir {
+                                path: PathBuf::from(&path),
+                            });
                             self.status = format!("Created directory {}", self.new_name_input);
                             self.status_is_error = false;
                         }
@@ -996,13 +2975,13 @@ impl eframe::App for FileManagerApp {
                             self.status_is_error = true;
                         }
                     }
-                    self.files = read_files(&self.current_dir);
+                    self.refresh();
                     self.new_name_input.clear();
                 }
 
                 if ui.button("Delete Directory").clicked() {
                     // open confirmation for directory delete
-                    let path = Path::new(&self.current_dir)
+                    let path = Path::new(&self.tab().dir)
                         .join(&self.new_name_input)
                         .to_string_lossy()
                         .to_string();
@@ -1020,6 +2999,11 @@ impl eframe::App for FileManagerApp {
                 ui.text_edit_singleline(&mut self.batch_input);
 
                 if ui.button("Batch Delete").clicked() {
+                    // no names typed in: delete the selection
+                    if self.batch_input.trim().is_empty() {
+                        self.delete_selection();
+                        return;
+                    }
                     // prepare batch confirmation
                     let names: Vec<String> = self
                         .batch_input
@@ -1027,7 +3011,7 @@ impl eframe::App for FileManagerApp {
                         .map(|s| s.trim())
                         .filter(|s| !s.is_empty())
                         .map(|n| {
-                            Path::new(&self.current_dir)
+                            Path::new(&self.tab().dir)
                                 .join(n)
                                 .to_string_lossy()
                                 .to_string()
@@ -1041,7 +3025,7 @@ impl eframe::App for FileManagerApp {
             // Refresh and Exit
             ui.horizontal(|ui| {
                 if ui.button("Refresh").clicked() {
-                    self.files = read_files(&self.current_dir);
+                    self.refresh();
                     self.status = "Refreshed file list".to_string();
                     self.status_is_error = false;
                 }
@@ -1071,12 +3055,43 @@ impl eframe::App for FileManagerApp {
                         self.confirm_delete_target
                     ));
                     ui.horizontal(|ui| {
-                        if ui.button("Yes").clicked() {
+                        if ui.button("Move to Trash").clicked() {
+                            match crate::actions::trash_noninteractive(&self.confirm_delete_target)
+                            {
+                                Ok(item) => {
+                                    self.journal.record(Operation::Trash {
+                                        original: item.original_path,
+                                        trash_name: item.name,
+                                    });
+                                    self.status =
+                                        format!("Moved {} to trash", self.confirm_delete_target);
+                                    self.status_is_error = false
+                                }
+                                Err(e) => {
+                                    self.status = format!(
+                                        "Error moving {} to trash: {}",
+                                        self.confirm_delete_target, e
+                                    );
+                                    self.status_is_error = true
+                                }
+                            }
+                            self.refresh();
+                            self.tab_mut().clear_selection();
+                            self.confirm_delete_open = false;
+                            self.confirm_delete_target.clear();
+                        }
+                        if ui.button("Delete Permanently").clicked() {
                             if self.confirm_delete_is_dir {
                                 match crate::actions::delete_directory_noninteractive(
                                     &self.confirm_delete_target,
                                 ) {
                                     Ok(_) => {
+                                        self.journal.record(Operation::Irreversible {
+                                            description: format!(
+                                                "permanent delete of {}",
+                                                self.confirm_delete_target
+                                            ),
+                                        });
                                         self.status = format!(
                                             "Deleted directory {}",
                                             self.confirm_delete_target
@@ -1096,6 +3111,12 @@ impl eframe::App for FileManagerApp {
                                     &self.confirm_delete_target,
                                 ) {
                                     Ok(_) => {
+                                        self.journal.record(Operation::Irreversible {
+                                            description: format!(
+                                                "permanent delete of {}",
+                                                self.confirm_delete_target
+                                            ),
+                                        });
                                         self.status =
                                             format!("Deleted {}", self.confirm_delete_target);
                                         self.status_is_error = false
@@ -1109,12 +3130,12 @@ impl eframe::App for FileManagerApp {
                                     }
                                 }
                             }
-                            self.files = read_files(&self.current_dir);
-                            self.selected = None;
+                            self.refresh();
+                            self.tab_mut().clear_selection();
                             self.confirm_delete_open = false;
                             self.confirm_delete_target.clear();
                         }
-                        if ui.button("No").clicked() {
+                        if ui.button("Cancel").clicked() {
                             self.confirm_delete_open = false;
                             self.confirm_delete_target.clear();
                         }
@@ -1131,10 +3152,54 @@ impl eframe::App for FileManagerApp {
                         self.confirm_batch_targets.len()
                     ));
                     ui.horizontal(|ui| {
-                        if ui.button("Yes").clicked() {
-                            let results = crate::actions::batch_delete_noninteractive(
+                        if ui.button("Move to Trash").clicked() {
+                            let results = crate::actions::batch_trash_noninteractive(
                                 &self.confirm_batch_targets,
                             );
+                            let mut ops = Vec::new();
+                            for (i, res) in results.iter().enumerate() {
+                                match res {
+                                    Ok(item) => {
+                                        ops.push(Operation::Trash {
+                                            original: item.original_path.clone(),
+                                            trash_name: item.name.clone(),
+                                        });
+                                        self.status = format!(
+                                            "Moved {} to trash",
+                                            self.confirm_batch_targets[i]
+                                        );
+                                        self.status_is_error = false
+                                    }
+                                    Err(e) => {
+                                        self.status = format!(
+                                            "Error moving {} to trash: {}",
+                                            self.confirm_batch_targets[i], e
+                                        );
+                                        self.status_is_error = true
+                                    }
+                                }
+                            }
+                            self.journal.record(Operation::Batch {
+                                label: "batch trash".to_string(),
+                                ops,
+                            });
+                            self.refresh();
+                            self.confirm_batch_open = false;
+                            self.confirm_batch_targets.clear();
+                        }
+                        if ui.button("Delete Permanently").clicked() {
+                            // a selection can hold directories as well as files
+                            let results: Vec<_> = self
+                                .confirm_batch_targets
+                                .iter()
+                                .map(|target| {
+                                    if Path::new(target).is_dir() {
+                                        crate::actions::delete_directory_noninteractive(target)
+                                    } else {
+                                        crate::actions::delete_file_noninteractive(target)
+                                    }
+                                })
+                                .collect();
                             for (i, res) in results.iter().enumerate() {
                                 match res {
                                     Ok(_) => {
@@ -1151,11 +3216,17 @@ impl eframe::App for FileManagerApp {
                                     }
                                 }
                             }
-                            self.files = read_files(&self.current_dir);
+                            self.journal.record(Operation::Irreversible {
+                                description: format!(
+                                    "permanent delete of {} files",
+                                    self.confirm_batch_targets.len()
+                                ),
+                            });
+                            self.refresh();
                             self.confirm_batch_open = false;
                             self.confirm_batch_targets.clear();
                         }
-                        if ui.button("No").clicked() {
+                        if ui.button("Cancel").clicked() {
                             self.confirm_batch_open = false;
                             self.confirm_batch_targets.clear();
                         }
@@ -1163,13 +3234,48 @@ impl eframe::App for FileManagerApp {
                 });
         }
 
+        if self.confirm_empty_trash_open {
+            egui::Window::new("Confirm Empty Trash")
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "Permanently delete all {} items in the trash?",
+                        self.trash_items.len()
+                    ));
+                    ui.horizontal(|ui| {
+                        if ui.button("Yes").clicked() {
+                            match crate::actions::trash_empty_noninteractive() {
+                                Ok(n) => {
+                                    self.journal.record(Operation::Irreversible {
+                                        description: "empty trash".to_string(),
+                                    });
+                                    self.status = format!("Emptied trash ({} items)", n);
+                                    self.status_is_error = false
+                                }
+                                Err(e) => {
+                                    self.status = format!("Error emptying trash: {}", e);
+                                    self.status_is_error = true
+                                }
+                            }
+                            self.refresh_trash();
+                            self.confirm_empty_trash_open = false;
+                        }
+                        if ui.button("No").clicked() {
+                            self.confirm_empty_trash_open = false;
+                        }
+                    });
+                });
+        }
+
         if self.confirm_secure_open {
             egui::Window::new("Confirm Secure Delete")
                 .collapsible(false)
                 .show(ctx, |ui| {
                     ui.label(format!(
-                        "Securely delete '{}' ? This will overwrite the file.",
-                        self.confirm_secure_target
+                        "Securely delete '{}' ? This will overwrite it ({}) and cannot be undone. \
+                         Directories are shredded recursively.",
+                        self.confirm_secure_target,
+                        self.shred_scheme.label()
                     ));
                     ui.horizontal(|ui| {
                         if ui.button("Yes").clicked() {
@@ -1180,10 +3286,18 @@ impl eframe::App for FileManagerApp {
                                 let (tx, rx) = mpsc::channel();
                                 let cancel = Arc::new(AtomicBool::new(false));
                                 let target = self.confirm_secure_target.clone();
+                                self.journal.record(Operation::Irreversible {
+                                    description: format!("secure delete of {}", target),
+                                });
+                                let options = ShredOptions {
+                                    scheme: self.shred_scheme,
+                                    verify: self.shred_verify,
+                                };
                                 let cancel_clone = cancel.clone();
                                 std::thread::spawn(move || {
                                     crate::actions::secure_delete_progress(
                                         &target,
+                                        options,
                                         tx,
                                         cancel_clone,
                                     );
@@ -1205,6 +3319,14 @@ impl eframe::App for FileManagerApp {
                 });
         }
 
+        if let Some(rx) = &self.duplicates_rx
+            && let Ok(groups) = rx.try_recv()
+        {
+            self.duplicate_keep = vec![0; groups.len()];
+            self.duplicate_groups = groups;
+            self.duplicates_rx = None;
+        }
+
         // Poll worker receiver for messages
         if let Some(rx) = &self.worker_rx {
             // pull all pending messages
@@ -1220,6 +3342,17 @@ impl eframe::App for FileManagerApp {
                         self.is_busy = false;
                         self.worker_rx = None;
                         self.worker_cancel = None;
+                        // background jobs report success with a final "Finished: ..." message
+                        if let Some(op) = self.pending_op.take()
+                            && self
+                                .progress_messages
+                                .last()
+                                .is_some_and(|m| m.starts_with("Finished"))
+                        {
+                            self.journal.record(op);
+                        }
+                        // the job may have added or removed entries
+                        self.refresh();
                     }
                     Err(std::sync::mpsc::TryRecvError::Empty) => {}
                     Ok(msg) => {
@@ -1237,13 +3370,12 @@ impl eframe::App for FileManagerApp {
                 if ui.button("Show Details").clicked() {
                     // toggle a large output — here we just leave messages visible via label
                 }
-                if self.is_busy {
-                    if ui.button("Cancel").clicked() {
-                        if let Some(cancel) = &self.worker_cancel {
-                            cancel.store(true, Ordering::SeqCst);
-                            self.status = "Cancellation requested".to_string();
-                        }
-                    }
+                if self.is_busy
+                    && ui.button("Cancel").clicked()
+                    && let Some(cancel) = &self.worker_cancel
+                {
+                    cancel.store(true, Ordering::SeqCst);
+                    self.status = "Cancellation requested".to_string();
                 }
             });
             for m in self.progress_messages.iter().rev().take(8) {
@@ -1253,6 +3385,30 @@ impl eframe::App for FileManagerApp {
     }
 }
 
+/// Journal entry for a finished copy: reversible only if it created a new path.
+fn copy_operation(src: &str, report: &CopyReport) -> Operation {
+    if report.target_is_new && report.files_copied + report.dirs_created > 0 {
+        Operation::Copy {
+            src: PathBuf::from(src),
+            created: report.target.clone(),
+        }
+    } else {
+        Operation::Irreversible {
+            description: format!("copy over {}", report.target.display()),
+        }
+    }
+}
+
+/// `dir` made absolute and normalized, so its ancestors can be listed as
+/// breadcrumbs and a ".." in it never shows up in the path bar.
+fn absolute_dir(dir: &str) -> String {
+    std::path::absolute(dir)
+        .map(|p| navigation::normalize(&p))
+        .unwrap_or_else(|_| PathBuf::from(dir))
+        .to_string_lossy()
+        .to_string()
+}
+
 fn read_files(dir: &str) -> Vec<FileEntry> {
     let mut files = Vec::new();
     if let Ok(entries) = fs::read_dir(dir) {
@@ -1271,6 +3427,7 @@ fn read_files(dir: &str) -> Vec<FileEntry> {
             let is_dir = metadata.is_dir();
             let name = entry.file_name().to_string_lossy().to_string();
 
+            #[cfg_attr(not(target_os = "windows"), allow(unused_mut))]
             let mut hidden = name.starts_with('.');
             #[cfg(target_os = "windows")]
             {
@@ -1294,12 +3451,43 @@ fn read_files(dir: &str) -> Vec<FileEntry> {
     files
 }
 
-fn sort_files(files: &mut Vec<FileEntry>, mode: SortMode) {
+/// Entries of `files` that are missing from `other` or differ from the entry of
+/// the same name there. Directories are only checked for presence.
+fn compare_listings(files: &[FileEntry], other: &[FileEntry]) -> HashMap<String, Difference> {
+    let other: HashMap<&str, &FileEntry> = other.iter().map(|f| (f.display.as_str(), f)).collect();
+    let mtime = |f: &FileEntry| f.modified.parse::<u64>().unwrap_or(0);
+    files
+        .iter()
+        .filter_map(|f| {
+            let difference = match other.get(f.display.as_str()) {
+                None => Difference::Missing,
+                Some(o) if f.is_dir || o.is_dir => return None,
+                Some(o) if f.size != o.size => Difference::Size,
+                Some(o) if mtime(f) > mtime(o) => Difference::Newer,
+                Some(o) if mtime(f) < mtime(o) => Difference::Older,
+                Some(_) => return None,
+            };
+            Some((f.display.clone(), difference))
+        })
+        .collect()
+}
+
+/// New name for entry `n` of a multi-selection rename; see `rename_selection`.
+fn rename_pattern(pattern: &str, name: &str, n: usize) -> String {
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+    pattern
+        .replace("{name}", stem)
+        .replace("{ext}", &ext)
+        .replace("{n}", &n.to_string())
+}
+
+fn sort_files(files: &mut [FileEntry], mode: SortMode) {
     match mode {
-        SortMode::Name => {
-            files.sort_by(|a, b| a.display.to_lowercase().cmp(&b.display.to_lowercase()))
-        }
-        SortMode::Size => files.sort_by(|a, b| a.size.cmp(&b.size)),
+        SortMode::Name => files.sort_by_key(|a| a.display.to_lowercase()),
+        SortMode::Size => files.sort_by_key(|a| a.size),
         SortMode::Date => files.sort_by(|a, b| a.modified.cmp(&b.modified)),
     }
 }
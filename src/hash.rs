@@ -0,0 +1,524 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Read size used when hashing; keeps memory flat regardless of file size.
+const HASH_CHUNK: usize = 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashAlgo {
+    Sha256,
+    Blake3,
+    Sha1,
+    Sha512,
+    Md5,
+    Crc32,
+}
+
+impl HashAlgo {
+    pub fn parse(s: &str) -> Option<HashAlgo> {
+        match s.trim().to_ascii_lowercase().replace('-', "").as_str() {
+            "sha256" => Some(HashAlgo::Sha256),
+            "blake3" | "b3" => Some(HashAlgo::Blake3),
+            "sha1" => Some(HashAlgo::Sha1),
+            "sha512" => Some(HashAlgo::Sha512),
+            "md5" => Some(HashAlgo::Md5),
+            "crc32" => Some(HashAlgo::Crc32),
+            _ => None,
+        }
+    }
+
+    /// Upper-case name, as printed before a digest and used by BSD-style checksum lines.
+    pub fn label(self) -> &'static str {
+        match self {
+            HashAlgo::Sha256 => "SHA256",
+            HashAlgo::Blake3 => "BLAKE3",
+            HashAlgo::Sha1 => "SHA1",
+            HashAlgo::Sha512 => "SHA512",
+            HashAlgo::Md5 => "MD5",
+            HashAlgo::Crc32 => "CRC32",
+        }
+    }
+
+    /// Length of the hex digest.
+    fn hex_len(self) -> usize {
+        match self {
+            HashAlgo::Sha256 | HashAlgo::Blake3 => 64,
+            HashAlgo::Sha1 => 40,
+            HashAlgo::Sha512 => 128,
+            HashAlgo::Md5 => 32,
+            HashAlgo::Crc32 => 8,
+        }
+    }
+
+    /// Guess the algorithm from a checksum file name such as `SHA256SUMS`,
+    /// `b3sums.txt`, `image.md5` or `image.iso.sha1sum`.
+    fn from_checksum_file_name(path: &Path) -> Option<HashAlgo> {
+        let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+        name.split('.')
+            .rev()
+            .find_map(|part| HashAlgo::parse(part.trim_end_matches("sums").trim_end_matches("sum")))
+    }
+
+    /// Guess from the digest length alone. 64 hex digits could be SHA-256
+    /// or BLAKE3, so that length gives no answer.
+    fn from_hex_len(len: usize) -> Option<HashAlgo> {
+        match len {
+            40 => Some(HashAlgo::Sha1),
+            128 => Some(HashAlgo::Sha512),
+            32 => Some(HashAlgo::Md5),
+            8 => Some(HashAlgo::Crc32),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HashAlgo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+enum Hasher {
+    Sha256(sha2::Sha256),
+    Blake3(Box<blake3::Hasher>),
+    Sha1(sha1::Sha1),
+    Sha512(sha2::Sha512),
+    Md5(md5::Md5),
+    Crc32(crc32fast::Hasher),
+}
+
+impl Hasher {
+    fn new(algo: HashAlgo) -> Hasher {
+        use sha2::Digest;
+        match algo {
+            HashAlgo::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            HashAlgo::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgo::Sha1 => Hasher::Sha1(sha1::Sha1::new()),
+            HashAlgo::Sha512 => Hasher::Sha512(sha2::Sha512::new()),
+            HashAlgo::Md5 => Hasher::Md5(md5::Md5::new()),
+            HashAlgo::Crc32 => Hasher::Crc32(crc32fast::Hasher::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        use sha2::Digest;
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+            Hasher::Sha1(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+            Hasher::Md5(h) => h.update(data),
+            Hasher::Crc32(h) => h.update(data),
+        }
+    }
+
+    fn finalize_hex(self) -> String {
+        use sha2::Digest;
+        match self {
+            Hasher::Sha256(h) => format!("{:x}", h.finalize()),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+            Hasher::Sha1(h) => format!("{:x}", h.finalize()),
+            Hasher::Sha512(h) => format!("{:x}", h.finalize()),
+            Hasher::Md5(h) => format!("{:x}", h.finalize()),
+            Hasher::Crc32(h) => format!("{:08x}", h.finalize()),
+        }
+    }
+}
+
+/// Writer that hashes everything written to it, for data that arrives as a
+/// stream rather than a file (such as an archive entry).
+pub struct DigestWriter {
+    hasher: Hasher,
+}
+
+impl DigestWriter {
+    pub fn new(algo: HashAlgo) -> DigestWriter {
+        DigestWriter {
+            hasher: Hasher::new(algo),
+        }
+    }
+
+    pub fn finalize_hex(self) -> String {
+        self.hasher.finalize_hex()
+    }
+}
+
+impl Write for DigestWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hasher.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hash a file in fixed-size chunks and return the lower-case hex digest.
+///
+/// `on_bytes` receives the running total of bytes hashed after each chunk.
+/// Returns `ErrorKind::Interrupted` if `cancel` is set.
+pub fn hash_file(
+    path: &Path,
+    algo: HashAlgo,
+    on_bytes: &mut dyn FnMut(u64),
+    cancel: &AtomicBool,
+) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Hasher::new(algo);
+    let mut buf = vec![0u8; HASH_CHUNK];
+    let mut done = 0u64;
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "hashing canceled",
+            ));
+        }
+        let n = match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buf[..n]);
+        done += n as u64;
+        on_bytes(done);
+    }
+    Ok(hasher.finalize_hex())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyStatus {
+    Ok,
+    Failed,
+    Missing,
+    /// The file exists but could not be read.
+    Unreadable,
+    /// A 64-digit digest with nothing to say whether it is SHA-256 or BLAKE3.
+    Ambiguous,
+}
+
+impl fmt::Display for VerifyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            VerifyStatus::Ok => "OK",
+            VerifyStatus::Failed => "FAILED",
+            VerifyStatus::Missing => "MISSING",
+            VerifyStatus::Unreadable => "UNREADABLE",
+            VerifyStatus::Ambiguous => "AMBIGUOUS ALGORITHM",
+        })
+    }
+}
+
+/// Outcome for one line of a checksum file.
+#[derive(Clone, Debug)]
+pub struct VerifyResult {
+    /// Path as written in the checksum file.
+    pub path: String,
+    pub status: VerifyStatus,
+    /// Why the file could not be checked, for `Unreadable` and `Ambiguous`.
+    pub detail: Option<String>,
+}
+
+impl fmt::Display for VerifyResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.status)?;
+        if let Some(detail) = &self.detail {
+            write!(f, " ({})", detail)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub results: Vec<VerifyResult>,
+    /// Non-empty, non-comment lines that could not be parsed.
+    pub malformed: usize,
+}
+
+impl VerifyReport {
+    pub fn count(&self, status: VerifyStatus) -> usize {
+        self.results.iter().filter(|r| r.status == status).count()
+    }
+
+    pub fn all_ok(&self) -> bool {
+        self.malformed == 0 && self.results.iter().all(|r| r.status == VerifyStatus::Ok)
+    }
+
+    pub fn summary(&self) -> String {
+        let mut s = format!(
+            "{} OK, {} FAILED, {} MISSING",
+            self.count(VerifyStatus::Ok),
+            self.count(VerifyStatus::Failed),
+            self.count(VerifyStatus::Missing)
+        );
+        let unreadable = self.count(VerifyStatus::Unreadable);
+        if unreadable > 0 {
+            s.push_str(&format!(", {} UNREADABLE", unreadable));
+        }
+        let ambiguous = self.count(VerifyStatus::Ambiguous);
+        if ambiguous > 0 {
+            s.push_str(&format!(
+                ", {} with an ambiguous algorithm (give it explicitly)",
+                ambiguous
+            ));
+        }
+        if self.malformed > 0 {
+            s.push_str(&format!(", {} improperly formatted lines", self.malformed));
+        }
+        s
+    }
+}
+
+/// One parsed checksum line.
+struct ChecksumLine {
+    algo: Option<HashAlgo>,
+    digest: String,
+    path: String,
+}
+
+/// Parse a `sha256sum`/`b3sum` line (`<hex>  <path>` or `<hex> *<path>`) or a
+/// BSD-style tagged line (`SHA256 (<path>) = <hex>`).
+fn parse_checksum_line(line: &str) -> Option<ChecksumLine> {
+    // coreutils prefixes lines whose file name contains `\` or a newline with `\`
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let unescape = |p: &str| {
+        if !escaped {
+            return p.to_string();
+        }
+        let mut out = String::with_capacity(p.len());
+        let mut chars = p.chars();
+        while let Some(c) = chars.next() {
+            match (c, chars.clone().next()) {
+                ('\\', Some('n')) => {
+                    out.push('\n');
+                    chars.next();
+                }
+                ('\\', Some('\\')) => {
+                    out.push('\\');
+                    chars.next();
+                }
+                _ => out.push(c),
+            }
+        }
+        out
+    };
+
+    if let Some((tag, rest)) = line.split_once(" (")
+        && let Some((path, digest)) = rest.rsplit_once(") = ")
+    {
+        let algo = HashAlgo::parse(tag)?;
+        let digest = digest.trim();
+        if digest.len() != algo.hex_len() || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        return Some(ChecksumLine {
+            algo: Some(algo),
+            digest: digest.to_ascii_lowercase(),
+            path: unescape(path),
+        });
+    }
+
+    let (digest, rest) = line.split_once(' ')?;
+    let path = rest.strip_prefix(' ').or_else(|| rest.strip_prefix('*'))?;
+    if digest.is_empty() || path.is_empty() || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(ChecksumLine {
+        algo: None,
+        digest: digest.to_ascii_lowercase(),
+        path: unescape(path),
+    })
+}
+
+/// Check every file listed in `sum_file` against its recorded digest.
+///
+/// Relative paths are resolved against the checksum file's directory, so a
+/// sum file can be checked from anywhere; `sha256sum -c` resolves them
+/// against the current directory instead. The algorithm is taken from
+/// `algo`, then from a BSD-style tag, then from the checksum file's name
+/// (`SHA1SUMS`, `*.md5`, `*.b3`...), then from the digest length; a
+/// 64-digit digest that none of these settle is reported as ambiguous.
+/// `on_result` is called as each line is checked.
+pub fn verify_checksum_file(
+    sum_file: &Path,
+    algo: Option<HashAlgo>,
+    on_result: &mut dyn FnMut(&VerifyResult),
+    cancel: &AtomicBool,
+) -> io::Result<VerifyReport> {
+    let contents = fs::read_to_string(sum_file)?;
+    let base = sum_file.parent().unwrap_or(Path::new("")).to_path_buf();
+    let file_algo = HashAlgo::from_checksum_file_name(sum_file);
+    let mut report = VerifyReport::default();
+
+    for line in contents.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some(entry) = parse_checksum_line(line) else {
+            report.malformed += 1;
+            continue;
+        };
+        let line_algo = algo
+            .or(entry.algo)
+            .or(file_algo)
+            .or_else(|| HashAlgo::from_hex_len(entry.digest.len()));
+        let (status, detail) = match line_algo {
+            Some(a) if a.hex_len() == entry.digest.len() => {
+                let target: PathBuf = base.join(&entry.path);
+                match hash_file(&target, a, &mut |_| {}, cancel) {
+                    Ok(actual) if actual == entry.digest => (VerifyStatus::Ok, None),
+                    Ok(_) => (VerifyStatus::Failed, None),
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => return Err(e),
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => (VerifyStatus::Missing, None),
+                    Err(e) => (VerifyStatus::Unreadable, Some(e.to_string())),
+                }
+            }
+            None if entry.digest.len() == HashAlgo::Sha256.hex_len() => (
+                VerifyStatus::Ambiguous,
+                Some("SHA-256 or BLAKE3".to_string()),
+            ),
+            _ => {
+                report.malformed += 1;
+                continue;
+            }
+        };
+        let result = VerifyResult {
+            path: entry.path,
+            status,
+            detail,
+        };
+        on_result(&result);
+        report.results.push(result);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> (Option<HashAlgo>, String, String) {
+        let l = parse_checksum_line(line).unwrap();
+        (l.algo, l.digest, l.path)
+    }
+
+    #[test]
+    fn checksum_lines_parse() {
+        let d = "ab".repeat(32);
+        assert_eq!(
+            parse(&format!("{}  a file.txt", d)),
+            (None, d.clone(), "a file.txt".to_string())
+        );
+        // `*` marks binary mode.
+        assert_eq!(parse(&format!("{} *bin", d)).2, "bin");
+        assert_eq!(parse(&format!("{}  *star", d)).2, "*star");
+        assert_eq!(
+            parse(&format!("\\{}  dir\\\\name\\nline", d)).2,
+            "dir\\name\nline"
+        );
+        assert_eq!(
+            parse(&format!("SHA256 (x (1).txt) = {}", d.to_uppercase())),
+            (Some(HashAlgo::Sha256), d.clone(), "x (1).txt".to_string())
+        );
+        assert!(parse_checksum_line(&format!("MD5 (x) = {}", d)).is_none());
+        assert!(parse_checksum_line(&format!("{}x  a", d)).is_none());
+        assert!(parse_checksum_line(&format!("{} a", d)).is_none());
+    }
+
+    #[test]
+    fn algorithm_comes_from_the_file_name() {
+        let name = |n| HashAlgo::from_checksum_file_name(Path::new(n));
+        assert_eq!(name("SHA256SUMS"), Some(HashAlgo::Sha256));
+        assert_eq!(name("B3SUMS"), Some(HashAlgo::Blake3));
+        assert_eq!(name("b3sums.txt"), Some(HashAlgo::Blake3));
+        assert_eq!(name("image.iso.b3"), Some(HashAlgo::Blake3));
+        assert_eq!(name("image.md5sum"), Some(HashAlgo::Md5));
+        assert_eq!(name("checksums.txt"), None);
+        assert_eq!(HashAlgo::from_hex_len(64), None);
+        assert_eq!(HashAlgo::from_hex_len(40), Some(HashAlgo::Sha1));
+    }
+
+    fn digest(data: &[u8], algo: HashAlgo) -> String {
+        let mut w = DigestWriter::new(algo);
+        w.write_all(data).unwrap();
+        w.finalize_hex()
+    }
+
+    fn verify(sum_file: &Path, algo: Option<HashAlgo>) -> Vec<(String, VerifyStatus)> {
+        verify_checksum_file(sum_file, algo, &mut |_| {}, &AtomicBool::new(false))
+            .unwrap()
+            .results
+            .into_iter()
+            .map(|r| (r.path, r.status))
+            .collect()
+    }
+
+    #[test]
+    fn files_are_ok_failed_missing_or_unreadable() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("good"), "good").unwrap();
+        fs::write(tmp.path().join("bad"), "changed").unwrap();
+        fs::create_dir(tmp.path().join("dir")).unwrap();
+        let sums = tmp.path().join("SHA256SUMS");
+        let sha = |d: &str| digest(d.as_bytes(), HashAlgo::Sha256);
+        fs::write(
+            &sums,
+            format!(
+                "# comment\n{}  good\n{} *bad\n{}  gone\n{}  dir\nnot a line\n",
+                sha("good"),
+                sha("bad"),
+                sha("gone"),
+                sha("dir")
+            ),
+        )
+        .unwrap();
+        let results = verify(&sums, None);
+        assert_eq!(
+            results,
+            [
+                ("good".to_string(), VerifyStatus::Ok),
+                ("bad".to_string(), VerifyStatus::Failed),
+                ("gone".to_string(), VerifyStatus::Missing),
+                ("dir".to_string(), VerifyStatus::Unreadable),
+            ]
+        );
+        let report =
+            verify_checksum_file(&sums, None, &mut |_| {}, &AtomicBool::new(false)).unwrap();
+        assert_eq!(report.malformed, 1);
+        assert!(!report.all_ok());
+    }
+
+    #[test]
+    fn blake3_sums_need_a_name_or_an_algorithm() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("a"), "alpha").unwrap();
+        let line = format!("{}  a\n", digest(b"alpha", HashAlgo::Blake3));
+        let ok = vec![("a".to_string(), VerifyStatus::Ok)];
+        for name in ["B3SUMS", "files.b3"] {
+            fs::write(tmp.path().join(name), &line).unwrap();
+            assert_eq!(verify(&tmp.path().join(name), None), ok, "{}", name);
+        }
+        let plain = tmp.path().join("checksums.txt");
+        fs::write(&plain, &line).unwrap();
+        assert_eq!(
+            verify(&plain, None),
+            [("a".to_string(), VerifyStatus::Ambiguous)]
+        );
+        assert_eq!(verify(&plain, Some(HashAlgo::Blake3)), ok);
+        // Other lengths still tell the algorithm.
+        fs::write(&plain, format!("{}  a\n", digest(b"alpha", HashAlgo::Md5))).unwrap();
+        assert_eq!(verify(&plain, None), ok);
+    }
+}
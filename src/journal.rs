@@ -0,0 +1,428 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use crate::copy::{self, CopyOptions};
+use crate::trash;
+
+/// A completed file operation, with enough information to reverse it.
+#[derive(Clone, Debug)]
+pub enum Operation {
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// `created` did not exist before the copy, so removing it undoes it.
+    Copy {
+        src: PathBuf,
+        created: PathBuf,
+    },
+    CreateFile {
+        path: PathBuf,
+    },
+    CreateDir {
+        path: PathBuf,
+    },
+    /// Moved to the trash under `trash_name`.
+    Trash {
+        original: PathBuf,
+        trash_name: String,
+    },
+    /// Restored from the trash to `original`.
+    Restore {
+        original: PathBuf,
+        trash_name: String,
+    },
+    /// Several operations applied as one action; undone in reverse order.
+    Batch {
+        label: String,
+        ops: Vec<Operation>,
+    },
+    /// Recorded for history only (secure delete, permanent delete, overwriting
+    /// copies). Never offered for undo.
+    Irreversible {
+        description: String,
+    },
+}
+
+impl Operation {
+    pub fn is_reversible(&self) -> bool {
+        match self {
+            Operation::Irreversible { .. } => false,
+            Operation::Batch { ops, .. } => ops.iter().all(Operation::is_reversible),
+            _ => true,
+        }
+    }
+
+    /// Short description for menus and the status bar.
+    pub fn describe(&self) -> String {
+        match self {
+            Operation::Rename { from, to } => {
+                format!("rename {} -> {}", display_name(from), display_name(to))
+            }
+            Operation::Move { from, to } => {
+                format!("move {} -> {}", display_name(from), to.display())
+            }
+            Operation::Copy { created, .. } => format!("copy to {}", created.display()),
+            Operation::CreateFile { path } => format!("create file {}", display_name(path)),
+            Operation::CreateDir { path } => format!("create directory {}", display_name(path)),
+            Operation::Trash { original, .. } => format!("trash {}", display_name(original)),
+            Operation::Restore { original, .. } => format!("restore {}", display_name(original)),
+            Operation::Batch { label, ops } => format!("{} ({} items)", label, ops.len()),
+            Operation::Irreversible { description } => description.clone(),
+        }
+    }
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Undo/redo history of file operations.
+///
+/// Irreversible operations are accepted by [`Journal::record`] but dropped from
+/// the undo history, so they are never offered for undo.
+#[derive(Default)]
+pub struct Journal {
+    undo_stack: Vec<Operation>,
+    redo_stack: Vec<Operation>,
+}
+
+impl Journal {
+    /// Record a completed operation. Any redo history is discarded.
+    pub fn record(&mut self, op: Operation) {
+        self.redo_stack.clear();
+        if op.is_reversible() {
+            self.undo_stack.push(op);
+        }
+    }
+
+    pub fn next_undo(&self) -> Option<&Operation> {
+        self.undo_stack.last()
+    }
+
+    pub fn next_redo(&self) -> Option<&Operation> {
+        self.redo_stack.last()
+    }
+
+    /// Reverse the most recent operation. On failure the operation is dropped,
+    /// since the filesystem no longer matches what was recorded; the items of
+    /// a batch that were undone before it failed can still be redone.
+    pub fn undo(&mut self) -> Result<String, String> {
+        let op = self.undo_stack.pop().ok_or("Nothing to undo")?;
+        let desc = op.describe();
+        match undo_op(op) {
+            Ok(redo) => {
+                self.redo_stack.push(redo);
+                Ok(format!("Undid {}", desc))
+            }
+            Err(failed) => {
+                self.redo_stack.extend(failed.done);
+                Err(format!("Undo {} failed: {}", desc, failed.message))
+            }
+        }
+    }
+
+    /// Re-apply the most recently undone operation. Like [`Journal::undo`],
+    /// the items of a batch that were redone before a failure can be undone.
+    pub fn redo(&mut self) -> Result<String, String> {
+        let op = self.redo_stack.pop().ok_or("Nothing to redo")?;
+        let desc = op.describe();
+        match redo_op(op) {
+            Ok(undo) => {
+                self.undo_stack.push(undo);
+                Ok(format!("Redid {}", desc))
+            }
+            Err(failed) => {
+                self.undo_stack.extend(failed.done);
+                Err(format!("Redo {} failed: {}", desc, failed.message))
+            }
+        }
+    }
+}
+
+/// Why undoing or redoing an operation stopped.
+struct Failed {
+    message: String,
+    /// The part of a batch that was handled before the failure, as the
+    /// operation for the other stack.
+    done: Option<Operation>,
+}
+
+impl From<String> for Failed {
+    fn from(message: String) -> Self {
+        Failed {
+            message,
+            done: None,
+        }
+    }
+}
+
+/// Apply `step` to each of a batch's `ops` in turn. On failure the message
+/// names the failing item and how many were handled, and those are kept.
+fn batch_step(
+    label: String,
+    ops: impl ExactSizeIterator<Item = Operation>,
+    verb: &str,
+    step: fn(Operation) -> Result<Operation, Failed>,
+) -> Result<Vec<Operation>, Failed> {
+    let total = ops.len();
+    let mut done = Vec::with_capacity(total);
+    for op in ops {
+        let desc = op.describe();
+        match step(op) {
+            Ok(op) => done.push(op),
+            Err(failed) => {
+                // A nested batch may have got partway itself.
+                done.extend(failed.done);
+                let message = format!(
+                    "{}: {} ({} of {} items were {})",
+                    desc,
+                    failed.message,
+                    done.len(),
+                    total,
+                    verb
+                );
+                let done = (!done.is_empty()).then_some(Operation::Batch { label, ops: done });
+                return Err(Failed { message, done });
+            }
+        }
+    }
+    Ok(done)
+}
+
+/// Reverse `op`; returns the operation to push onto the redo stack.
+fn undo_op(op: Operation) -> Result<Operation, Failed> {
+    match op {
+        Operation::Rename { from, to } => {
+            move_back(&to, &from)?;
+            Ok(Operation::Rename { from, to })
+        }
+        Operation::Move { from, to } => {
+            move_back(&to, &from)?;
+            Ok(Operation::Move { from, to })
+        }
+        Operation::Copy { src, created } => {
+            // Trash rather than delete, in case the copy was edited since.
+            trash::move_to_trash(&created).map_err(|e| e.to_string())?;
+            Ok(Operation::Copy { src, created })
+        }
+        Operation::CreateFile { path } => {
+            let empty = fs::metadata(&path).map(|m| m.len() == 0).unwrap_or(false);
+            if empty {
+                fs::remove_file(&path).map_err(|e| e.to_string())?;
+            } else {
+                trash::move_to_trash(&path).map_err(|e| e.to_string())?;
+            }
+            Ok(Operation::CreateFile { path })
+        }
+        Operation::CreateDir { path } => {
+            if fs::remove_dir(&path).is_err() {
+                trash::move_to_trash(&path).map_err(|e| e.to_string())?;
+            }
+            Ok(Operation::CreateDir { path })
+        }
+        Operation::Trash {
+            original,
+            trash_name,
+        } => {
+            trash::restore(&trash_name).map_err(|e| e.to_string())?;
+            Ok(Operation::Trash {
+                original,
+                trash_name,
+            })
+        }
+        Operation::Restore { original, .. } => {
+            let item = trash::move_to_trash(&original).map_err(|e| e.to_string())?;
+            Ok(Operation::Restore {
+                original,
+                trash_name: item.name,
+            })
+        }
+        Operation::Batch { label, ops } => {
+            let mut done = batch_step(label.clone(), ops.into_iter().rev(), "undone", undo_op)
+                .map_err(|mut failed| {
+                    if let Some(Operation::Batch { ops, .. }) = &mut failed.done {
+                        ops.reverse();
+                    }
+                    failed
+                })?;
+            done.reverse();
+            Ok(Operation::Batch { label, ops: done })
+        }
+        Operation::Irreversible { description } => {
+            Err(format!("'{}' cannot be undone", description).into())
+        }
+    }
+}
+
+/// Re-apply `op`; returns the operation to push back onto the undo stack.
+fn redo_op(op: Operation) -> Result<Operation, Failed> {
+    match op {
+        Operation::Rename { from, to } => {
+            move_back(&from, &to)?;
+            Ok(Operation::Rename { from, to })
+        }
+        Operation::Move { from, to } => {
+            move_back(&from, &to)?;
+            Ok(Operation::Move { from, to })
+        }
+        Operation::Copy { src, created } => {
+            if fs::symlink_metadata(&created).is_ok() {
+                return Err(format!("'{}' already exists", created.display()).into());
+            }
+            copy::copy_recursive(&src, &created, &CopyOptions::default())
+                .map_err(|e| e.to_string())?;
+            Ok(Operation::Copy { src, created })
+        }
+        Operation::CreateFile { path } => {
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .map_err(|e| e.to_string())?;
+            Ok(Operation::CreateFile { path })
+        }
+        Operation::CreateDir { path } => {
+            fs::create_dir(&path).map_err(|e| e.to_string())?;
+            Ok(Operation::CreateDir { path })
+        }
+        Operation::Trash { original, .. } => {
+            let item = trash::move_to_trash(&original).map_err(|e| e.to_string())?;
+            Ok(Operation::Trash {
+                original,
+                trash_name: item.name,
+            })
+        }
+        Operation::Restore {
+            original,
+            trash_name,
+        } => {
+            trash::restore(&trash_name).map_err(|e| e.to_string())?;
+            Ok(Operation::Restore {
+                original,
+                trash_name,
+            })
+        }
+        Operation::Batch { label, ops } => {
+            let done = batch_step(label.clone(), ops.into_iter(), "redone", redo_op)?;
+            Ok(Operation::Batch { label, ops: done })
+        }
+        Operation::Irreversible { description } => {
+            Err(format!("'{}' cannot be redone", description).into())
+        }
+    }
+}
+
+/// Move `from` back to `to`, refusing to clobber anything now at `to`.
+fn move_back(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::symlink_metadata(to).is_ok() {
+        return Err(format!("'{}' already exists", to.display()));
+    }
+    let cancel = AtomicBool::new(false);
+    copy::move_path(from, to, &mut |_| {}, &cancel)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn rename(from: &Path, to: &Path) -> Operation {
+        fs::rename(from, to).unwrap();
+        Operation::Rename {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        }
+    }
+
+    #[test]
+    fn operations_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (a, b) = (tmp.path().join("a"), tmp.path().join("b"));
+        fs::write(&a, "a").unwrap();
+        let dir = tmp.path().join("dir");
+        fs::create_dir(&dir).unwrap();
+
+        let mut journal = Journal::default();
+        journal.record(rename(&a, &b));
+        journal.record(Operation::CreateDir { path: dir.clone() });
+        journal.record(Operation::Irreversible {
+            description: "shred x".to_string(),
+        });
+        assert_eq!(
+            journal.next_undo().unwrap().describe(),
+            "create directory dir"
+        );
+
+        journal.undo().unwrap();
+        assert!(!dir.exists());
+        journal.undo().unwrap();
+        assert!(a.exists() && !b.exists());
+        assert!(journal.undo().is_err());
+
+        journal.redo().unwrap();
+        assert!(b.exists() && !a.exists());
+        journal.redo().unwrap();
+        assert!(dir.is_dir());
+        assert!(journal.redo().is_err());
+    }
+
+    #[test]
+    fn batch_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let names = ["a", "b", "c"].map(|n| tmp.path().join(n));
+        let moved = ["a2", "b2", "c2"].map(|n| tmp.path().join(n));
+        let mut ops = Vec::new();
+        for (from, to) in names.iter().zip(&moved) {
+            fs::write(from, "x").unwrap();
+            ops.push(rename(from, to));
+        }
+        let mut journal = Journal::default();
+        journal.record(Operation::Batch {
+            label: "rename".to_string(),
+            ops,
+        });
+
+        assert_eq!(journal.undo().unwrap(), "Undid rename (3 items)");
+        assert!(names.iter().all(|p| p.exists()));
+        assert_eq!(journal.redo().unwrap(), "Redid rename (3 items)");
+        assert!(moved.iter().all(|p| p.exists()));
+    }
+
+    #[test]
+    fn partial_batch_failure_keeps_what_was_undone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (a, a2) = (tmp.path().join("a"), tmp.path().join("a2"));
+        let (b, b2) = (tmp.path().join("b"), tmp.path().join("b2"));
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+        let mut journal = Journal::default();
+        journal.record(Operation::Batch {
+            label: "rename".to_string(),
+            ops: vec![rename(&a, &a2), rename(&b, &b2)],
+        });
+        // Undo goes in reverse, so b comes back and a is blocked.
+        fs::write(&a, "in the way").unwrap();
+
+        let err = journal.undo().unwrap_err();
+        assert!(err.contains("failed: rename a -> a2: "), "{}", err);
+        assert!(err.contains("1 of 2 items were undone"), "{}", err);
+        assert!(b.exists() && !b2.exists());
+        assert_eq!(journal.next_redo().unwrap().describe(), "rename (1 items)");
+        assert!(journal.next_undo().is_none());
+
+        journal.redo().unwrap();
+        assert!(b2.exists() && !b.exists());
+        assert_eq!(fs::read_to_string(&a2).unwrap(), "a");
+    }
+}
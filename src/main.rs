@@ -1,14 +1,30 @@
+use std::env;
 use std::io;
 
 mod actions;
 mod archive;
+mod cli;
+mod compress;
+mod copy;
+mod dupes;
 mod error;
 mod fs;
 mod gui; // 👈 GUI module
+mod hash;
+mod journal;
 mod navigation;
+mod shred;
+mod split;
+mod trash;
 mod ui;
 
 fn main() {
+    // Subcommands make every action scriptable; the menu below is the fallback.
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
     println!("Redox File Manager starting...");
     ui::launch();
 
@@ -16,27 +32,36 @@ fn main() {
         println!("\nChoose an action:");
         println!("1. List files");
         println!("2. Copy file");
-        println!("3. Delete file");
+        println!("3. Delete file (move to trash)");
         println!("4. Handle error");
         println!("5. Change directory");
         println!("6. Search files");
-        println!("7. Batch delete files");
+        println!("7. Batch delete files (move to trash)");
         println!("8. Rename file");
         println!("9. Move file");
         println!("10. Batch copy files");
         println!("11. Batch rename files");
         println!("12. Create file");
         println!("13. Create directory");
-        println!("14. Delete directory");
+        println!("14. Delete directory (move to trash)");
         println!("15. Launch GUI");
         println!("17. Archive: List contents");
         println!("18. Archive: Extract");
         println!("19. Archive: Create");
         println!("20. Calculate file hash");
         println!("21. Find duplicate files");
-        println!("22. Secure delete file");
+        println!("22. Secure delete file or directory");
         println!("23. Split file");
         println!("24. Join file chunks");
+        println!("26. Permanently delete file or directory");
+        println!("27. Trash: List");
+        println!("28. Trash: Restore");
+        println!("29. Trash: Empty");
+        println!("30. Verify checksum file");
+        println!("31. Archive: Test integrity");
+        println!("32. Archive: Update (add, replace, delete entries)");
+        println!("33. Compress file (gz, bz2, xz, zst)");
+        println!("34. Decompress file");
         println!("25. Exit");
 
         let mut choice = String::new();
@@ -71,6 +96,15 @@ fn main() {
             "22" => actions::secure_delete(),
             "23" => actions::split_file(),
             "24" => actions::join_files(),
+            "26" => actions::permanent_delete(),
+            "27" => actions::trash_list(),
+            "28" => actions::trash_restore(),
+            "29" => actions::trash_empty(),
+            "30" => actions::verify_checksums(),
+            "31" => actions::archive_test_cli(),
+            "32" => actions::archive_update_cli(),
+            "33" => actions::compress_file_cli(),
+            "34" => actions::decompress_file_cli(),
             "25" => {
                 println!("Exiting File Manager...");
                 break;
@@ -1,15 +1,39 @@
 use std::env;
 use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+/// Directory left by the last successful change, for `cd -`.
+static PREVIOUS_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
 
 pub fn change_directory() {
-    print!("Enter directory to change into: ");
+    print!("Enter directory to change into (.., - and ~ work as in a shell): ");
     io::stdout().flush().unwrap();
     let mut dir = String::new();
     io::stdin().read_line(&mut dir).unwrap();
     let dir = dir.trim();
+    // accept a typed "cd" out of habit
+    let dir = match dir {
+        "cd" => "",
+        _ => dir.strip_prefix("cd ").map_or(dir, str::trim),
+    };
+
+    let current = env::current_dir().unwrap_or_default();
+    let previous = PREVIOUS_DIR.lock().unwrap().clone();
+    let target = match resolve(dir, &current, previous.as_deref()) {
+        Ok(target) => target,
+        Err(e) => {
+            println!("Error: {}.", e);
+            return;
+        }
+    };
 
-    match env::set_current_dir(dir) {
-        Ok(_) => println!("Changed directory to {}", dir),
+    match env::set_current_dir(&target) {
+        Ok(_) => {
+            *PREVIOUS_DIR.lock().unwrap() = Some(current);
+            let now = env::current_dir().unwrap_or(target);
+            println!("Changed directory to {}", now.display());
+        }
         Err(e) if e.kind() == io::ErrorKind::NotFound => println!("Error: Directory not found."),
         Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
             println!("Error: Permission denied.")
@@ -17,3 +41,84 @@ pub fn change_directory() {
         Err(e) => println!("Unexpected error: {}", e),
     }
 }
+
+/// Where `cd input` leads from `current`: `-` is `previous`, `..` the parent
+/// (the root stays the root), an empty input or `~` the home directory, and
+/// `~/path` a path under it. Anything else is taken relative to `current`.
+pub fn resolve(input: &str, current: &Path, previous: Option<&Path>) -> Result<PathBuf, String> {
+    let home = || home_dir().ok_or_else(|| "home directory is not set".to_string());
+    match input {
+        "-" => previous
+            .map(Path::to_path_buf)
+            .ok_or_else(|| "no previous directory".to_string()),
+        "" | "~" => home(),
+        ".." => Ok(current.parent().unwrap_or(current).to_path_buf()),
+        _ => match input.strip_prefix("~/") {
+            Some(rest) => Ok(home()?.join(rest)),
+            None => Ok(normalize(&current.join(input))),
+        },
+    }
+}
+
+/// The user's home directory, from `HOME` (or `USERPROFILE` on Windows).
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .filter(|h| !h.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Drop `.` components and resolve `..` against the component before it,
+/// without touching the filesystem, the way a shell's `cd` treats the path.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            // "/.." is "/"; a relative path keeps the ".." it cannot resolve
+            Component::ParentDir => {
+                if out.file_name().is_some() {
+                    out.pop();
+                } else if !out.has_root() {
+                    out.push(component);
+                }
+            }
+            _ => out.push(component),
+        }
+    }
+    if out.as_os_str().is_empty() {
+        out.push(".");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_handles_dash_dotdot_and_relative_paths() {
+        let current = Path::new("/srv/data/logs");
+        assert_eq!(
+            resolve("..", current, None).unwrap(),
+            PathBuf::from("/srv/data")
+        );
+        assert_eq!(
+            resolve("..", Path::new("/"), None).unwrap(),
+            PathBuf::from("/")
+        );
+        assert_eq!(
+            resolve("-", current, Some(Path::new("/tmp"))).unwrap(),
+            PathBuf::from("/tmp")
+        );
+        assert!(resolve("-", current, None).is_err());
+        assert_eq!(
+            resolve("./old/../2024", current, None).unwrap(),
+            PathBuf::from("/srv/data/logs/2024")
+        );
+        assert_eq!(
+            resolve("/etc/", current, None).unwrap(),
+            PathBuf::from("/etc")
+        );
+    }
+}
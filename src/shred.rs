@@ -0,0 +1,448 @@
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use walkdir::WalkDir;
+
+/// Write size for each overwrite pass.
+const SHRED_CHUNK: usize = 1024 * 1024;
+
+/// Number of random renames before a file is unlinked.
+const RENAME_ROUNDS: usize = 3;
+
+/// What a single overwrite pass writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pass {
+    Fill(u8),
+    Random,
+}
+
+impl Pass {
+    fn label(self) -> String {
+        match self {
+            Pass::Fill(b) => format!("0x{:02X}", b),
+            Pass::Random => "random".to_string(),
+        }
+    }
+}
+
+/// Overwrite scheme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    /// One pass of zeros.
+    Zero,
+    /// One pass of random data.
+    Random,
+    /// DoD 5220.22-M style: zeros, ones, then random.
+    Dod3,
+    /// The given number of random passes.
+    Custom(u32),
+}
+
+impl Scheme {
+    pub const ALL: [Scheme; 4] = [
+        Scheme::Zero,
+        Scheme::Random,
+        Scheme::Dod3,
+        Scheme::Custom(7),
+    ];
+
+    pub fn parse(s: &str) -> Option<Scheme> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "zero" | "zeros" => Some(Scheme::Zero),
+            "random" => Some(Scheme::Random),
+            "dod" | "dod3" => Some(Scheme::Dod3),
+            n => n.parse::<u32>().ok().filter(|n| *n > 0).map(Scheme::Custom),
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Scheme::Zero => "Zero (1 pass)".to_string(),
+            Scheme::Random => "Random (1 pass)".to_string(),
+            Scheme::Dod3 => "DoD (3 passes)".to_string(),
+            Scheme::Custom(n) => format!("Custom ({} random passes)", n),
+        }
+    }
+
+    pub fn passes(self) -> Vec<Pass> {
+        match self {
+            Scheme::Zero => vec![Pass::Fill(0x00)],
+            Scheme::Random => vec![Pass::Random],
+            Scheme::Dod3 => vec![Pass::Fill(0x00), Pass::Fill(0xFF), Pass::Random],
+            Scheme::Custom(n) => vec![Pass::Random; n.max(1) as usize],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ShredOptions {
+    pub scheme: Scheme,
+    /// Read the file back after the last pass and compare.
+    pub verify: bool,
+}
+
+impl Default for ShredOptions {
+    fn default() -> Self {
+        ShredOptions {
+            scheme: Scheme::Random,
+            verify: false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ShredReport {
+    pub files: usize,
+    pub dirs: usize,
+}
+
+fn canceled() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "shred canceled")
+}
+
+/// Securely delete a file, or every file in a directory tree.
+///
+/// Each file is overwritten with every pass of `options.scheme`, synced to
+/// disk after each pass, optionally verified, renamed to random names and then
+/// unlinked. Directories are renamed and removed once empty. Symlinks are
+/// removed without touching their targets, and other special files (FIFOs,
+/// sockets, device nodes) without being opened.
+pub fn shred_path(
+    path: &Path,
+    options: &ShredOptions,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<ShredReport> {
+    let meta = fs::symlink_metadata(path)?;
+    let mut report = ShredReport::default();
+    if !meta.is_dir() {
+        shred_file(path, options, progress, cancel)?;
+        report.files = 1;
+        return Ok(report);
+    }
+
+    let entries: Vec<_> = WalkDir::new(path)
+        .follow_links(false)
+        .contents_first(true)
+        .into_iter()
+        .collect::<Result<_, _>>()?;
+    let total_files = entries.iter().filter(|e| !e.file_type().is_dir()).count();
+    for entry in entries {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(canceled());
+        }
+        if entry.file_type().is_dir() {
+            remove_obscured(entry.path(), true)?;
+            report.dirs += 1;
+        } else {
+            shred_file(entry.path(), options, &mut |_| {}, cancel)?;
+            report.files += 1;
+            progress(format!(
+                "Shredded {}/{}: {}",
+                report.files,
+                total_files,
+                entry.path().display()
+            ));
+        }
+    }
+    Ok(report)
+}
+
+/// Overwrite, sync, optionally verify, rename and unlink one file. Anything
+/// but a regular file is only renamed and unlinked: opening a FIFO would
+/// block and writing to a device node would overwrite the device.
+pub fn shred_file(
+    path: &Path,
+    options: &ShredOptions,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<()> {
+    let meta = fs::symlink_metadata(path)?;
+    if !meta.file_type().is_file() {
+        return remove_obscured(path, false);
+    }
+    let size = meta.len();
+    let mut file = fs::OpenOptions::new().read(true).write(true).open(path)?;
+    let passes = options.scheme.passes();
+    let mut last_seed = None;
+    for (i, pass) in passes.iter().enumerate() {
+        let seed: [u8; 32] = rand::thread_rng().r#gen();
+        let mut on_pct = |pct: u64| {
+            progress(format!(
+                "Pass {}/{} ({}) on {}: {}%",
+                i + 1,
+                passes.len(),
+                pass.label(),
+                path.display(),
+                pct
+            ))
+        };
+        overwrite(&mut file, size, *pass, seed, &mut on_pct, cancel)?;
+        file.sync_all()?;
+        last_seed = Some(seed);
+    }
+    if options.verify
+        && let (Some(pass), Some(seed)) = (passes.last(), last_seed)
+    {
+        progress(format!("Verifying {}", path.display()));
+        verify(&mut file, size, *pass, seed, cancel)?;
+    }
+    // Drop the data blocks' link to the inode before the name goes away.
+    file.set_len(0)?;
+    file.sync_all()?;
+    drop(file);
+    remove_obscured(path, false)
+}
+
+/// Fill `size` bytes from the start of `file` with `pass`. Random passes are
+/// generated from `seed` so the verify step can reproduce them.
+fn overwrite(
+    file: &mut fs::File,
+    size: u64,
+    pass: Pass,
+    seed: [u8; 32],
+    on_pct: &mut dyn FnMut(u64),
+    cancel: &AtomicBool,
+) -> io::Result<()> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut rng = StdRng::from_seed(seed);
+    let mut buf = vec![0u8; SHRED_CHUNK];
+    let mut written = 0u64;
+    let mut last_pct = 0;
+    while written < size {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(canceled());
+        }
+        let n = (size - written).min(SHRED_CHUNK as u64) as usize;
+        fill(&mut buf[..n], pass, &mut rng);
+        file.write_all(&buf[..n])?;
+        written += n as u64;
+        let pct = written * 100 / size;
+        if pct >= last_pct + 10 || written == size {
+            last_pct = pct;
+            on_pct(pct);
+        }
+    }
+    Ok(())
+}
+
+fn fill(buf: &mut [u8], pass: Pass, rng: &mut StdRng) {
+    match pass {
+        Pass::Fill(b) => buf.fill(b),
+        Pass::Random => rng.fill_bytes(buf),
+    }
+}
+
+/// Read the file back and check it matches what the last pass wrote.
+fn verify(
+    file: &mut fs::File,
+    size: u64,
+    pass: Pass,
+    seed: [u8; 32],
+    cancel: &AtomicBool,
+) -> io::Result<()> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut rng = StdRng::from_seed(seed);
+    let mut expected = vec![0u8; SHRED_CHUNK];
+    let mut actual = vec![0u8; SHRED_CHUNK];
+    let mut checked = 0u64;
+    while checked < size {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(canceled());
+        }
+        let n = (size - checked).min(SHRED_CHUNK as u64) as usize;
+        fill(&mut expected[..n], pass, &mut rng);
+        file.read_exact(&mut actual[..n])?;
+        if expected[..n] != actual[..n] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("verification failed at byte offset {}", checked),
+            ));
+        }
+        checked += n as u64;
+    }
+    Ok(())
+}
+
+/// Rename `path` to random names of the same length a few times, then remove it.
+fn remove_obscured(path: &Path, is_dir: bool) -> io::Result<()> {
+    let len = path.file_name().map(|n| n.len()).unwrap_or(8).max(1);
+    let mut current = path.to_path_buf();
+    for _ in 0..RENAME_ROUNDS {
+        let candidate = current.with_file_name(random_name(len));
+        if fs::symlink_metadata(&candidate).is_ok() {
+            continue;
+        }
+        fs::rename(&current, &candidate)?;
+        current = candidate;
+    }
+    sync_parent(&current);
+    if is_dir {
+        fs::remove_dir(&current)?;
+    } else {
+        fs::remove_file(&current)?;
+    }
+    sync_parent(&current);
+    Ok(())
+}
+
+fn random_name(len: usize) -> String {
+    const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| CHARS[rng.gen_range(0..CHARS.len())] as char)
+        .collect()
+}
+
+/// Flush the directory entry changes so old names do not linger in the journal.
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let dir: PathBuf = if parent.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            parent.to_path_buf()
+        };
+        if let Ok(d) = fs::File::open(dir) {
+            let _ = d.sync_all();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn schemes_have_their_passes() {
+        assert_eq!(Scheme::Zero.passes(), [Pass::Fill(0x00)]);
+        assert_eq!(Scheme::Random.passes(), [Pass::Random]);
+        assert_eq!(
+            Scheme::Dod3.passes(),
+            [Pass::Fill(0x00), Pass::Fill(0xFF), Pass::Random]
+        );
+        assert_eq!(Scheme::Custom(7).passes(), [Pass::Random; 7]);
+        assert_eq!(Scheme::Custom(0).passes(), [Pass::Random]);
+        assert_eq!(Scheme::parse("dod"), Some(Scheme::Dod3));
+        assert_eq!(Scheme::parse("5"), Some(Scheme::Custom(5)));
+        assert_eq!(Scheme::parse("0"), None);
+    }
+
+    #[test]
+    fn passes_overwrite_the_whole_file_and_verify() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("data");
+        // More than one chunk, and not a multiple of it.
+        let size = SHRED_CHUNK as u64 + 100;
+        let cancel = AtomicBool::new(false);
+        let seed = [7u8; 32];
+        for pass in [Pass::Fill(0x00), Pass::Fill(0xFF), Pass::Random] {
+            fs::write(&path, vec![0x5A; size as usize]).unwrap();
+            let mut file = fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&path)
+                .unwrap();
+            let mut pcts = Vec::new();
+            overwrite(&mut file, size, pass, seed, &mut |p| pcts.push(p), &cancel).unwrap();
+            assert_eq!(pcts.last(), Some(&100));
+            let data = fs::read(&path).unwrap();
+            assert_eq!(data.len() as u64, size);
+            match pass {
+                Pass::Fill(b) => assert!(data.iter().all(|&d| d == b), "{:?}", pass),
+                Pass::Random => assert!(data.iter().filter(|&&d| d == 0x5A).count() < 10_000),
+            }
+            verify(&mut file, size, pass, seed, &cancel).unwrap();
+            if pass == Pass::Random {
+                // Only the same seed reproduces a random pass.
+                assert!(verify(&mut file, size, pass, [8u8; 32], &cancel).is_err());
+            }
+
+            // A byte that did not get written is caught.
+            let mut data = data;
+            data[size as usize - 1] ^= 1;
+            fs::write(&path, &data).unwrap();
+            let e = verify(&mut file, size, pass, seed, &cancel).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn files_are_renamed_then_unlinked() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("secret.txt");
+        fs::write(&path, "top secret").unwrap();
+        let mut messages = Vec::new();
+        let options = ShredOptions {
+            scheme: Scheme::Dod3,
+            verify: true,
+        };
+        shred_file(
+            &path,
+            &options,
+            &mut |m| messages.push(m),
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        assert!(names(tmp.path()).is_empty());
+        assert_eq!(
+            messages.iter().filter(|m| m.ends_with("100%")).count(),
+            3,
+            "{:?}",
+            messages
+        );
+        assert!(messages.last().unwrap().starts_with("Verifying"));
+
+        // Empty directories go the same way.
+        fs::create_dir(&path).unwrap();
+        remove_obscured(&path, true).unwrap();
+        assert!(names(tmp.path()).is_empty());
+    }
+
+    #[test]
+    fn directories_are_shredded_recursively() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("root");
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("top.txt"), "top").unwrap();
+        fs::write(root.join("a/mid.txt"), "mid").unwrap();
+        fs::write(root.join("a/b/deep.txt"), "deep").unwrap();
+        fs::write(tmp.path().join("target"), "kept").unwrap();
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(tmp.path().join("target"), root.join("a/link")).unwrap();
+            // Opening a FIFO for writing would block until a reader came.
+            let status = std::process::Command::new("mkfifo")
+                .arg(root.join("a/b/fifo"))
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+        let report = shred_path(
+            &root,
+            &ShredOptions::default(),
+            &mut |_| {},
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        assert_eq!(report.dirs, 3);
+        assert_eq!(report.files, if cfg!(unix) { 5 } else { 3 });
+        assert_eq!(names(tmp.path()), ["target"]);
+        assert_eq!(fs::read(tmp.path().join("target")).unwrap(), b"kept");
+    }
+}
@@ -0,0 +1,417 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Read size used when splitting and joining.
+const SPLIT_CHUNK: usize = 1024 * 1024;
+
+/// First line of every manifest written by [`split_file`].
+const MANIFEST_HEADER: &str = "# redox-filemanager split manifest v1";
+
+/// How to cut a file into parts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitMode {
+    /// Parts of at most this many bytes.
+    Size(u64),
+    /// This many parts of (nearly) equal size.
+    Parts(u64),
+}
+
+/// One part as recorded in the manifest.
+#[derive(Clone, Debug)]
+pub struct Part {
+    pub name: String,
+    pub size: u64,
+    pub hash: String,
+}
+
+/// Contents of `<file>.manifest`: the original file and every part, in order.
+#[derive(Clone, Debug)]
+pub struct Manifest {
+    pub file_name: String,
+    pub size: u64,
+    pub hash: String,
+    pub parts: Vec<Part>,
+}
+
+impl Manifest {
+    fn to_text(&self) -> String {
+        let mut out = format!(
+            "{}\nfile {} {} {}\n",
+            MANIFEST_HEADER, self.size, self.hash, self.file_name
+        );
+        for part in &self.parts {
+            out.push_str(&format!("part {} {} {}\n", part.size, part.hash, part.name));
+        }
+        out
+    }
+
+    fn parse(text: &str) -> Option<Manifest> {
+        let mut lines = text.lines();
+        if lines.next()?.trim() != MANIFEST_HEADER {
+            return None;
+        }
+        let mut file = None;
+        let mut parts = Vec::new();
+        for line in lines {
+            // <kind> <size> <hash> <name>; the name may contain spaces
+            let mut fields = line.splitn(4, ' ');
+            let kind = fields.next()?;
+            let size = fields.next()?.parse::<u64>().ok()?;
+            let hash = fields.next()?.to_string();
+            let name = fields.next()?.to_string();
+            match kind {
+                "file" => file = Some((name, size, hash)),
+                "part" => parts.push(Part { name, size, hash }),
+                _ => return None,
+            }
+        }
+        let (file_name, size, hash) = file?;
+        Some(Manifest {
+            file_name,
+            size,
+            hash,
+            parts,
+        })
+    }
+}
+
+/// Path of part `index` of `file`: `<file>.000`, `<file>.001`, ...
+pub fn part_path(file: &Path, index: usize) -> PathBuf {
+    let mut name = file.as_os_str().to_os_string();
+    name.push(format!(".{:03}", index));
+    PathBuf::from(name)
+}
+
+/// Path of the manifest for `file`: `<file>.manifest`.
+pub fn manifest_path(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_os_string();
+    name.push(".manifest");
+    PathBuf::from(name)
+}
+
+fn canceled() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "canceled")
+}
+
+/// Split `path` into `<path>.000`, `<path>.001`, ... and write `<path>.manifest`
+/// with the size and BLAKE3 hash of every part and of the whole file.
+///
+/// Parts are written as they are read, so memory use does not depend on the
+/// file size. On error or cancel the parts written so far are removed.
+pub fn split_file(
+    path: &Path,
+    mode: SplitMode,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<Manifest> {
+    let total = fs::metadata(path)?.len();
+    let part_size = match mode {
+        SplitMode::Size(0) | SplitMode::Parts(0) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "part size and part count must be greater than zero",
+            ));
+        }
+        SplitMode::Size(size) => size,
+        SplitMode::Parts(count) => total.div_ceil(count).max(1),
+    };
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+
+    let mut written = Vec::new();
+    let result = write_parts(path, total, part_size, &mut written, progress, cancel);
+    let (hash, parts) = match result {
+        Ok(r) => r,
+        Err(e) => {
+            for p in &written {
+                let _ = fs::remove_file(p);
+            }
+            return Err(e);
+        }
+    };
+    let manifest = Manifest {
+        file_name,
+        size: total,
+        hash,
+        parts,
+    };
+    fs::write(manifest_path(path), manifest.to_text())?;
+    Ok(manifest)
+}
+
+/// Stream `path` into parts of `part_size`; returns the whole-file hash and the parts.
+fn write_parts(
+    path: &Path,
+    total: u64,
+    part_size: u64,
+    written: &mut Vec<PathBuf>,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<(String, Vec<Part>)> {
+    let mut reader = fs::File::open(path)?;
+    let mut whole = blake3::Hasher::new();
+    let mut buf = vec![0u8; SPLIT_CHUNK];
+    let mut parts = Vec::new();
+    let mut done = 0u64;
+    while done < total || (total == 0 && parts.is_empty()) {
+        let out_path = part_path(path, parts.len());
+        let mut writer = fs::File::create(&out_path)?;
+        written.push(out_path.clone());
+        let mut hasher = blake3::Hasher::new();
+        let mut size = 0u64;
+        while size < part_size {
+            if cancel.load(Ordering::SeqCst) {
+                return Err(canceled());
+            }
+            let want = (part_size - size).min(buf.len() as u64) as usize;
+            let n = reader.read(&mut buf[..want])?;
+            if n == 0 {
+                break;
+            }
+            writer.write_all(&buf[..n])?;
+            hasher.update(&buf[..n]);
+            whole.update(&buf[..n]);
+            size += n as u64;
+        }
+        writer.sync_all()?;
+        done += size;
+        let name = out_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        progress(format!("Created: {} ({} bytes)", out_path.display(), size));
+        parts.push(Part {
+            name,
+            size,
+            hash: hasher.finalize().to_hex().to_string(),
+        });
+        if size == 0 {
+            break;
+        }
+    }
+    Ok((whole.finalize().to_hex().to_string(), parts))
+}
+
+/// Why a part cannot be used for joining.
+#[derive(Debug)]
+pub enum PartProblem {
+    Missing(String),
+    WrongSize {
+        name: String,
+        expected: u64,
+        actual: u64,
+    },
+    Corrupt(String),
+}
+
+impl fmt::Display for PartProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartProblem::Missing(name) => write!(f, "{} is missing", name),
+            PartProblem::WrongSize {
+                name,
+                expected,
+                actual,
+            } => write!(f, "{} has {} bytes, expected {}", name, actual, expected),
+            PartProblem::Corrupt(name) => write!(f, "{} is corrupt (hash mismatch)", name),
+        }
+    }
+}
+
+/// Read `<file>.manifest`. Every part it lists must be named like
+/// [`part_path`] names the parts of `file`, so it is found beside the manifest.
+pub fn read_manifest(file: &Path) -> io::Result<Manifest> {
+    let text = fs::read_to_string(manifest_path(file))?;
+    let manifest = Manifest::parse(&text).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("malformed manifest {}", manifest_path(file).display()),
+        )
+    })?;
+    if let Some(part) = manifest.parts.iter().find(|p| !is_part_name(file, &p.name)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "manifest {} lists '{}', which is not a part of {}",
+                manifest_path(file).display(),
+                part.name,
+                file.display()
+            ),
+        ));
+    }
+    Ok(manifest)
+}
+
+/// Whether `name` is a bare `<file name>.NNN` (three or more digits).
+fn is_part_name(file: &Path, name: &str) -> bool {
+    let Some(base) = file.file_name() else {
+        return false;
+    };
+    name.strip_prefix(base.to_string_lossy().as_ref())
+        .and_then(|rest| rest.strip_prefix('.'))
+        .is_some_and(|n| n.len() >= 3 && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Check every part listed in `manifest` (found next to `file`) by size and hash.
+pub fn check_parts(
+    file: &Path,
+    manifest: &Manifest,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<Vec<PartProblem>> {
+    let dir = file.parent().unwrap_or(Path::new(""));
+    let mut problems = Vec::new();
+    for part in &manifest.parts {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(canceled());
+        }
+        let path = dir.join(&part.name);
+        let actual = match fs::metadata(&path) {
+            Ok(m) => m.len(),
+            Err(_) => {
+                problems.push(PartProblem::Missing(part.name.clone()));
+                continue;
+            }
+        };
+        if actual != part.size {
+            problems.push(PartProblem::WrongSize {
+                name: part.name.clone(),
+                expected: part.size,
+                actual,
+            });
+            continue;
+        }
+        let hash =
+            crate::hash::hash_file(&path, crate::hash::HashAlgo::Blake3, &mut |_| {}, cancel)?;
+        if hash != part.hash {
+            problems.push(PartProblem::Corrupt(part.name.clone()));
+        } else {
+            progress(format!("Verified {}", part.name));
+        }
+    }
+    Ok(problems)
+}
+
+/// Join the parts of `file` into `output` and return the number of parts used.
+///
+/// With a manifest, every part is checked before `output` is created, and the
+/// joined result is checked against the recorded whole-file hash. Without one,
+/// consecutive parts `<file>.000`, `<file>.001`, ... are joined unchecked.
+pub fn join_files(
+    file: &Path,
+    output: &Path,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<usize> {
+    let manifest = match read_manifest(file) {
+        Ok(m) => Some(m),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let (parts, expected_hash) = match &manifest {
+        Some(m) => {
+            let problems = check_parts(file, m, progress, cancel)?;
+            if !problems.is_empty() {
+                let list: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("cannot join: {}", list.join("; ")),
+                ));
+            }
+            let dir = file.parent().unwrap_or(Path::new(""));
+            let parts: Vec<PathBuf> = m.parts.iter().map(|p| dir.join(&p.name)).collect();
+            (parts, Some(m.hash.clone()))
+        }
+        None => {
+            progress("No manifest found; joining parts without verification".to_string());
+            let parts: Vec<PathBuf> = (0..)
+                .map(|i| part_path(file, i))
+                .take_while(|p| p.is_file())
+                .collect();
+            if parts.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no parts found for '{}'", file.display()),
+                ));
+            }
+            (parts, None)
+        }
+    };
+
+    let result =
+        write_joined(&parts, output, progress, cancel).and_then(|hash| match expected_hash {
+            Some(expected) if expected != hash => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "joined file does not match the manifest hash",
+            )),
+            _ => Ok(parts.len()),
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(output);
+    }
+    result
+}
+
+/// Concatenate `parts` into `output`; returns the BLAKE3 hash of what was written.
+fn write_joined(
+    parts: &[PathBuf],
+    output: &Path,
+    progress: &mut dyn FnMut(String),
+    cancel: &AtomicBool,
+) -> io::Result<String> {
+    let mut writer = fs::File::create(output)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = vec![0u8; SPLIT_CHUNK];
+    for (i, part) in parts.iter().enumerate() {
+        let mut reader = fs::File::open(part)?;
+        loop {
+            if cancel.load(Ordering::SeqCst) {
+                return Err(canceled());
+            }
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            writer.write_all(&buf[..n])?;
+            hasher.update(&buf[..n]);
+        }
+        progress(format!("Joined part {}/{}", i + 1, parts.len()));
+    }
+    writer.sync_all()?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_parts_must_be_named_after_the_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("data.bin");
+        fs::write(&file, vec![1u8; 10]).unwrap();
+        let cancel = AtomicBool::new(false);
+        let manifest = split_file(&file, SplitMode::Size(4), &mut |_| {}, &cancel).unwrap();
+        assert_eq!(read_manifest(&file).unwrap().parts.len(), 3);
+
+        let text = manifest.to_text();
+        for evil in [
+            "../secret",
+            "/etc/passwd",
+            "other.bin.000",
+            "data.bin.0x1",
+            "data.bin.00",
+        ] {
+            let tampered = text.replacen("data.bin.001", evil, 1);
+            fs::write(manifest_path(&file), tampered).unwrap();
+            let err = read_manifest(&file).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{}", evil);
+        }
+        assert!(is_part_name(&file, "data.bin.1000"));
+    }
+}
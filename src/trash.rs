@@ -0,0 +1,413 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use chrono::{Local, NaiveDateTime};
+
+/// Date format used by `DeletionDate` in `.trashinfo` files.
+const DELETION_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// One trashed file or directory, as described by its `.trashinfo` file.
+#[derive(Clone, Debug)]
+pub struct TrashItem {
+    /// Name under `Trash/files` (and `Trash/info/<name>.trashinfo`).
+    pub name: String,
+    /// Absolute path the item was deleted from.
+    pub original_path: PathBuf,
+    /// Local time of deletion, as written in the info file.
+    pub deletion_date: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+impl TrashItem {
+    fn files_path(&self, trash: &Path) -> PathBuf {
+        trash.join("files").join(&self.name)
+    }
+}
+
+/// The home trash directory per the freedesktop.org Trash spec:
+/// `$XDG_DATA_HOME/Trash`, defaulting to `~/.local/share/Trash`.
+pub fn trash_dir() -> io::Result<PathBuf> {
+    if let Some(data_home) = std::env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+        return Ok(PathBuf::from(data_home).join("Trash"));
+    }
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "home directory not set"))?;
+    Ok(PathBuf::from(home).join(".local/share/Trash"))
+}
+
+/// Create `Trash/files` and `Trash/info` if needed (mode 0700 on Unix).
+fn ensure_trash_dirs(trash: &Path) -> io::Result<()> {
+    for sub in ["files", "info"] {
+        let dir = trash.join(sub);
+        if !dir.is_dir() {
+            fs::create_dir_all(&dir)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Move `path` into the trash and write its `.trashinfo`.
+///
+/// Everything goes to the home trash; the per-mount `$topdir/.Trash-$uid`
+/// directories of the spec are not used. An item on another filesystem is
+/// therefore copied into the home trash and then deleted, which takes as
+/// long as copying it and needs room for it on the home filesystem.
+pub fn move_to_trash(path: &Path) -> io::Result<TrashItem> {
+    let meta = fs::symlink_metadata(path)?;
+    let original = std::path::absolute(path)?;
+    let trash = trash_dir()?;
+    if original.starts_with(&trash) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "item is already in the trash",
+        ));
+    }
+    ensure_trash_dirs(&trash)?;
+
+    let base = original
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "cannot trash a root path"))?;
+    let deletion_date = Local::now().format(DELETION_DATE_FORMAT).to_string();
+
+    // The spec makes creating the info file with O_EXCL the way to claim a name.
+    let mut n = 1;
+    let (name, info_path) = loop {
+        let candidate = if n == 1 {
+            base.clone()
+        } else {
+            format!("{}.{}", base, n)
+        };
+        let info_path = trash.join("info").join(format!("{}.trashinfo", candidate));
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            // symlink_metadata, so a trashed dangling symlink still counts.
+            Ok(mut f) if fs::symlink_metadata(trash.join("files").join(&candidate)).is_err() => {
+                write!(
+                    f,
+                    "[Trash Info]\nPath={}\nDeletionDate={}\n",
+                    encode_path(&original),
+                    deletion_date
+                )?;
+                break (candidate, info_path);
+            }
+            Ok(_) => {
+                // Orphaned info file slot with a leftover payload; keep looking.
+                let _ = fs::remove_file(&info_path);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+        n += 1;
+    };
+
+    let item = TrashItem {
+        name,
+        original_path: original.clone(),
+        deletion_date,
+        is_dir: meta.is_dir(),
+        size: if meta.is_dir() { 0 } else { meta.len() },
+    };
+    let cancel = AtomicBool::new(false);
+    if let Err(e) =
+        crate::copy::move_path(&original, &item.files_path(&trash), &mut |_| {}, &cancel)
+    {
+        let _ = fs::remove_file(&info_path);
+        return Err(e);
+    }
+    Ok(item)
+}
+
+/// List everything in the trash that has both an info file and a payload.
+pub fn list_trash() -> io::Result<Vec<TrashItem>> {
+    let trash = trash_dir()?;
+    let info_dir = trash.join("info");
+    let entries = match fs::read_dir(&info_dir) {
+        Ok(e) => e,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut items = Vec::new();
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Some(name) = file_name.strip_suffix(".trashinfo") else {
+            continue;
+        };
+        let Ok(contents) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let Some((original_path, deletion_date)) = parse_trash_info(&contents) else {
+            continue;
+        };
+        let Ok(meta) = fs::symlink_metadata(trash.join("files").join(name)) else {
+            continue;
+        };
+        items.push(TrashItem {
+            name: name.to_string(),
+            original_path,
+            deletion_date,
+            is_dir: meta.is_dir(),
+            size: if meta.is_dir() { 0 } else { meta.len() },
+        });
+    }
+    // Most recently deleted first.
+    items.sort_by(|a, b| b.deletion_date.cmp(&a.deletion_date));
+    Ok(items)
+}
+
+/// Put a trashed item back where it came from. Fails if something now
+/// occupies the original path.
+pub fn restore(name: &str) -> io::Result<PathBuf> {
+    check_name(name)?;
+    let trash = trash_dir()?;
+    let info_path = trash.join("info").join(format!("{}.trashinfo", name));
+    let contents = fs::read_to_string(&info_path)?;
+    let (original, _) = parse_trash_info(&contents).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("malformed trash info for '{}'", name),
+        )
+    })?;
+    if fs::symlink_metadata(&original).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("'{}' already exists", original.display()),
+        ));
+    }
+    if let Some(parent) = original.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let cancel = AtomicBool::new(false);
+    crate::copy::move_path(
+        &trash.join("files").join(name),
+        &original,
+        &mut |_| {},
+        &cancel,
+    )?;
+    fs::remove_file(&info_path)?;
+    Ok(original)
+}
+
+/// A trash name is a single file name under `files/`; anything that could
+/// reach outside it is refused.
+fn check_name(name: &str) -> io::Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '\0']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("'{}' is not a trash item name", name),
+        ));
+    }
+    Ok(())
+}
+
+/// Permanently delete everything in the trash. Returns the number of items
+/// removed. Items that cannot be removed are skipped and stay listed; the
+/// error then names them along with how many others were removed.
+pub fn empty_trash() -> io::Result<usize> {
+    let trash = trash_dir()?;
+    let mut removed = 0;
+    let mut failed = Vec::new();
+    if let Ok(entries) = fs::read_dir(trash.join("files")) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let result = if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+            match result {
+                Ok(()) => removed += 1,
+                Err(e) => failed.push(format!("{}: {}", entry.file_name().to_string_lossy(), e)),
+            }
+        }
+    }
+    if let Ok(entries) = fs::read_dir(trash.join("info")) {
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let payload = file_name
+                .strip_suffix(".trashinfo")
+                .map(|name| trash.join("files").join(name));
+            if payload.is_some_and(|p| fs::symlink_metadata(p).is_ok()) {
+                continue;
+            }
+            if let Err(e) = fs::remove_file(entry.path()) {
+                failed.push(format!("{}: {}", file_name, e));
+            }
+        }
+    }
+    // Cache from the spec's 1.0 revision; stale once the payloads are gone.
+    let _ = fs::remove_file(trash.join("directorysizes"));
+    if failed.is_empty() {
+        Ok(removed)
+    } else {
+        Err(io::Error::other(format!(
+            "{} items removed, {} could not be: {}",
+            removed,
+            failed.len(),
+            failed.join("; ")
+        )))
+    }
+}
+
+/// Parse the `Path` and `DeletionDate` keys of a `.trashinfo` file.
+fn parse_trash_info(contents: &str) -> Option<(PathBuf, String)> {
+    let mut lines = contents.lines().map(str::trim);
+    if lines.next()? != "[Trash Info]" {
+        return None;
+    }
+    let mut path = None;
+    let mut date = String::new();
+    for line in lines {
+        if let Some(v) = line.strip_prefix("Path=") {
+            path = Some(PathBuf::from(decode_path(v)?));
+        } else if let Some(v) = line.strip_prefix("DeletionDate=") {
+            if NaiveDateTime::parse_from_str(v, DELETION_DATE_FORMAT).is_ok() {
+                date = v.to_string();
+            }
+        } else if line.starts_with('[') {
+            break;
+        }
+    }
+    path.map(|p| (p, date))
+}
+
+/// Percent-encode a path as the spec requires (RFC 2396 escaping, `/` kept).
+fn encode_path(path: &Path) -> String {
+    let raw = path.to_string_lossy();
+    let mut out = String::with_capacity(raw.len());
+    for b in raw.bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.!~*'()".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+fn decode_path(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Run `f` with the home trash in a fresh directory, which is passed to
+    /// it. The trash is found through the environment, so tests that use
+    /// it take turns.
+    pub(crate) fn with_temp_trash<T>(f: impl FnOnce(&Path) -> T) -> T {
+        static LOCK: Mutex<()> = Mutex::new(());
+        let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let data_home = tempfile::tempdir().unwrap();
+        let previous = std::env::var_os("XDG_DATA_HOME");
+        // SAFETY: the lock keeps the tests that read this variable apart.
+        unsafe { std::env::set_var("XDG_DATA_HOME", data_home.path()) };
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            f(&data_home.path().join("Trash"))
+        }));
+        match previous {
+            Some(v) => unsafe { std::env::set_var("XDG_DATA_HOME", v) },
+            None => unsafe { std::env::remove_var("XDG_DATA_HOME") },
+        }
+        result.unwrap_or_else(|e| std::panic::resume_unwind(e))
+    }
+
+    #[test]
+    fn trash_round_trip() {
+        with_temp_trash(|trash| {
+            let tmp = tempfile::tempdir().unwrap();
+            let file = tmp.path().join("notes.txt");
+            let dir = tmp.path().join("docs");
+            fs::write(&file, "first").unwrap();
+            fs::create_dir(&dir).unwrap();
+            fs::write(dir.join("a.txt"), "a").unwrap();
+
+            let first = move_to_trash(&file).unwrap();
+            assert_eq!(first.name, "notes.txt");
+            fs::write(&file, "second").unwrap();
+            let second = move_to_trash(&file).unwrap();
+            assert_eq!(second.name, "notes.txt.2");
+            move_to_trash(&dir).unwrap();
+            assert!(!file.exists() && !dir.exists());
+            assert!(move_to_trash(&trash.join("files/notes.txt")).is_err());
+
+            let mut names: Vec<_> = list_trash().unwrap().into_iter().map(|i| i.name).collect();
+            names.sort();
+            assert_eq!(names, ["docs", "notes.txt", "notes.txt.2"]);
+            let item = list_trash().unwrap();
+            let item = item.iter().find(|i| i.name == "notes.txt").unwrap();
+            assert_eq!(item.original_path, std::path::absolute(&file).unwrap());
+            assert_eq!(item.size, 5);
+
+            assert_eq!(restore("notes.txt").unwrap(), item.original_path);
+            assert_eq!(fs::read_to_string(&file).unwrap(), "first");
+            // The original path is taken again.
+            let taken = restore("notes.txt.2").unwrap_err();
+            assert_eq!(taken.kind(), io::ErrorKind::AlreadyExists);
+            restore("docs").unwrap();
+            assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a");
+
+            assert_eq!(empty_trash().unwrap(), 1);
+            assert!(list_trash().unwrap().is_empty());
+            assert_eq!(fs::read_dir(trash.join("files")).unwrap().count(), 0);
+            assert_eq!(fs::read_dir(trash.join("info")).unwrap().count(), 0);
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn trashed_dangling_symlinks_keep_their_name() {
+        with_temp_trash(|trash| {
+            let tmp = tempfile::tempdir().unwrap();
+            let link = tmp.path().join("link");
+            std::os::unix::fs::symlink("missing", &link).unwrap();
+            assert_eq!(move_to_trash(&link).unwrap().name, "link");
+            // A payload whose info file is gone still holds on to its name.
+            fs::remove_file(trash.join("info/link.trashinfo")).unwrap();
+            std::os::unix::fs::symlink("other", &link).unwrap();
+            assert_eq!(move_to_trash(&link).unwrap().name, "link.2");
+            assert_eq!(
+                fs::read_link(trash.join("files/link")).unwrap(),
+                Path::new("missing")
+            );
+        });
+    }
+
+    #[test]
+    fn names_outside_the_trash_are_refused() {
+        for name in ["", ".", "..", "../../x", "a/b", "a\\b", "/etc/passwd"] {
+            assert!(restore(name).is_err_and(|e| e.kind() == io::ErrorKind::InvalidInput));
+        }
+        assert!(check_name("notes.txt.2").is_ok());
+        assert!(check_name("a..b").is_ok());
+    }
+}